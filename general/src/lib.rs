@@ -11,4 +11,12 @@ pub mod logging {
     pub fn init_logger_from_env() {
         env_logger::builder().format_timestamp(None).init();
     }
+
+    /// Like [init_logger_from_env], but shows messages up to `level` when `RUST_LOG` is not set
+    pub fn init_logger_from_env_or(level: LevelFilter) {
+        let env = env_logger::Env::default().default_filter_or(level.to_string());
+        env_logger::Builder::from_env(env)
+            .format_timestamp(None)
+            .init();
+    }
 }
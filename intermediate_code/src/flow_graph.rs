@@ -1,18 +1,16 @@
 use crate::{
     ic_info::{ICInfo, ICLineNumber},
     icode::IntermediateCode,
-    ioperand::IOperand,
-    ioperator::IOperator,
 };
 use id_arena::Arena;
 use itertools::Itertools;
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{HashMap, HashSet},
     fmt,
     iter::FromIterator,
 };
-use syntax::{SymbolId, SymbolTable};
+use syntax::{Linkage, SymbolId, SymbolTable};
 
 #[derive(Clone)]
 pub struct BasicBlock {
@@ -73,7 +71,8 @@ type Blockmap = HashMap<ICLineNumber, BasicBlockId>;
 
 pub struct FlowGraph {
     graph: Arena<BasicBlock>,
-    entry: BasicBlockId,
+    /// The first block of `main`, if this file has one
+    entry: Option<BasicBlockId>,
     reachable: HashSet<BasicBlockId>,
     block_map: HashMap<ICLineNumber, BasicBlockId>,
     liveness: Liveness,
@@ -81,10 +80,10 @@ pub struct FlowGraph {
 
 impl FlowGraph {
     pub fn new(table: &SymbolTable, icode: &IntermediateCode) -> Self {
-        let info = ICInfo::from(icode);
-        let (entry, graph, block_map) = FlowGraph::build_graph(icode, table, &info);
-        let reachable = FlowGraph::determine_reachable(entry, &graph);
-        let liveness = FlowGraph::compute_liveness(icode, table, &graph);
+        let info = ICInfo::new(icode, table);
+        let (entry, roots, graph, block_map) = FlowGraph::build_graph(icode, table, &info);
+        let reachable = FlowGraph::determine_reachable(roots, &graph);
+        let liveness = FlowGraph::compute_liveness(icode, table, &info);
         Self {
             graph,
             entry,
@@ -94,8 +93,8 @@ impl FlowGraph {
         }
     }
 
-    pub fn entry(&self) -> &BasicBlock {
-        self.graph.get(self.entry).unwrap()
+    pub fn entry(&self) -> Option<&BasicBlock> {
+        self.graph.get(self.entry?)
     }
 
     pub fn is_reachable(&self, line: &ICLineNumber) -> bool {
@@ -103,6 +102,10 @@ impl FlowGraph {
         self.reachable.contains(block)
     }
 
+    pub fn liveness(&self) -> &Liveness {
+        &self.liveness
+    }
+
     pub fn is_live_at(&self, line: &ICLineNumber, sym: &SymbolId) -> bool {
         match self.liveness.live_out.get(line) {
             Some(live) => live.contains(sym),
@@ -130,48 +133,56 @@ impl FlowGraph {
         }
     }
 
-    /// Returns a three-tuple of the entry to the graph, the graph itself and the blockmap
+    /// Returns a four-tuple of the entry to the graph, the blocks that can be entered from
+    /// outside of this file, the graph itself and the blockmap.
+    /// Besides `main`, every function that is not `static` can be called by another file.
     fn build_graph(
         icode: &IntermediateCode,
         table: &SymbolTable,
         info: &ICInfo,
-    ) -> (BasicBlockId, Arena<BasicBlock>, Blockmap) {
+    ) -> (
+        Option<BasicBlockId>,
+        Vec<BasicBlockId>,
+        Arena<BasicBlock>,
+        Blockmap,
+    ) {
         let mut graph = Arena::new();
         let mut iter = info.leaders.iter();
         let mut entry = None;
-        let mut prev_leader = iter.next().unwrap();
+        let mut roots = vec![];
         let mut leader_to_block = HashMap::new();
         let mut block_map = HashMap::new();
+        let mut prev_leader = match iter.next() {
+            Some(leader) => leader,
+            None => return (entry, roots, graph, block_map),
+        };
+        let mut add_block =
+            |graph: &mut Arena<BasicBlock>, start: ICLineNumber, end: ICLineNumber| {
+                let id = graph.alloc(BasicBlock::new(start, end));
+                for line in start.0..(end + 1).0 {
+                    block_map.insert(ICLineNumber(line), id);
+                }
+                leader_to_block.insert(start, id);
+                if let Some(func) = FlowGraph::func_at_line(&start, icode) {
+                    if Some(func) == table.get_main_id() {
+                        entry = Some(id);
+                        graph.get_mut(id).unwrap().is_entry = true;
+                    }
+                    if table.get_linkage(&func) == Some(Linkage::External) {
+                        roots.push(id);
+                    }
+                }
+            };
         for curr_leader in iter {
-            let block = BasicBlock::new(*prev_leader, *curr_leader - 1);
-            let id = graph.alloc(block);
-            for line in prev_leader.0..curr_leader.0 {
-                block_map.insert(ICLineNumber(line), id);
-            }
-            leader_to_block.insert(*prev_leader, id);
-            if FlowGraph::line_is_main(table, prev_leader, icode) {
-                entry = Some(id);
-                graph.get_mut(id).unwrap().is_entry = true;
-            }
+            add_block(&mut graph, *prev_leader, *curr_leader - 1);
             prev_leader = curr_leader;
         }
-        {
-            let last_line = (icode.n_statements() - 1).into();
-            let last = graph.alloc(BasicBlock::new(*prev_leader, last_line));
-            leader_to_block.insert(*prev_leader, last);
-            if FlowGraph::line_is_main(table, prev_leader, icode) {
-                entry = Some(last);
-                graph.get_mut(last).unwrap().is_entry = true;
-            }
-            for line in prev_leader.0..(last_line + 1).0 {
-                block_map.insert(ICLineNumber(line), last);
-            }
-        }
+        add_block(&mut graph, *prev_leader, (icode.n_statements() - 1).into());
         for block_id in leader_to_block.values() {
             let out;
             {
                 let block = graph.get_mut(*block_id).unwrap();
-                out = FlowGraph::get_outgoing_edges(block, icode, info, &leader_to_block);
+                out = FlowGraph::get_outgoing_edges(block, icode, table, info, &leader_to_block);
                 for b in out.iter() {
                     block.outgoing.push(*b);
                 }
@@ -182,103 +193,79 @@ impl FlowGraph {
             }
         }
         FlowGraph::add_calls(&mut graph, &leader_to_block, info);
-        (
-            entry.expect("No entry found in FlowGraph"),
-            graph,
-            block_map,
-        )
+        (entry, roots, graph, block_map)
     }
 
-    fn compute_liveness_block(
-        block: &BasicBlock,
-        icode: &IntermediateCode,
-        liveness: &mut Liveness,
-    ) {
-        let Liveness {
-            live_in,
-            live_out,
-            def,
-            used,
-        } = liveness;
-        let mut worklist: VecDeque<ICLineNumber> = icode
-            .get_statements(block.start, block.end)
-            .iter()
-            .zip(block.start.0..block.end.0)
-            .map(|(_, ln)| ICLineNumber(ln))
-            .rev()
-            .collect();
-
-        while let Some(ln) = worklist.pop_front() {
-            let succ_in = if ln.0 <= block.end.0 {
-                Some(live_in.get(&(ln + 1)).unwrap().clone())
-            } else {
-                None
-            };
-            let used = used.get_mut(&ln).unwrap();
-            let def = def.get_mut(&ln).unwrap();
-            let live_out = live_out.get_mut(&ln).unwrap();
-            let live_in = live_in.get_mut(&ln).unwrap();
-            let old_live_in = live_in.clone();
-            if let Some(successor) = succ_in {
-                *live_out = successor.clone();
-            }
-            *live_in = used
-                .union(&live_out.difference(def).copied().collect())
-                .copied()
-                .collect();
-            if old_live_in != *live_in {
-                for pred in (block.start.0..ln.0).rev() {
-                    worklist.push_back(ICLineNumber(pred));
+    /// Computes liveness per line by iterating until a fixed point is reached.
+    /// Liveness is intraprocedural: calls are assumed to return to the next line.
+    fn compute_liveness(icode: &IntermediateCode, table: &SymbolTable, info: &ICInfo) -> Liveness {
+        let mut liveness = Liveness::default();
+        let globals: HashSet<SymbolId> = table.get_globals().keys().copied().collect();
+        for (l, stmt) in icode {
+            liveness.def.insert(l, HashSet::from_iter(stmt.defined()));
+            liveness.used.insert(l, HashSet::from_iter(stmt.used()));
+            liveness.live_out.insert(l, globals.clone());
+            liveness.live_in.insert(l, HashSet::new());
+        }
+
+        let lines = icode.into_iter().map(|(l, _)| l).collect_vec();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for l in lines.iter().rev() {
+                let mut live_out = globals.clone();
+                for succ in FlowGraph::get_successors(*l, icode, info) {
+                    live_out.extend(liveness.live_in.get(&succ).unwrap());
                 }
+                let def = liveness.def.get(l).unwrap();
+                let mut live_in: HashSet<SymbolId> = live_out.difference(def).copied().collect();
+                live_in.extend(liveness.used.get(l).unwrap());
+                if live_in != *liveness.live_in.get(l).unwrap() {
+                    changed = true;
+                    liveness.live_in.insert(*l, live_in);
+                }
+                liveness.live_out.insert(*l, live_out);
             }
         }
+        liveness
     }
 
-    fn compute_liveness(
+    /// Get the lines that control can flow to from `line` within the same function
+    fn get_successors(
+        line: ICLineNumber,
         icode: &IntermediateCode,
-        table: &SymbolTable,
-        graph: &Arena<BasicBlock>,
-    ) -> Liveness {
-        let mut liveness = Liveness::default();
-        let globals = table.get_globals().keys().copied().collect_vec();
-        for (l, stmt) in icode {
-            let mut def = HashSet::new();
-            if stmt.operator == IOperator::Assign {
-                def.insert(stmt.ret_target.as_ref().unwrap().id());
-            }
-            liveness.def.insert(l, def);
-
-            let mut used = HashSet::new();
-            if let Some(IOperand::Symbol { id, .. }) = stmt.operand1.as_ref() {
-                used.insert(*id);
-            }
-            if let Some(IOperand::Symbol { id, .. }) = stmt.operand2.as_ref() {
-                used.insert(*id);
-            }
-            liveness.used.insert(l, used);
-            liveness
-                .live_out
-                .insert(l, HashSet::from_iter(globals.clone()));
-            liveness.live_in.insert(l, HashSet::new());
+        info: &ICInfo,
+    ) -> Vec<ICLineNumber> {
+        let stmt = icode.get_statement(line);
+        let mut successors = vec![];
+        if stmt.is_return() {
+            return successors;
         }
-
-        for (_, block) in graph.iter() {
-            FlowGraph::compute_liveness_block(block, icode, &mut liveness);
+        if stmt.is_jump() {
+            successors.push(*info.labels.get(&stmt.label_id()).unwrap());
         }
-        liveness
+        let next = line + 1;
+        if !stmt.is_unconditional_jump()
+            && next.0 <= icode.n_statements()
+            && !icode.get_statement(next).is_func()
+        {
+            successors.push(next);
+        }
+        successors
     }
 
     fn determine_reachable(
-        entry: BasicBlockId,
+        roots: Vec<BasicBlockId>,
         graph: &Arena<BasicBlock>,
     ) -> HashSet<BasicBlockId> {
-        graph_walk(graph, entry).collect()
+        graph_walk(graph, roots).collect()
     }
 
     /// Get the [BasicBlock] ids for all the outgoing edges of the given basic block except for calls
     fn get_outgoing_edges(
         block: &BasicBlock,
         icode: &IntermediateCode,
+        table: &SymbolTable,
         info: &ICInfo,
         leaders: &HashMap<ICLineNumber, BasicBlockId>,
     ) -> Vec<BasicBlockId> {
@@ -297,11 +284,13 @@ impl FlowGraph {
             let leader_else = info.labels.get(&label).unwrap();
             let block = leaders.get(leader_else).unwrap();
             out.push(*block);
-        } else if last_stmt.is_non_builtin_call() {
+        } else if last_stmt.is_non_builtin_call(table) {
             let func_id = last_stmt.label_id();
-            let line = info.funcs.get(&func_id).unwrap();
-            let block = leaders.get(line).unwrap();
-            out.push(*block);
+            match info.funcs.get(&func_id) {
+                Some(line) => out.push(*leaders.get(line).unwrap()),
+                // An `extern` function has no code in this file, so it returns right after the call
+                None => out.push(*leaders.get(&(block.end + 1)).unwrap()),
+            }
         } else if last_stmt.is_return() {
             // Function doesn't know where it will return to. This is handled in [FlowGraph::add_calls].
         } else if let Some(next_block_id) = leaders.get(&(block.end + 1)) {
@@ -315,13 +304,14 @@ impl FlowGraph {
         out
     }
 
-    fn line_is_main(table: &SymbolTable, line: &ICLineNumber, icode: &IntermediateCode) -> bool {
+    /// The function that starts at `line`, if any
+    fn func_at_line(line: &ICLineNumber, icode: &IntermediateCode) -> Option<SymbolId> {
         let stmt = icode.get_statement(*line);
-        if !stmt.is_func() {
-            return false;
+        if stmt.is_func() {
+            Some(stmt.label_id())
+        } else {
+            None
         }
-        let sym_id = icode.get_statement(*line).label_id();
-        sym_id == table.get_main_id()
     }
 
     fn add_calls(
@@ -349,10 +339,10 @@ impl FlowGraph {
     }
 }
 
-fn graph_walk(graph: &Arena<BasicBlock>, entry: BasicBlockId) -> GraphIter {
+fn graph_walk(graph: &Arena<BasicBlock>, roots: Vec<BasicBlockId>) -> GraphIter<'_> {
     GraphIter {
         graph,
-        to_visit: vec![entry],
+        to_visit: roots,
         visited: HashSet::new(),
     }
 }
@@ -386,11 +376,11 @@ type Vertex<'a> = (BasicBlockId, &'a BasicBlock);
 type Edge = (BasicBlockId, BasicBlockId);
 
 impl<'a> dot::Labeller<'a, Vertex<'a>, Edge> for &FlowGraph {
-    fn graph_id(&'a self) -> dot::Id {
+    fn graph_id(&'a self) -> dot::Id<'a> {
         dot::Id::new("Control_Flow_Graph").unwrap()
     }
 
-    fn node_id(&'a self, n: &Vertex) -> dot::Id {
+    fn node_id(&'a self, n: &Vertex) -> dot::Id<'a> {
         let (_, node) = n;
         dot::Id::new(node.to_string()).unwrap()
     }
@@ -405,7 +395,7 @@ impl<'a> dot::Labeller<'a, Vertex<'a>, Edge> for &FlowGraph {
 }
 
 impl<'a> dot::GraphWalk<'a, Vertex<'a>, Edge> for &FlowGraph {
-    fn nodes(&'a self) -> dot::Nodes<'a, Vertex> {
+    fn nodes(&'a self) -> dot::Nodes<'a, Vertex<'a>> {
         let v: Vec<Vertex> = self.graph.iter().collect();
         Cow::Owned(v)
     }
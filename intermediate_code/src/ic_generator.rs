@@ -5,7 +5,7 @@ use crate::{
 use std::fmt;
 use syntax::{SymbolTable, SyntaxTree};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum OptLevel {
     None,
     Pre,
@@ -45,11 +45,11 @@ pub fn generate(
 
     let func_ids = table.get_function_ids();
     let mut visitor = IVisitor::new(table);
+    // `extern` functions have no root, their code is in another file
     for id in func_ids {
-        let func = tree
-            .get_root(&id)
-            .unwrap_or_else(|| panic!("Function with id {} not found", id));
-        visitor.visit_function(func, id);
+        if let Some(func) = tree.get_root(&id) {
+            visitor.visit_function(func, id);
+        }
     }
     let mut icode = visitor.result();
     let mut graph = FlowGraph::new(table, &icode);
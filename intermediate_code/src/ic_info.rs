@@ -3,7 +3,7 @@ use std::{
     fmt,
     ops::{Add, Sub},
 };
-use syntax::SymbolId;
+use syntax::{SymbolId, SymbolTable};
 #[derive(Default, Debug)]
 pub struct ICInfo {
     pub leaders: BTreeSet<ICLineNumber>,
@@ -27,10 +27,8 @@ impl ICInfo {
             .and_modify(|vec| vec.push(line))
             .or_insert_with(|| vec![line]);
     }
-}
 
-impl From<&IntermediateCode> for ICInfo {
-    fn from(icode: &IntermediateCode) -> Self {
+    pub fn new(icode: &IntermediateCode, table: &SymbolTable) -> Self {
         let mut info = Self {
             ..Default::default()
         };
@@ -46,7 +44,7 @@ impl From<&IntermediateCode> for ICInfo {
                 let id = stmt.label_id();
                 info.funcs.insert(id, line);
                 current_func = Some(id);
-            } else if stmt.is_non_builtin_call() {
+            } else if stmt.is_non_builtin_call(table) {
                 let id = stmt.label_id();
                 info.add_call(id, line);
                 if statements.peek().is_some() {
@@ -63,7 +63,7 @@ impl IntermediateCode {
 impl fmt::Display for IntermediateCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (line, statement) in self.into_iter() {
-            writeln!(f, "{:<3} {}", format!("{}", line), format!("{}", statement))?;
+            writeln!(f, "{:<3} {}", format!("{}", line), statement)?;
         }
         Ok(())
     }
@@ -0,0 +1,583 @@
+use crate::error::ICodeError;
+use crate::ic_info::{ICInfo, ICLineNumber};
+use crate::icode::IntermediateCode;
+use crate::ioperand::IOperand;
+use crate::ioperator::{IOperator, IOperatorSize};
+use crate::istatement::IStatement;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use syntax::{BuiltinCall, ConstantNodeValue, ReturnType, SymbolId, SymbolTable, SymbolType};
+
+pub use syntax::ProgramIO;
+
+/// Deepest nesting of calls before the interpreted program is considered to overflow its stack.
+/// Frames live on the heap, so this only guards against runaway recursion.
+const MAX_CALL_DEPTH: usize = 1_000_000;
+
+/// [ProgramIO] over a reader and a writer, such as stdin and stdout.
+/// Like `scanf`, reading a number leaves the byte after it for the next `readchar`.
+pub struct StreamIO<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    buffer: VecDeque<u8>,
+}
+
+impl<R: BufRead, W: Write> StreamIO<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// The next byte of input without consuming it, or [None] at the end of the input
+    fn peek(&mut self) -> Result<Option<u8>, String> {
+        if self.buffer.is_empty() {
+            let mut line = vec![];
+            self.input
+                .read_until(b'\n', &mut line)
+                .map_err(|e| format!("Could not read input: {}", e))?;
+            self.buffer.extend(line);
+        }
+        Ok(self.buffer.front().copied())
+    }
+
+    /// Consumes whitespace up to the next byte that is not whitespace
+    fn skip_whitespace(&mut self) -> Result<(), String> {
+        while self.peek()?.is_some_and(|c| c.is_ascii_whitespace()) {
+            self.buffer.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Consumes the next byte if it is one of `bytes`
+    fn take_one_of(&mut self, bytes: &[u8], taken: &mut Vec<u8>) -> Result<bool, String> {
+        match self.peek()? {
+            Some(c) if bytes.contains(&c) => {
+                taken.push(c);
+                self.buffer.pop_front();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Consumes a run of decimal digits and returns how many there were
+    fn take_digits(&mut self, taken: &mut Vec<u8>) -> Result<usize, String> {
+        let mut count = 0;
+        while self.take_one_of(b"0123456789", taken)? {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<R: BufRead, W: Write> ProgramIO for StreamIO<R, W> {
+    /// Like `scanf`, a missing or malformed number reads as 0 and leaves the rest of the input.
+    /// Integers wrap around, so values above `i64::MAX` keep their bits in a `uint64_t`.
+    fn read_integer(&mut self) -> Result<i64, String> {
+        self.skip_whitespace()?;
+        let mut taken = vec![];
+        self.take_one_of(b"+-", &mut taken)?;
+        self.take_digits(&mut taken)?;
+        let magnitude = taken
+            .iter()
+            .filter(|c| c.is_ascii_digit())
+            .fold(0i64, |n, c| {
+                n.wrapping_mul(10).wrapping_add((c - b'0') as i64)
+            });
+        Ok(if taken.first() == Some(&b'-') {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        })
+    }
+
+    fn read_real(&mut self) -> Result<f64, String> {
+        self.skip_whitespace()?;
+        let mut taken = vec![];
+        self.take_one_of(b"+-", &mut taken)?;
+        self.take_digits(&mut taken)?;
+        if self.take_one_of(b".", &mut taken)? {
+            self.take_digits(&mut taken)?;
+        }
+        if self.take_one_of(b"eE", &mut taken)? {
+            self.take_one_of(b"+-", &mut taken)?;
+            self.take_digits(&mut taken)?;
+        }
+        Ok(String::from_utf8_lossy(&taken).parse().unwrap_or(0.0))
+    }
+
+    fn read_char(&mut self) -> Result<Option<u8>, String> {
+        let c = self.peek()?;
+        self.buffer.pop_front();
+        Ok(c)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.output, "{}", line).map_err(|e| format!("Could not write output: {}", e))?;
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.output
+            .write_all(bytes)
+            .map_err(|e| format!("Could not write output: {}", e))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Value {
+    /// Reals are stored as the bits of their `f64`
+    Scalar(i64),
+    /// Arrays are passed by reference, so callers and callees share the elements
+    Array(Rc<RefCell<Vec<i64>>>),
+}
+
+impl Value {
+    fn new_array(size: usize) -> Self {
+        Self::Array(Rc::new(RefCell::new(vec![0; size])))
+    }
+}
+
+struct Frame {
+    func: SymbolId,
+    locals: HashMap<SymbolId, Value>,
+    /// The call to continue after when this frame returns
+    call_line: Option<ICLineNumber>,
+}
+
+/// Executes intermediate code directly, as a reference for the semantics of a program.
+/// Every value wraps around to the range of its [ReturnType], like it does on the target.
+pub struct Interpreter<'a> {
+    icode: &'a IntermediateCode,
+    table: &'a SymbolTable,
+    io: &'a mut dyn ProgramIO,
+    info: ICInfo,
+    globals: HashMap<SymbolId, Value>,
+    frames: Vec<Frame>,
+    params: Vec<Value>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(
+        icode: &'a IntermediateCode,
+        table: &'a SymbolTable,
+        io: &'a mut dyn ProgramIO,
+    ) -> Self {
+        let globals = table
+            .get_globals()
+            .into_iter()
+            .map(|(id, symbol)| {
+                let mut value = Self::initial_value(symbol.symbol_type);
+                match (&mut value, table.get_initializer(&id)) {
+                    (Value::Array(elements), Some(init)) => {
+                        for (elem, init) in elements.borrow_mut().iter_mut().zip(init) {
+                            *elem = to_scalar(*init);
+                        }
+                    }
+                    (Value::Scalar(scalar), Some(init)) => *scalar = to_scalar(init[0]),
+                    _ => {}
+                }
+                (id, value)
+            })
+            .collect();
+        Self {
+            icode,
+            table,
+            io,
+            info: ICInfo::new(icode, table),
+            globals,
+            frames: vec![],
+            params: vec![],
+        }
+    }
+
+    /// Runs the program from `main` and returns the value `main` returns, or 0 if it returns nothing
+    pub fn run(&mut self) -> Result<i64, ICodeError> {
+        let main = self
+            .table
+            .get_main_id()
+            .ok_or_else(|| ICodeError::from("Cannot run a program without `main`"))?;
+        let mut line = self.enter_function(main, None)?;
+        loop {
+            let statement = self.icode.get_statement(line);
+            log::trace!("Interpreting {}: {}", line, statement);
+            let next = if statement.is_return() {
+                let value = match &statement.operand1 {
+                    Some(op) => Some(self.read_scalar(op)?),
+                    None => None,
+                };
+                let frame = self.frames.pop().unwrap();
+                match frame.call_line {
+                    None => return Ok(value.unwrap_or(0)),
+                    Some(call_line) => {
+                        let call = self.icode.get_statement(call_line);
+                        if let (Some(value), Some(target)) = (value, &call.ret_target) {
+                            self.write_scalar(target, value);
+                        }
+                        call_line + 1
+                    }
+                }
+            } else {
+                self.execute(statement, line)?
+            };
+            line = next;
+        }
+    }
+
+    /// Executes a single statement and returns the line to continue at
+    fn execute(
+        &mut self,
+        statement: &IStatement,
+        line: ICLineNumber,
+    ) -> Result<ICLineNumber, ICodeError> {
+        match statement.operator {
+            IOperator::Func | IOperator::Label => {}
+            IOperator::Goto => return Ok(self.info.labels[&statement.label_id()]),
+            IOperator::Param => {
+                let value = self.read(statement.operand1.as_ref().unwrap())?;
+                self.params.push(value);
+            }
+            IOperator::FuncCall => {
+                let func = statement.label_id();
+                if self.table.is_builtin(&func) {
+                    self.call_builtin(statement)?;
+                } else {
+                    return self.enter_function(func, Some(line));
+                }
+            }
+            IOperator::Coerce => {
+                let (from, target) = (
+                    statement.operand1.as_ref().unwrap(),
+                    statement.ret_target.as_ref().unwrap(),
+                );
+                let value = self.read_scalar(from)?;
+                let value = match (from.ret_type(), target.ret_type()) {
+                    (ReturnType::Real, ReturnType::Real) => value,
+                    (ReturnType::Real, ReturnType::Bool) => {
+                        (f64::from_bits(value as u64) != 0.0) as i64
+                    }
+                    (ReturnType::Real, ReturnType::Uint64) => {
+                        f64::from_bits(value as u64) as u64 as i64
+                    }
+                    (ReturnType::Real, _) => f64::from_bits(value as u64) as i64,
+                    (ReturnType::Uint64, ReturnType::Real) => {
+                        (value as u64 as f64).to_bits() as i64
+                    }
+                    (_, ReturnType::Real) => (value as f64).to_bits() as i64,
+                    _ => value,
+                };
+                self.write_scalar(target, value);
+            }
+            IOperator::Assign => {
+                let value = match (&statement.operand1, &statement.operand2) {
+                    (Some(op), _) | (None, Some(op)) => self.read_scalar(op)?,
+                    (None, None) => unreachable!("{} without a value", statement.operator),
+                };
+                self.write_scalar(statement.ret_target.as_ref().unwrap(), value);
+            }
+            IOperator::Array => {
+                let (array, index, target) = statement.get_triple();
+                let value = self.read_element(array, index)?;
+                self.write_scalar(target, value);
+            }
+            IOperator::ArrayAssign => {
+                let (index, value, array) = statement.get_triple();
+                let value = wrap(self.read_scalar(value)?, array.ret_type());
+                let elements = self.read_array(array)?;
+                let index = self.read_index(&elements, index)?;
+                elements.borrow_mut()[index] = value;
+            }
+            IOperator::Not | IOperator::Minus | IOperator::BitNot => {
+                let operand = statement.operand1.as_ref().unwrap();
+                let value = self.read_scalar(operand)?;
+                let result = match statement.operator {
+                    IOperator::Not => (value == 0) as i64,
+                    // Negating a real flips its sign bit
+                    IOperator::Minus if operand.ret_type() == ReturnType::Real => value ^ i64::MIN,
+                    IOperator::BitNot => !value,
+                    _ => value.wrapping_neg(),
+                };
+                self.write_scalar(statement.ret_target.as_ref().unwrap(), result);
+            }
+            ref op if statement.is_conditional_jump() => {
+                let (l, r) = (
+                    statement.operand1.as_ref().unwrap(),
+                    statement.operand2.as_ref().unwrap(),
+                );
+                let is_real = l.ret_type() == ReturnType::Real;
+                let (l, r) = (self.read_scalar(l)?, self.read_scalar(r)?);
+                let taken = if is_real {
+                    compare_reals(op, f64::from_bits(l as u64), f64::from_bits(r as u64))
+                } else {
+                    compare(op, l, r)
+                };
+                if taken {
+                    return Ok(self.info.labels[&statement.label_id()]);
+                }
+            }
+            ref op => {
+                let (l, r, target) = statement.get_triple();
+                let is_real = l.ret_type() == ReturnType::Real;
+                let shift_mask = match IOperatorSize::from(l.ret_type()) {
+                    IOperatorSize::Quad => 63,
+                    _ => 31,
+                };
+                let (l, r) = (self.read_scalar(l)?, self.read_scalar(r)?);
+                let result = match op {
+                    _ if is_real => real_binop(op, l, r),
+                    IOperator::Add => l.wrapping_add(r),
+                    IOperator::Sub => l.wrapping_sub(r),
+                    IOperator::Mul => l.wrapping_mul(r),
+                    IOperator::Div | IOperator::IDiv | IOperator::Mod | IOperator::Imod
+                        if r == 0 =>
+                    {
+                        return Err(format!("Division by zero on line {}", line).into())
+                    }
+                    IOperator::IDiv => l.wrapping_div(r),
+                    IOperator::Imod => l.wrapping_rem(r),
+                    IOperator::Div => (l as u64 / r as u64) as i64,
+                    IOperator::Mod => (l as u64 % r as u64) as i64,
+                    IOperator::And => (l != 0 && r != 0) as i64,
+                    IOperator::Or => (l != 0 || r != 0) as i64,
+                    IOperator::BitAnd => l & r,
+                    IOperator::BitOr => l | r,
+                    IOperator::BitXor => l ^ r,
+                    // Like x86, only the lower 5 bits of the shift count are used, or 6 for quads
+                    IOperator::Shl => l.wrapping_shl(r as u32 & shift_mask),
+                    IOperator::Sar => l >> (r as u32 & shift_mask),
+                    IOperator::Shr => ((l as u64) >> (r as u32 & shift_mask)) as i64,
+                    IOperator::SetE => (l == r) as i64,
+                    IOperator::SetNE => (l != r) as i64,
+                    IOperator::SetG => (l > r) as i64,
+                    IOperator::SetGE => (l >= r) as i64,
+                    IOperator::SetL => (l < r) as i64,
+                    IOperator::SetLE => (l <= r) as i64,
+                    IOperator::SetA => (l as u64 > r as u64) as i64,
+                    IOperator::SetAE => (l as u64 >= r as u64) as i64,
+                    IOperator::SetB => ((l as u64) < r as u64) as i64,
+                    IOperator::SetBE => (l as u64 <= r as u64) as i64,
+                    _ => unreachable!("Cannot interpret {}", statement),
+                };
+                self.write_scalar(target, result);
+            }
+        }
+        Ok(line + 1)
+    }
+
+    /// Pushes a frame for `func` with the pending params, and returns its first line
+    fn enter_function(
+        &mut self,
+        func: SymbolId,
+        call_line: Option<ICLineNumber>,
+    ) -> Result<ICLineNumber, ICodeError> {
+        let first_line = match self.info.funcs.get(&func) {
+            Some(line) => *line,
+            None => {
+                let name = &self.table.get_symbol(&func).unwrap().name;
+                return Err(
+                    format!("Cannot call `{}`, it is defined in another file", name).into(),
+                );
+            }
+        };
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(
+                format!("Stack overflow: more than {} nested calls", MAX_CALL_DEPTH).into(),
+            );
+        }
+        let mut locals: HashMap<SymbolId, Value> = self
+            .table
+            .get_func_var_symbols(&func)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, symbol)| (id, Self::initial_value(symbol.symbol_type)))
+            .collect();
+        let param_ids = self.table.get_func_param_ids(&func).cloned();
+        let params = std::mem::take(&mut self.params);
+        for (id, value) in param_ids.unwrap_or_default().into_iter().zip(params) {
+            locals.insert(id, value);
+        }
+        self.frames.push(Frame {
+            func,
+            locals,
+            call_line,
+        });
+        Ok(first_line)
+    }
+
+    fn call_builtin(&mut self, statement: &IStatement) -> Result<(), ICodeError> {
+        let builtin = self.table.get_builtin(&statement.label_id()).unwrap();
+        let interpret = builtin
+            .interpret
+            .ok_or_else(|| format!("Builtin `{}` cannot be interpreted", builtin.name))?;
+        let args = std::mem::take(&mut self.params)
+            .into_iter()
+            .zip(builtin.params.iter())
+            .map(|(value, (_, ret_type))| match value {
+                Value::Scalar(value) => wrap(value, *ret_type),
+                Value::Array(_) => unreachable!("Builtin called with an array argument"),
+            })
+            .collect::<Vec<_>>();
+        let value = interpret(&mut BuiltinCall {
+            args: &args,
+            strings: self.table.get_strings(),
+            io: &mut *self.io,
+        })?;
+        if let (Some(value), Some(target)) = (value, &statement.ret_target) {
+            self.write_scalar(target, wrap(value, builtin.return_type));
+        }
+        Ok(())
+    }
+
+    fn initial_value(symbol_type: SymbolType) -> Value {
+        match symbol_type {
+            SymbolType::ArrayVariable { size } => Value::new_array(size),
+            _ => Value::Scalar(0),
+        }
+    }
+
+    fn read(&self, operand: &IOperand) -> Result<Value, ICodeError> {
+        match operand {
+            IOperand::Immediate { value, ret_type } => {
+                Ok(Value::Scalar(wrap(to_scalar(*value), *ret_type)))
+            }
+            IOperand::Symbol { id, .. } => {
+                let locals = &self.frames.last().unwrap().locals;
+                Ok(locals
+                    .get(id)
+                    .or_else(|| self.globals.get(id))
+                    .cloned()
+                    .unwrap_or(Value::Scalar(0)))
+            }
+            IOperand::String { index } => Ok(Value::Scalar(*index as i64)),
+            IOperand::Unknown => Err("Cannot interpret an unknown operand".into()),
+        }
+    }
+
+    fn read_scalar(&self, operand: &IOperand) -> Result<i64, ICodeError> {
+        match self.read(operand)? {
+            Value::Scalar(value) => Ok(value),
+            Value::Array(_) => Err(format!("Expected a scalar, got array {}", operand).into()),
+        }
+    }
+
+    fn read_array(&self, operand: &IOperand) -> Result<Rc<RefCell<Vec<i64>>>, ICodeError> {
+        match self.read(operand)? {
+            Value::Array(elements) => Ok(elements),
+            Value::Scalar(_) => Err(format!("Expected an array, got scalar {}", operand).into()),
+        }
+    }
+
+    fn read_index(
+        &self,
+        elements: &Rc<RefCell<Vec<i64>>>,
+        index: &IOperand,
+    ) -> Result<usize, ICodeError> {
+        let index = self.read_scalar(index)?;
+        let len = elements.borrow().len();
+        if index < 0 || index as usize >= len {
+            let func = self.frames.last().unwrap().func;
+            return Err(format!(
+                "Index {} out of bounds for an array of size {} in `{}`",
+                index,
+                len,
+                self.table.get_symbol(&func).unwrap().name
+            )
+            .into());
+        }
+        Ok(index as usize)
+    }
+
+    fn read_element(&self, array: &IOperand, index: &IOperand) -> Result<i64, ICodeError> {
+        let elements = self.read_array(array)?;
+        let index = self.read_index(&elements, index)?;
+        let value = elements.borrow()[index];
+        Ok(value)
+    }
+
+    fn write_scalar(&mut self, target: &IOperand, value: i64) {
+        let id = target.id();
+        let value = Value::Scalar(wrap(value, target.ret_type()));
+        match self.globals.get_mut(&id) {
+            Some(global) => *global = value,
+            None => {
+                self.frames.last_mut().unwrap().locals.insert(id, value);
+            }
+        }
+    }
+}
+
+/// Wraps `value` around to the range of `ret_type`
+fn wrap(value: i64, ret_type: ReturnType) -> i64 {
+    match ret_type.to_base_type() {
+        ReturnType::Int => value as i32 as i64,
+        ReturnType::Uint => value as u32 as i64,
+        ReturnType::Int8 => value as i8 as i64,
+        ReturnType::Uint8 => value as u8 as i64,
+        ReturnType::Int16 => value as i16 as i64,
+        ReturnType::Uint16 => value as u16 as i64,
+        ReturnType::Bool => (value != 0) as i64,
+        _ => value,
+    }
+}
+
+/// The scalar that holds a constant, which are the bits of the `f64` for reals
+fn to_scalar(value: ConstantNodeValue) -> i64 {
+    match value {
+        ConstantNodeValue::Real(v) => v.to_bits() as i64,
+        _ => i64::from(value),
+    }
+}
+
+/// Applies a binary operator to the bits of two reals. Comparisons give a bool.
+fn real_binop(op: &IOperator, l: i64, r: i64) -> i64 {
+    let (l, r) = (f64::from_bits(l as u64), f64::from_bits(r as u64));
+    let result = match op {
+        IOperator::FAdd => l + r,
+        IOperator::FSub => l - r,
+        IOperator::FMul => l * r,
+        IOperator::FDiv => l / r,
+        op => return compare_reals(&op.clone().to_jump(), l, r) as i64,
+    };
+    result.to_bits() as i64
+}
+
+/// Whether a conditional jump on reals is taken. Like after `ucomisd`, `Jb` and `Jbe` negate
+/// `Jae` and `Ja`, so they hold if either operand is NaN, as does `!=`.
+fn compare_reals(op: &IOperator, l: f64, r: f64) -> bool {
+    let unordered = l.is_nan() || r.is_nan();
+    match op {
+        IOperator::Je => l == r,
+        IOperator::Jne => l != r,
+        IOperator::Ja => l > r,
+        IOperator::Jae => l >= r,
+        IOperator::Jb => l < r || unordered,
+        IOperator::Jbe => l <= r || unordered,
+        _ => unreachable!("{} is not a conditional jump on reals", op),
+    }
+}
+
+/// Whether a conditional jump is taken. Unsigned operands are compared as `u64`, as the bits of
+/// a `uint64_t` above `i64::MAX` are a negative `i64`.
+fn compare(op: &IOperator, l: i64, r: i64) -> bool {
+    match op {
+        IOperator::Je => l == r,
+        IOperator::Jne => l != r,
+        IOperator::Jg => l > r,
+        IOperator::Jge => l >= r,
+        IOperator::Jl => l < r,
+        IOperator::Jle => l <= r,
+        IOperator::Ja => l as u64 > r as u64,
+        IOperator::Jae => l as u64 >= r as u64,
+        IOperator::Jb => (l as u64) < r as u64,
+        IOperator::Jbe => l as u64 <= r as u64,
+        IOperator::Jnz => l != 0,
+        IOperator::Jz => l == 0,
+        _ => unreachable!("{} is not a conditional jump", op),
+    }
+}
@@ -13,6 +13,10 @@ pub enum IOperand {
         id: SymbolId,
         ret_type: ReturnType,
     },
+    /// A string literal, by its index in the symbol table
+    String {
+        index: usize,
+    },
 }
 
 impl IOperand {
@@ -26,6 +30,7 @@ impl IOperand {
     pub fn ret_type(&self) -> ReturnType {
         match self {
             IOperand::Immediate { ret_type, .. } | IOperand::Symbol { ret_type, .. } => *ret_type,
+            IOperand::String { .. } => ReturnType::String,
             _ => panic!("ret_type called on unknown operand"),
         }
     }
@@ -50,6 +55,7 @@ impl fmt::Display for IOperand {
             Self::Unknown => write!(f, "unknown"),
             Self::Immediate { ret_type, value } => write!(f, "imm:{} {}", ret_type, value),
             Self::Symbol { id, .. } => write!(f, "sym:{}", id),
+            Self::String { index } => write!(f, "str:{}", index),
         }
     }
 }
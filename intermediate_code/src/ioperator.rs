@@ -34,12 +34,21 @@ impl From<ReturnType> for IOperatorSize {
         match rt {
             ReturnType::Void => Self::Void,
             ReturnType::Bool | ReturnType::Uint8 | ReturnType::Int8 => Self::Byte,
+            ReturnType::Uint16 | ReturnType::Int16 => Self::Word,
             ReturnType::Uint | ReturnType::Int => Self::Double,
             ReturnType::Real
+            | ReturnType::Uint64
+            | ReturnType::Int64
+            | ReturnType::RealArray
+            | ReturnType::Int16Array
+            | ReturnType::Uint16Array
+            | ReturnType::Int64Array
+            | ReturnType::Uint64Array
             | ReturnType::Int8Array
             | ReturnType::Uint8Array
             | ReturnType::UintArray
-            | ReturnType::IntArray => Self::Quad,
+            | ReturnType::IntArray
+            | ReturnType::String => Self::Quad,
             _ => unreachable!("Cannot convert {} to IOperatorType", rt),
         }
     }
@@ -52,6 +61,11 @@ impl From<ConstantNodeValue> for IOperatorSize {
             ConstantNodeValue::Int(_) => IOperatorSize::Word,
             ConstantNodeValue::Uint(_) => IOperatorSize::Word,
             ConstantNodeValue::Uint8(_) => IOperatorSize::Byte,
+            ConstantNodeValue::Int16(_) => IOperatorSize::Word,
+            ConstantNodeValue::Uint16(_) => IOperatorSize::Word,
+            ConstantNodeValue::Int64(_) => IOperatorSize::Quad,
+            ConstantNodeValue::Uint64(_) => IOperatorSize::Quad,
+            ConstantNodeValue::Real(_) => IOperatorSize::Quad,
         }
     }
 }
@@ -70,9 +84,7 @@ impl From<IOperatorSize> for usize {
 
 impl PartialOrd for IOperatorSize {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let x: usize = (*self).into();
-        let y: usize = (*other).into();
-        x.partial_cmp(&y)
+        Some(self.cmp(other))
     }
 }
 
@@ -94,7 +106,8 @@ pub enum IOperator {
     Label,
     Goto,
     Assign,
-    Array,
+    Array,       // Indexed load
+    ArrayAssign, // Indexed store
 
     // Branching
     Je,  // ==
@@ -133,9 +146,24 @@ pub enum IOperator {
     And,  // AND operation
     Or,   // OR operation
 
+    // Binary bitwise operators
+    BitAnd, // &
+    BitOr,  // |
+    BitXor, // ^
+    Shl,    // <<
+    Sar,    // >> signed, shifts in the sign bit
+    Shr,    // >> unsigned, shifts in zeroes
+
+    // Binary real operators
+    FAdd, // Real addition
+    FSub, // Real subtraction
+    FMul, // Real multiplication
+    FDiv, // Real division
+
     // Unary arithmetic operators
-    Not,   // !
-    Minus, // -
+    Not,    // !
+    Minus,  // -
+    BitNot, // ~
 
     // Coercion
     Coerce,
@@ -156,6 +184,7 @@ impl fmt::Display for IOperator {
                 Self::Goto => "GOTO",
                 Self::Assign => "ASSIGN",
                 Self::Array => "ARRAY",
+                Self::ArrayAssign => "ARRAY_ASSIGN",
                 Self::Je => "JUMP_EQUAL",
                 Self::Jne => "JUMP_NOT_EQUAL",
                 Self::Jb => "JUMP_BELOW",
@@ -187,8 +216,19 @@ impl fmt::Display for IOperator {
                 Self::Imod => "SIGNED_MOD",
                 Self::And => "AND",
                 Self::Or => "OR",
+                Self::BitAnd => "BIT_AND",
+                Self::BitOr => "BIT_OR",
+                Self::BitXor => "BIT_XOR",
+                Self::Shl => "SHIFT_LEFT",
+                Self::Sar => "SHIFT_RIGHT",
+                Self::Shr => "UNSIGNED_SHIFT_RIGHT",
+                Self::FAdd => "REAL_ADD",
+                Self::FSub => "REAL_SUB",
+                Self::FMul => "REAL_MUL",
+                Self::FDiv => "REAL_DIV",
                 Self::Not => "NOT",
                 Self::Minus => "UNARY_MINUS",
+                Self::BitNot => "BIT_NOT",
                 Self::Coerce => "COERCE",
             }
         )
@@ -201,7 +241,7 @@ impl From<NodeType> for IOperator {
             NodeType::Add => Self::Add,
             NodeType::Sub => Self::Sub,
             NodeType::Mul => Self::Mul,
-            NodeType::Div => Self::Div,
+            NodeType::Div => Self::IDiv,
             NodeType::Assignment => Self::Assign,
             NodeType::RelEqual => Self::SetE,
             NodeType::RelNotEqual => Self::SetNE,
@@ -212,7 +252,12 @@ impl From<NodeType> for IOperator {
             NodeType::Or => Self::Or,
             NodeType::And => Self::And,
             NodeType::Unknown => Self::Unknown,
-            NodeType::Mod => Self::Mod,
+            NodeType::Mod => Self::Imod,
+            NodeType::BitAnd => Self::BitAnd,
+            NodeType::BitOr => Self::BitOr,
+            NodeType::BitXor => Self::BitXor,
+            NodeType::ShiftLeft => Self::Shl,
+            NodeType::ShiftRight => Self::Sar,
             _ => unreachable!("Cannot convert {} to IOperator", node_type),
         }
     }
@@ -225,10 +270,37 @@ impl IOperator {
             Self::SetGE => Self::SetAE,
             Self::SetL => Self::SetB,
             Self::SetLE => Self::SetBE,
+            Self::IDiv => Self::Div,
+            Self::Imod => Self::Mod,
+            Self::Sar => Self::Shr,
             _ => self,
         }
     }
 
+    /// Convert an operator to the variant for operands of a real type.
+    /// Reals are compared like unsigned integers, as `ucomisd` sets the carry flag for `<`.
+    /// Their `<` and `<=` also hold for NaN, which is why the IR swaps them to `>` and `>=`.
+    pub fn to_real(self) -> Self {
+        match self {
+            Self::Add => Self::FAdd,
+            Self::Sub => Self::FSub,
+            Self::Mul => Self::FMul,
+            Self::IDiv => Self::FDiv,
+            _ => self.to_unsigned(),
+        }
+    }
+
+    /// Convert an operator to the variant for operands of type `ret_type`
+    pub fn for_type(self, ret_type: ReturnType) -> Self {
+        if ret_type == ReturnType::Real {
+            self.to_real()
+        } else if ret_type.is_unsigned() {
+            self.to_unsigned()
+        } else {
+            self
+        }
+    }
+
     /// Convert a Setx instruction to a Jumpx
     pub fn to_jump(self) -> Self {
         match self {
@@ -238,6 +310,29 @@ impl IOperator {
             Self::SetGE => Self::Jge,
             Self::SetL => Self::Jl,
             Self::SetLE => Self::Jle,
+            Self::SetA => Self::Ja,
+            Self::SetAE => Self::Jae,
+            Self::SetB => Self::Jb,
+            Self::SetBE => Self::Jbe,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Convert a jump to the jump taken when the condition does not hold
+    pub fn negate_jump(self) -> Self {
+        match self {
+            Self::Je => Self::Jne,
+            Self::Jne => Self::Je,
+            Self::Jz => Self::Jnz,
+            Self::Jnz => Self::Jz,
+            Self::Jg => Self::Jle,
+            Self::Jle => Self::Jg,
+            Self::Jge => Self::Jl,
+            Self::Jl => Self::Jge,
+            Self::Ja => Self::Jbe,
+            Self::Jbe => Self::Ja,
+            Self::Jae => Self::Jb,
+            Self::Jb => Self::Jae,
             _ => unreachable!(),
         }
     }
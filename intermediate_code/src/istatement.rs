@@ -1,4 +1,4 @@
-use syntax::{ReturnType, SymbolId, BUILTIN_IDS};
+use syntax::{ReturnType, SymbolId, SymbolTable};
 
 use crate::{
     ioperand::IOperand,
@@ -78,8 +78,8 @@ impl IStatement {
             || self.operator == IOperator::Return
     }
 
-    pub fn is_non_builtin_call(&self) -> bool {
-        self.is_call() && !BUILTIN_IDS.contains(&self.label_id().0)
+    pub fn is_non_builtin_call(&self, table: &SymbolTable) -> bool {
+        self.is_call() && !table.is_builtin(&self.label_id())
     }
 
     pub fn is_call(&self) -> bool {
@@ -108,6 +108,10 @@ impl IStatement {
         self.operator == IOperator::Assign
     }
 
+    pub fn is_array_assign(&self) -> bool {
+        self.operator == IOperator::ArrayAssign
+    }
+
     pub fn is_binop(&self) -> bool {
         matches!(
             self.operator,
@@ -120,6 +124,16 @@ impl IStatement {
                 | IOperator::Mod
                 | IOperator::IDiv
                 | IOperator::Imod
+                | IOperator::BitAnd
+                | IOperator::BitOr
+                | IOperator::BitXor
+                | IOperator::Shl
+                | IOperator::Sar
+                | IOperator::Shr
+                | IOperator::FAdd
+                | IOperator::FSub
+                | IOperator::FMul
+                | IOperator::FDiv
         )
     }
 
@@ -127,6 +141,35 @@ impl IStatement {
         self.operator == IOperator::Param
     }
 
+    /// The variable this statement assigns a value to, if any
+    pub fn defined(&self) -> Option<SymbolId> {
+        if self.is_array_assign() || self.is_conditional_jump() {
+            return None;
+        }
+        match self.ret_target.as_ref() {
+            Some(IOperand::Symbol { id, .. }) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The variables whose value this statement reads
+    pub fn used(&self) -> Vec<SymbolId> {
+        let mut used = vec![];
+        if self.is_func() || self.is_label() || self.is_unconditional_jump() || self.is_call() {
+            return used;
+        }
+        for operand in [&self.operand1, &self.operand2] {
+            if let Some(IOperand::Symbol { id, .. }) = operand {
+                used.push(*id);
+            }
+        }
+        if self.is_array_assign() {
+            // Storing into an element reads the array's base address
+            used.push(self.ret_target.as_ref().unwrap().id());
+        }
+        used
+    }
+
     pub fn get_triple(&self) -> (&IOperand, &IOperand, &IOperand) {
         (
             self.operand1.as_ref().unwrap(),
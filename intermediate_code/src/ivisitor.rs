@@ -5,13 +5,20 @@ use crate::istatement::IStatement;
 use syntax::{ConstantNodeValue, SymbolId, SyntaxNode, SyntaxNodeBox};
 use syntax::{
     NodeType::{self, *},
-    ReturnType,
+    ReturnType, SymbolType,
 };
 
+struct LoopLabels {
+    exit: SymbolId,
+    next: SymbolId,
+}
+
 pub struct IVisitor<'a> {
     table: &'a mut syntax::SymbolTable,
     icode: IntermediateCode,
     func_stack: Vec<SymbolId>,
+    /// The labels `break` and `continue` jump to, for each loop around the current statement
+    loop_stack: Vec<LoopLabels>,
     // label_counter: usize,
     // temp_counter: usize,
 }
@@ -22,6 +29,7 @@ impl<'a> IVisitor<'a> {
             table,
             icode: IntermediateCode::new(),
             func_stack: vec![],
+            loop_stack: vec![],
             // label_counter: 0,
             // temp_counter: 0,
         }
@@ -64,10 +72,35 @@ impl<'a> IVisitor<'a> {
                 let (cond, body) = (*node.borrow()).get_both_binary_children();
                 self.visit_while(cond, body);
             }
+            DoWhile => {
+                let (body, cond) = (*node.borrow()).get_both_binary_children();
+                self.visit_do_while(body, cond);
+            }
+            For => {
+                let (cond, targets) = (*node.borrow()).get_both_binary_children();
+                let (body, step) = (*targets.borrow()).get_both_binary_children();
+                self.visit_for(cond, body, step);
+            }
+            Break | Continue => {
+                let labels = self
+                    .loop_stack
+                    .last()
+                    .expect("`break` or `continue` outside of a loop");
+                let target = if ntype == Break {
+                    labels.exit
+                } else {
+                    labels.next
+                };
+                self.icode.append_statement(IStatement::make_goto(target));
+            }
             Return => {
                 let ret_child = (*node.borrow()).get_unary_child();
                 self.visit_return(ret_child);
             }
+            ArrayInitializer => {
+                let (array, elements) = (*node.borrow()).get_binary_children();
+                self.visit_array_initializer(array.unwrap(), elements);
+            }
             _ => {
                 unimplemented!("{:?}", (*node.borrow()).node_type());
             }
@@ -86,21 +119,27 @@ impl<'a> IVisitor<'a> {
                 let (l, r) = (*exp.borrow()).get_both_binary_children();
                 self.visit_assignment(l, r)
             }
+            AddAssign | SubAssign | MulAssign | DivAssign | ModAssign => {
+                let (l, r) = (*exp.borrow()).get_both_binary_children();
+                self.visit_update(l, ntype, Some(r))
+            }
+            PreIncrement | PreDecrement | PostIncrement | PostDecrement => {
+                let child = (*exp.borrow()).get_unary_child().unwrap();
+                self.visit_update(child, ntype, None)
+            }
             Add | Sub | Mul | Div | Mod | And | Or | RelEqual | RelNotEqual | RelGT | RelGTE
-            | RelLT | RelLTE => {
+            | RelLT | RelLTE | BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight => {
                 let (l, r) = (*exp.borrow()).get_both_binary_children();
                 let ret_type = if ntype.is_rel_expression() {
                     ReturnType::Bool
                 } else {
                     (*l.borrow()).return_type()
                 };
-                let operator = if ret_type.is_unsigned() {
-                    IOperator::from(ntype).to_unsigned()
-                } else {
-                    IOperator::from(ntype)
-                };
+                // Comparisons, division and right shifts depend on the type of their operands
+                let operator = IOperator::from(ntype).for_type((*l.borrow()).return_type());
                 let l_expr = self.accept_expression(l);
                 let r_expr = self.accept_expression(r);
+                let (operator, l_expr, r_expr) = order_real_comparison(operator, l_expr, r_expr);
 
                 let ret = self.make_temp(ret_type);
                 let ret_target = IOperand::Symbol { id: ret, ret_type };
@@ -141,6 +180,13 @@ impl<'a> IVisitor<'a> {
                     ret_type: (*exp.borrow()).return_type(),
                 }
             }
+            StringLiteral => {
+                if let SyntaxNode::StringLiteral { index, .. } = *exp.borrow() {
+                    IOperand::String { index }
+                } else {
+                    unreachable!()
+                }
+            }
             Id => {
                 if let SyntaxNode::Symbol {
                     symbol_id,
@@ -173,7 +219,12 @@ impl<'a> IVisitor<'a> {
                 });
                 ret_target
             }
-            SignMinus => {
+            SignMinus | BitNot => {
+                let operator = if ntype == SignMinus {
+                    IOperator::Minus
+                } else {
+                    IOperator::BitNot
+                };
                 let child = (*exp.borrow()).get_unary_child().unwrap();
                 let ret_type = (*child.borrow()).return_type();
                 let child_exp = self.accept_expression(child);
@@ -184,7 +235,7 @@ impl<'a> IVisitor<'a> {
                 };
                 self.icode.append_statement(IStatement {
                     op_type: ret_type.into(),
-                    operator: IOperator::Minus,
+                    operator,
                     operand1: Some(child_exp),
                     operand2: None,
                     ret_target: Some(ret_target.clone()),
@@ -199,47 +250,40 @@ impl<'a> IVisitor<'a> {
         }
     }
 
+    /// Returns a jump that is taken when `exp` holds, along with the operands it compares
     fn accept_cond_expression(&mut self, exp: SyntaxNodeBox) -> (IOperator, IOperand, IOperand) {
         let ntype = (*exp.borrow()).node_type();
-        if ntype.is_rel_expression() {
+        if ntype.is_rel_expression() && !matches!(ntype, NodeType::And | NodeType::Or) {
             let (l, r) = (*exp.borrow()).get_both_binary_children();
+            let op = IOperator::from(ntype).for_type((*l.borrow()).return_type());
             let l_expr = self.accept_expression(l);
             let r_expr = self.accept_expression(r);
-            let op = IOperator::from(ntype).to_jump();
-            (op, l_expr, r_expr)
-        } else if ntype == NodeType::Coercion {
-            let l = (*exp.borrow()).get_unary_child().unwrap();
-            let expr = self.accept_expression(l);
+            let (op, l_expr, r_expr) = order_real_comparison(op, l_expr, r_expr);
+            (op.to_jump(), l_expr, r_expr)
+        } else {
+            // Any other expression holds when it is non-zero,
+            // which a coercion to bool does not change
+            let is_bool_coercion =
+                ntype == NodeType::Coercion && (*exp.borrow()).return_type() == ReturnType::Bool;
+            let exp = if is_bool_coercion {
+                (*exp.borrow()).get_unary_child().unwrap()
+            } else {
+                exp
+            };
+            let ret_type = (*exp.borrow()).return_type();
+            let expr = self.accept_expression(exp);
             (
                 IOperator::Jne,
                 expr,
                 IOperand::Immediate {
-                    value: ConstantNodeValue::from(0),
-                    ret_type: ReturnType::Bool,
-                },
-            )
-        } else if ntype == NodeType::Num {
-            let num = self.accept_expression(exp);
-            (
-                IOperator::Je,
-                num,
-                IOperand::Immediate {
-                    value: ConstantNodeValue::from(0),
-                    ret_type: ReturnType::Bool,
+                    value: ConstantNodeValue::new_with_ret(0, ret_type),
+                    ret_type,
                 },
             )
-        } else {
-            unreachable!(
-                "accept_cond_expression only accept relational operators, coercions or num, got {}",
-                ntype
-            );
         }
     }
 
     pub fn visit_function(&mut self, func: SyntaxNodeBox, func_id: SymbolId) {
-        if func_id.is_builtin() {
-            return;
-        }
         let name = self.table.get_symbol(&func_id).unwrap().name.clone();
         log::trace!("Visiting function {}", name);
         self.func_stack.push(func_id);
@@ -255,8 +299,8 @@ impl<'a> IVisitor<'a> {
             ret_target: None,
         });
         self.accept(func.clone());
-        let last_stmt = self.icode.get_last_statement();
-        if !last_stmt.is_jump() && !last_stmt.is_recursive_call(&func_id) {
+        // Control can reach the end of the body unless it ends in a return
+        if !self.icode.get_last_statement().is_return() {
             self.add_implicit_return((*func.borrow()).return_type());
         }
     }
@@ -279,13 +323,12 @@ impl<'a> IVisitor<'a> {
         // let l_var = *l_var.borrow();
         // let r_expr = *r_expr.borrow();
         let common_ret = (*l_var.borrow()).return_type().to_base_type();
-        let ret_target = if (*l_var.borrow()).node_type() == NodeType::ArrayAccess {
-            self.visit_array_access(l_var)
-        } else {
-            IOperand::Symbol {
-                id: (*l_var.borrow()).symbol_id(),
-                ret_type: common_ret,
-            }
+        if (*l_var.borrow()).node_type() == NodeType::ArrayAccess {
+            return self.visit_array_assignment(l_var, r_expr);
+        }
+        let ret_target = IOperand::Symbol {
+            id: (*l_var.borrow()).symbol_id(),
+            ret_type: common_ret,
         };
         let r_expr = self.accept_expression(r_expr);
         self.icode.append_statement(IStatement {
@@ -309,7 +352,7 @@ impl<'a> IVisitor<'a> {
         // Check condition, jump to else-label if condition was false
         self.icode.append_statement(IStatement {
             op_type: IOperatorSize::Void,
-            operator: op,
+            operator: op.negate_jump(),
             operand1: Some(l),
             operand2: Some(r),
             ret_target: Some(IOperand::Symbol {
@@ -344,13 +387,62 @@ impl<'a> IVisitor<'a> {
     fn visit_while(&mut self, cond: SyntaxNodeBox, body: SyntaxNodeBox) {
         let loop_cond = self.make_label();
         let loop_body = self.make_label();
+        let loop_exit = self.make_label();
         self.icode
             .append_statement(IStatement::make_goto(loop_cond));
         self.icode
             .append_statement(IStatement::make_label(loop_body));
-        self.accept(body);
+        self.accept_loop_body(body, loop_exit, loop_cond);
+        self.icode
+            .append_statement(IStatement::make_label(loop_cond));
+        self.append_loop_condition(cond, loop_body, loop_exit);
+    }
+
+    fn visit_do_while(&mut self, body: SyntaxNodeBox, cond: SyntaxNodeBox) {
+        let loop_body = self.make_label();
+        let loop_cond = self.make_label();
+        let loop_exit = self.make_label();
+        self.icode
+            .append_statement(IStatement::make_label(loop_body));
+        self.accept_loop_body(body, loop_exit, loop_cond);
         self.icode
             .append_statement(IStatement::make_label(loop_cond));
+        self.append_loop_condition(cond, loop_body, loop_exit);
+    }
+
+    /// The init statement of a `for` loop precedes its node, so only the loop itself is lowered here
+    fn visit_for(&mut self, cond: SyntaxNodeBox, body: SyntaxNodeBox, step: SyntaxNodeBox) {
+        let loop_cond = self.make_label();
+        let loop_body = self.make_label();
+        let loop_step = self.make_label();
+        let loop_exit = self.make_label();
+        self.icode
+            .append_statement(IStatement::make_goto(loop_cond));
+        self.icode
+            .append_statement(IStatement::make_label(loop_body));
+        self.accept_loop_body(body, loop_exit, loop_step);
+        self.icode
+            .append_statement(IStatement::make_label(loop_step));
+        self.accept(step);
+        self.icode
+            .append_statement(IStatement::make_label(loop_cond));
+        self.append_loop_condition(cond, loop_body, loop_exit);
+    }
+
+    /// Accepts `body` with `break` jumping to `exit` and `continue` jumping to `next`
+    fn accept_loop_body(&mut self, body: SyntaxNodeBox, exit: SymbolId, next: SymbolId) {
+        self.loop_stack.push(LoopLabels { exit, next });
+        self.accept(body);
+        self.loop_stack.pop();
+    }
+
+    /// Jumps back to `loop_body` if `cond` holds, and places the `loop_exit` label after it
+    fn append_loop_condition(
+        &mut self,
+        cond: SyntaxNodeBox,
+        loop_body: SymbolId,
+        loop_exit: SymbolId,
+    ) {
         let (op, l, r) = self.accept_cond_expression(cond);
         self.icode.append_statement(IStatement {
             op_type: IOperatorSize::Void,
@@ -362,6 +454,8 @@ impl<'a> IVisitor<'a> {
                 ret_type: ReturnType::Void,
             }),
         });
+        self.icode
+            .append_statement(IStatement::make_label(loop_exit));
     }
 
     fn visit_func_call(&mut self, func: SyntaxNodeBox, args: Option<SyntaxNodeBox>) -> IOperand {
@@ -372,8 +466,19 @@ impl<'a> IVisitor<'a> {
             id: ret_temp,
             ret_type,
         };
+        // Evaluate all arguments before passing any, so nested calls don't interleave their params
+        let mut params = vec![];
         if let Some(args) = args {
-            self.visit_expr_list(args);
+            self.visit_expr_list(args, &mut params);
+        }
+        for (exp, exp_type) in params {
+            self.icode.append_statement(IStatement {
+                op_type: exp_type.into(),
+                operator: IOperator::Param,
+                operand1: Some(exp),
+                operand2: None,
+                ret_target: None,
+            });
         }
         self.icode.append_statement(IStatement {
             op_type: ret_type.into(),
@@ -388,19 +493,17 @@ impl<'a> IVisitor<'a> {
         ret_temp
     }
 
-    fn visit_expr_list(&mut self, expr_list: SyntaxNodeBox) {
+    fn visit_expr_list(
+        &mut self,
+        expr_list: SyntaxNodeBox,
+        params: &mut Vec<(IOperand, ReturnType)>,
+    ) {
         let (current_exp_node, next) = (*expr_list.borrow()).get_binary_children();
         if let Some(ref exp_node) = current_exp_node {
             let exp = self.accept_expression(exp_node.clone());
-            self.icode.append_statement(IStatement {
-                op_type: (*exp_node.borrow()).return_type().into(),
-                operator: IOperator::Param,
-                operand1: Some(exp),
-                operand2: None,
-                ret_target: None,
-            });
+            params.push((exp, (*exp_node.borrow()).return_type()));
             if let Some(next) = next {
-                self.visit_expr_list(next);
+                self.visit_expr_list(next, params);
             }
         }
     }
@@ -417,39 +520,187 @@ impl<'a> IVisitor<'a> {
     }
 
     fn visit_array_access(&mut self, node: SyntaxNodeBox) -> IOperand {
-        // let n = (*node.borrow());
+        let (array, index) = self.accept_array_element(node);
+        self.load_array_element(&array, index)
+    }
+
+    /// Stores `r_expr` into an array element and returns the stored value.
+    /// The element is the return target: `ARRAY_ASSIGN [index] [value] -> [array]`
+    fn visit_array_assignment(&mut self, l_var: SyntaxNodeBox, r_expr: SyntaxNodeBox) -> IOperand {
+        let (array, index) = self.accept_array_element(l_var);
+        let value = self.accept_expression(r_expr);
+        self.store_array_element(&array, index, value.clone());
+        value
+    }
+
+    /// Stores the `elements` into the first elements of `array` and zeroes the rest
+    fn visit_array_initializer(&mut self, array: SyntaxNodeBox, elements: Option<SyntaxNodeBox>) {
+        let id = (*array.borrow()).symbol_id();
+        let array = IOperand::from_symbol(id, (*array.borrow()).return_type());
+        let size = match self.table.get_symbol(&id).unwrap().symbol_type {
+            SymbolType::ArrayVariable { size } => size,
+            _ => unreachable!("{} is not an array variable", array),
+        };
+        let mut values = vec![];
+        if let Some(elements) = elements {
+            self.visit_expr_list(elements, &mut values);
+        }
+        let base_type = array.ret_type().to_base_type();
+        let zero = IOperand::Immediate {
+            value: ConstantNodeValue::new_with_ret(0, base_type),
+            ret_type: base_type,
+        };
+        let mut values = values.into_iter().map(|(value, _)| value);
+        for index in 0..size {
+            let index = IOperand::Immediate {
+                value: ConstantNodeValue::Int(index as i32),
+                ret_type: ReturnType::Int,
+            };
+            let value = values.next().unwrap_or_else(|| zero.clone());
+            self.store_array_element(&array, index, value);
+        }
+    }
+
+    /// Evaluates the index of an array access, so the element can be loaded and stored
+    /// without evaluating the index again. Returns the array and the index.
+    fn accept_array_element(&mut self, node: SyntaxNodeBox) -> (IOperand, IOperand) {
         let (array, access) = (*node.borrow()).get_both_binary_children();
         log::trace!(
             "Array - array: {}, access: {}",
             *array.borrow(),
             *access.borrow()
         );
-        let array_id = (*array.borrow()).symbol_id();
-        let ret_type = (*array.borrow()).return_type().to_base_type();
-        let access_with_offset = self.calc_array_index(ret_type, access);
-        let array_access_retval = IOperand::from_symbol(self.make_temp(ret_type), ret_type);
+        let array = IOperand::from_symbol(
+            (*array.borrow()).symbol_id(),
+            (*array.borrow()).return_type(),
+        );
+        let index = self.calc_array_index(array.id(), access);
+        (array, index)
+    }
+
+    /// Emits `ARRAY [array] [index] -> [temp]` and returns the temp
+    fn load_array_element(&mut self, array: &IOperand, index: IOperand) -> IOperand {
+        let ret_type = array.ret_type().to_base_type();
+        let element = IOperand::from_symbol(self.make_temp(ret_type), ret_type);
         self.icode.append_statement(IStatement {
             op_type: ret_type.into(),
             operator: IOperator::Array,
-            operand1: Some(IOperand::from_symbol(array_id, ret_type)),
-            operand2: Some(access_with_offset),
-            ret_target: Some(array_access_retval.clone()),
+            operand1: Some(IOperand::from_symbol(array.id(), ret_type)),
+            operand2: Some(index),
+            ret_target: Some(element.clone()),
         });
-        array_access_retval
+        element
     }
 
-    fn calc_array_index(&mut self, base_type: ReturnType, access: SyntaxNodeBox) -> IOperand {
-        let type_size: usize = IOperatorSize::from(base_type).into();
-        let acccess_exp = self.accept_expression(access);
-        let access_with_offset = IOperand::from_symbol(self.make_temp(base_type), base_type);
+    fn store_array_element(&mut self, array: &IOperand, index: IOperand, value: IOperand) {
         self.icode.append_statement(IStatement {
-            op_type: IOperatorSize::Double,
-            operator: IOperator::Mul,
-            operand1: Some(IOperand::from(type_size)),
-            operand2: Some(acccess_exp),
-            ret_target: Some(access_with_offset.clone()),
+            op_type: array.ret_type().to_base_type().into(),
+            operator: IOperator::ArrayAssign,
+            operand1: Some(index),
+            operand2: Some(value),
+            ret_target: Some(array.clone()),
         });
-        access_with_offset
+    }
+
+    /// Lowers a compound assignment, or an increment or decrement if `r_expr` is [None].
+    /// The index of an array element is evaluated once, before `r_expr`.
+    /// Returns the value before the update for postfix operators, and the new value otherwise.
+    fn visit_update(
+        &mut self,
+        l_var: SyntaxNodeBox,
+        ntype: NodeType,
+        r_expr: Option<SyntaxNodeBox>,
+    ) -> IOperand {
+        let ret_type = (*l_var.borrow()).return_type().to_base_type();
+        let operator = IOperator::from(ntype.update_operator().unwrap()).for_type(ret_type);
+        let element = if (*l_var.borrow()).node_type() == NodeType::ArrayAccess {
+            Some(self.accept_array_element(l_var.clone()))
+        } else {
+            None
+        };
+        let rhs = match r_expr {
+            Some(r_expr) => self.accept_expression(r_expr),
+            None => IOperand::Immediate {
+                value: ConstantNodeValue::new_with_ret(1, ret_type),
+                ret_type,
+            },
+        };
+        let (current, updated) = match element {
+            Some((ref array, ref index)) => (
+                self.load_array_element(array, index.clone()),
+                IOperand::from_symbol(self.make_temp(ret_type), ret_type),
+            ),
+            None => {
+                let var = IOperand::from_symbol((*l_var.borrow()).symbol_id(), ret_type);
+                (var.clone(), var)
+            }
+        };
+        let is_postfix = matches!(ntype, PostIncrement | PostDecrement);
+        // A variable is updated in place, so a postfix operator needs a copy of its old value
+        let result = if is_postfix && element.is_none() {
+            let old = IOperand::from_symbol(self.make_temp(ret_type), ret_type);
+            self.icode.append_statement(IStatement {
+                op_type: ret_type.into(),
+                operator: IOperator::Assign,
+                operand1: None,
+                operand2: Some(current.clone()),
+                ret_target: Some(old.clone()),
+            });
+            old
+        } else if is_postfix {
+            current.clone()
+        } else {
+            updated.clone()
+        };
+        self.icode.append_statement(IStatement {
+            op_type: ret_type.into(),
+            operator,
+            operand1: Some(current),
+            operand2: Some(rhs),
+            ret_target: Some(updated.clone()),
+        });
+        if let Some((array, index)) = element {
+            self.store_array_element(&array, index, updated);
+        }
+        result
+    }
+
+    /// Returns the element index of an array access.
+    /// The indexes of a multi-dimensional array are combined in row-major order,
+    /// e.g. `m[i][j][k]` of `int m[2][3][4]` has index `(i * 3 + j) * 4 + k`.
+    /// Scaling by the element size is left to the addressing mode of the target.
+    fn calc_array_index(&mut self, array: SymbolId, access: SyntaxNodeBox) -> IOperand {
+        if (*access.borrow()).node_type() != NodeType::ExpressionList {
+            return self.accept_expression(access);
+        }
+        let dimensions = self.table.get_symbol(&array).unwrap().dimensions.clone();
+        let mut indexes = vec![];
+        self.visit_expr_list(access, &mut indexes);
+        let mut indexes = indexes.into_iter().map(|(index, _)| index);
+        let first = indexes.next().unwrap();
+        dimensions
+            .into_iter()
+            .zip(indexes)
+            .fold(first, |row, (size, index)| {
+                let size = IOperand::Immediate {
+                    value: ConstantNodeValue::Uint(size as u32),
+                    ret_type: ReturnType::Uint,
+                };
+                let offset = self.append_index_op(IOperator::Mul, row, size);
+                self.append_index_op(IOperator::Add, offset, index)
+            })
+    }
+
+    fn append_index_op(&mut self, operator: IOperator, lhs: IOperand, rhs: IOperand) -> IOperand {
+        let result = IOperand::from_symbol(self.make_temp(ReturnType::Uint), ReturnType::Uint);
+        self.icode.append_statement(IStatement {
+            op_type: ReturnType::Uint.into(),
+            operator,
+            operand1: Some(lhs),
+            operand2: Some(rhs),
+            ret_target: Some(result.clone()),
+        });
+        result
     }
 
     fn make_temp(&mut self, ret_type: ReturnType) -> SymbolId {
@@ -460,3 +711,21 @@ impl<'a> IVisitor<'a> {
         self.table.add_label(self.current_func())
     }
 }
+
+/// Reals are compared like `ucomisd`, after which only `>` and `>=` do not hold if an operand
+/// is NaN. `<` and `<=` swap their operands to use those, so that the jumps that negate a
+/// comparison, `Jbe` and `Jb`, are taken for NaN.
+fn order_real_comparison(
+    op: IOperator,
+    l: IOperand,
+    r: IOperand,
+) -> (IOperator, IOperand, IOperand) {
+    if l.ret_type() != ReturnType::Real {
+        return (op, l, r);
+    }
+    match op {
+        IOperator::SetB => (IOperator::SetA, r, l),
+        IOperator::SetBE => (IOperator::SetAE, r, l),
+        _ => (op, l, r),
+    }
+}
@@ -1,7 +1,9 @@
 use error::ICodeError;
 use ic_generator::Intermediate;
 use ic_generator::OptLevel;
-use syntax::{SymbolTable, SyntaxAnalysisResult, SyntaxTree};
+use icode::IntermediateCode;
+use interpreter::{Interpreter, ProgramIO};
+use syntax::{BuiltinRegistry, SymbolTable, SyntaxAnalysisResult, SyntaxTree};
 
 pub mod error;
 pub mod flow_graph;
@@ -9,6 +11,7 @@ pub mod ic_generator;
 pub mod ic_info;
 pub mod icode;
 mod icode_optimization;
+pub mod interpreter;
 pub mod ioperand;
 pub mod ioperator;
 pub mod istatement;
@@ -28,6 +31,15 @@ pub fn generate_from_str(input: &str, opt: OptLevel) -> Result<Intermediate, ICo
         mut symbol_table,
         mut tree,
         ..
-    } = syntax::generate(input).unwrap();
+    } = syntax::generate(input, &BuiltinRegistry::default()).unwrap();
     generate(&mut tree, &mut symbol_table, opt)
 }
+
+/// Runs `icode` from `main` with `io` for the builtins, and returns the value `main` returns
+pub fn interpret(
+    icode: &IntermediateCode,
+    symbol_table: &SymbolTable,
+    io: &mut dyn ProgramIO,
+) -> Result<i64, ICodeError> {
+    Interpreter::new(icode, symbol_table, io).run()
+}
@@ -4,6 +4,7 @@ pub mod ic_generator;
 pub mod ic_info;
 pub mod icode;
 mod icode_optimization;
+pub mod interpreter;
 pub mod ioperand;
 pub mod ioperator;
 pub mod istatement;
@@ -13,11 +14,13 @@ mod syntax_tree_optimization;
 use crate::error::ICodeError;
 use crate::flow_graph::FlowGraph;
 use crate::ic_generator::{Intermediate, OptLevel};
+use crate::interpreter::{Interpreter, StreamIO};
 use clap::clap_app;
-use general::logging::init_logger_from_env;
+use general::logging::{init_logger_from_env, init_logger_from_env_or};
+use log::LevelFilter;
 use std::io::Write;
 use std::process::{Command, Stdio};
-use syntax::SyntaxAnalysisResult;
+use syntax::{BuiltinRegistry, Source, SyntaxAnalysisResult};
 
 fn save_cfg(filename: &str, graph: &FlowGraph) {
     let mut dot = Command::new("dot")
@@ -37,11 +40,15 @@ fn save_cfg(filename: &str, graph: &FlowGraph) {
         .unwrap()
         .write_all(graph.to_string().as_bytes())
         .unwrap();
-    log::info!(
-        "Saved control flow graph to {} with entrypoint {}",
-        filename,
-        graph.entry()
-    );
+    dot.wait().unwrap();
+    match graph.entry() {
+        Some(entry) => log::info!(
+            "Saved control flow graph to {} with entrypoint {}",
+            filename,
+            entry
+        ),
+        None => log::info!("Saved control flow graph to {}", filename),
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -50,11 +57,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         (about: "Produce intermediate code for the given input C-minus file")
         (@arg annotate: -a --annotate "Also print the annotated intermediate code")
         (@arg flowgraph: +takes_value -g --flowgraph  "Save the control flow graph in .png format to the provided file. Requires the Graphviz library (`dot`).")
+        (@arg run: -r --run "Interpret the intermediate code, reading from stdin and writing to stdout. Exits with the value `main` returns.")
+        (@arg warn_narrowing: -W --warn_narrowing "Warn about explicit casts that narrow their operand, such as `(uint8_t) i`")
         (@arg INPUT: +required "Sets the input")
         (@arg OPTIMIZE: -O +takes_value "Optimize compiler output. Takes a value between 0 and 3 (inclusive).")
     )
     .get_matches();
-    init_logger_from_env();
+    let warn_narrowing = matches.is_present("warn_narrowing");
+    // The narrowing casts are logged as warnings, which are hidden by default
+    if warn_narrowing {
+        init_logger_from_env_or(LevelFilter::Warn);
+    } else {
+        init_logger_from_env();
+    }
     let opt_level = match matches.value_of("OPTIMIZE") {
         Some("1") => OptLevel::Pre,
         Some("2") => OptLevel::Post,
@@ -64,15 +79,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
     log::info!("Using `{:?}` optimization", opt_level);
     let annotate = matches.is_present("annotate");
+    let run = matches.is_present("run");
     let graph_filename = matches.value_of("flowgraph");
-    let input = matches.value_of("INPUT").unwrap();
-    let input = std::fs::read_to_string(input)?;
+    let input_path = matches.value_of("INPUT").unwrap();
+    let input = std::fs::read_to_string(input_path)?;
+    let source = Source {
+        name: input_path,
+        text: &input,
+    };
     let SyntaxAnalysisResult {
         errors,
         mut symbol_table,
         mut tree,
         warnings,
-    } = syntax::generate(&input)?;
+        narrowing_casts,
+    } = syntax::generate(source, &BuiltinRegistry::default())?;
     let has_errors = !errors.is_empty();
     if has_errors {
         syntax::display_errors(&errors);
@@ -83,6 +104,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         ))));
     }
     syntax::display_warnings(&warnings);
+    if warn_narrowing {
+        syntax::display_warnings(&narrowing_casts);
+    }
     let ic = ic_generator::generate(&mut tree, &mut symbol_table, opt_level);
     log::info!("\n{}", symbol_table);
     log::info!("\n{}", tree);
@@ -99,6 +123,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             if let Some(filename) = graph_filename {
                 save_cfg(filename, graph);
             }
+            if run {
+                let stdin = std::io::stdin();
+                let stdout = std::io::stdout();
+                let mut io = StreamIO::new(stdin.lock(), stdout.lock());
+                let ret = Interpreter::new(&icode, &symbol_table, &mut io).run()?;
+                log::info!("`main` returned {}", ret);
+                std::process::exit(ret as i32);
+            }
             Ok(())
         }
 
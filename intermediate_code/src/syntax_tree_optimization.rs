@@ -1,3 +1,4 @@
+use crate::ioperator::IOperatorSize;
 use syntax::{ConstantNodeValue, NodeType, SyntaxNode, SyntaxTree};
 
 // pub fn propagate_constants(tree: &mut SyntaxTree) {}
@@ -22,7 +23,7 @@ pub fn fold_constants(tree: &mut SyntaxTree) {
                             NodeType::Sub => *lval - *rval,
                             NodeType::Mul => *lval * *rval,
                             NodeType::Div => {
-                                if i64::from(*rval) == 0 {
+                                if !rval.is_real() && i64::from(*rval) == 0 {
                                     continue;
                                 }
                                 *lval / *rval
@@ -42,8 +43,54 @@ pub fn fold_constants(tree: &mut SyntaxTree) {
                             NodeType::Mod => {
                                 let l = i64::from(*lval);
                                 let r = i64::from(*rval);
-                                let new = l % r;
-                                ConstantNodeValue::from(new)
+                                if r == 0 {
+                                    continue;
+                                }
+                                let new = if rtype.is_unsigned() {
+                                    (l as u64 % r as u64) as i64
+                                } else {
+                                    l.wrapping_rem(r)
+                                };
+                                ConstantNodeValue::new_with_ret(new, rtype)
+                            }
+                            NodeType::BitAnd | NodeType::BitOr | NodeType::BitXor => {
+                                let l = i64::from(*lval);
+                                let r = i64::from(*rval);
+                                let new = match n.node_type() {
+                                    NodeType::BitAnd => l & r,
+                                    NodeType::BitOr => l | r,
+                                    _ => l ^ r,
+                                };
+                                ConstantNodeValue::new_with_ret(new, rtype)
+                            }
+                            NodeType::ShiftLeft | NodeType::ShiftRight => {
+                                // Like x86, only the lowest 5 bits of the shift count are used,
+                                // or 6 bits for 64-bit values.
+                                let mask = match IOperatorSize::from(rtype) {
+                                    IOperatorSize::Quad => 63,
+                                    _ => 31,
+                                };
+                                let l = i64::from(*lval);
+                                let r = i64::from(*rval) & mask;
+                                let new = if n.node_type() == NodeType::ShiftLeft {
+                                    l << r
+                                } else if rtype.is_unsigned() {
+                                    ((l as u64) >> r) as i64
+                                } else {
+                                    l >> r
+                                };
+                                ConstantNodeValue::new_with_ret(new, rtype)
+                            }
+                            // Only `!=` holds for NaN, which the ordering of constants ignores
+                            NodeType::RelGT
+                            | NodeType::RelGTE
+                            | NodeType::RelLT
+                            | NodeType::RelLTE
+                            | NodeType::RelEqual
+                            | NodeType::RelNotEqual
+                                if lval.is_nan() || rval.is_nan() =>
+                            {
+                                continue;
                             }
                             NodeType::RelGT => ConstantNodeValue::from((*lval > *rval) as i64),
                             NodeType::RelGTE => ConstantNodeValue::from((*lval >= *rval) as i64),
@@ -56,10 +103,14 @@ pub fn fold_constants(tree: &mut SyntaxTree) {
 
                             _ => unreachable!(),
                         };
+                        // Comparisons, `&&` and `||` give a bool rather than their operand type
+                        let ret = n.return_type();
+                        let folded = folded.cast(ret);
                         log::debug!("Folded value: {}", folded);
                         let new_node = SyntaxNode::Constant {
+                            span: n.span(),
                             node_type: ntype,
-                            return_type: rtype,
+                            return_type: ret,
                             value: folded,
                         };
                         *n = new_node;
@@ -76,18 +127,27 @@ pub fn fold_constants(tree: &mut SyntaxTree) {
                 } = &*child.borrow()
                 {
                     let folded = match parent_type {
-                        NodeType::Coercion => {
-                            let val = i64::from(*value);
-                            ConstantNodeValue::new_with_ret(val, parent_ret)
-                        }
+                        NodeType::Coercion => value.cast(parent_ret),
                         NodeType::SignPlus => *value,
+                        NodeType::SignMinus if value.is_real() => {
+                            ConstantNodeValue::Real(-f64::from(*value))
+                        }
                         NodeType::SignMinus => {
                             let val = i64::from(*value);
-                            ConstantNodeValue::new_with_ret(-val, parent_ret)
+                            ConstantNodeValue::new_with_ret(val.wrapping_neg(), parent_ret)
+                        }
+                        NodeType::Not => {
+                            let val = i64::from(*value);
+                            ConstantNodeValue::new_with_ret((val == 0) as i64, parent_ret)
+                        }
+                        NodeType::BitNot => {
+                            let val = i64::from(*value);
+                            ConstantNodeValue::new_with_ret(!val, parent_ret)
                         }
                         _ => unreachable!(),
                     };
                     let new_node = SyntaxNode::Constant {
+                        span: n.span(),
                         node_type: *child_type,
                         return_type: parent_ret,
                         value: folded,
@@ -12,7 +12,8 @@ pub type ParseNode<'a> = Pair<'a, Rule>;
 #[grammar = "grammar.pest"]
 struct LexicalParser;
 
-pub fn parse(input: &str) -> Result<Pairs<Rule>, ParseError<Rule>> {
+#[allow(clippy::result_large_err)]
+pub fn parse(input: &str) -> Result<Pairs<'_, Rule>, ParseError<Rule>> {
     let pairs = LexicalParser::parse(Rule::program, input)?;
     Ok(pairs)
 }
@@ -14,18 +14,47 @@ pub mod asm {
         Mul(IOperatorSize),
         IMul(IOperatorSize),
         Xor(IOperatorSize),
-        Div,
+        And(IOperatorSize),
+        Or(IOperatorSize),
+        Neg(IOperatorSize),
+        Not(IOperatorSize),
+        Shl(IOperatorSize),
+        // Arithmetic (sign-preserving) right shift
+        Sar(IOperatorSize),
+        // Logical right shift
+        Shr(IOperatorSize),
+        Div(IOperatorSize),
+        IDiv(IOperatorSize),
+        // Sign-extend the accumulator into %rdx (cwtd/cltd/cqto)
+        Cqto(IOperatorSize),
+        Lea(IOperatorSize),
         // Mov with sign-extension
         Movs(IOperatorSize, IOperatorSize),
 
         // Mov with zero-extension
         Movz(IOperatorSize, IOperatorSize),
         Sub(IOperatorSize),
+        // Mov of a 64-bit immediate
+        Movabs,
+        // Scalar double-precision arithmetic
+        Addsd,
+        Subsd,
+        Mulsd,
+        Divsd,
+        // Compare doubles, setting the flags like an unsigned compare
+        Ucomisd,
+        // Convert a 64-bit integer to a double
+        Cvtsi2sd,
+        // Convert a double to a 64-bit integer, truncating towards zero
+        Cvttsd2si,
+        // Complement a single bit
+        Btc(IOperatorSize),
         Jmp, // unconditional jump
         Je,  // jump if ==
         Jne, // jump if !=
         Js,  // jump if negative
         Jns, // jump if nonnegative
+        Jp,  // jump if parity, which after ucomisd means an operand is NaN
         Jg,  // jump if > signed
         Jge, // jump if >= signed
         Jl,  // jump if < signed
@@ -40,6 +69,8 @@ pub mod asm {
         SetNE, // set if !=
         SetS,  // set if negative
         SetNS, // set if nonnegative
+        SetP,  // set if parity
+        SetNP, // set if no parity
         SetG,  // set if > signed
         SetGE, // set if >= signed
         SetL,  // set if < signed
@@ -69,10 +100,34 @@ pub mod asm {
                     Mul(s) => format!("mul{}", s),
                     IMul(s) => format!("imul{}", s),
                     Xor(s) => format!("xor{}", s),
-                    Movs(to, from) => format!("movz{}{}", to, from),
-                    Movz(to, from) => format!("movs{}{}", to, from),
+                    And(s) => format!("and{}", s),
+                    Or(s) => format!("or{}", s),
+                    Neg(s) => format!("neg{}", s),
+                    Not(s) => format!("not{}", s),
+                    Shl(s) => format!("shl{}", s),
+                    Sar(s) => format!("sar{}", s),
+                    Shr(s) => format!("shr{}", s),
+                    Movs(from, to) => format!("movs{}{}", from, to),
+                    Movz(from, to) => format!("movz{}{}", from, to),
                     Sub(s) => format!("sub{}", s),
-                    Div => "div".into(),
+                    Movabs => "movabsq".into(),
+                    Addsd => "addsd".into(),
+                    Subsd => "subsd".into(),
+                    Mulsd => "mulsd".into(),
+                    Divsd => "divsd".into(),
+                    Ucomisd => "ucomisd".into(),
+                    Cvtsi2sd => "cvtsi2sdq".into(),
+                    Cvttsd2si => "cvttsd2siq".into(),
+                    Btc(s) => format!("btc{}", s),
+                    Div(s) => format!("div{}", s),
+                    IDiv(s) => format!("idiv{}", s),
+                    Cqto(s) => match s {
+                        IOperatorSize::Word => "cwtd",
+                        IOperatorSize::Quad => "cqto",
+                        _ => "cltd",
+                    }
+                    .into(),
+                    Lea(s) => format!("lea{}", s),
                     Jmp => "jmp".into(),
                     Jne => "jne".into(),
                     Je => "je".into(),
@@ -81,7 +136,8 @@ pub mod asm {
                     Jl => "jl".into(),
                     Jle => "jle".into(),
                     Js => "js".into(),
-                    Jns => "jse".into(),
+                    Jns => "jns".into(),
+                    Jp => "jp".into(),
                     Ja => "ja".into(),
                     Jae => "jae".into(),
                     Jb => "jb".into(),
@@ -90,6 +146,8 @@ pub mod asm {
                     SetNE => "setne".into(),
                     SetS => "sets".into(),
                     SetNS => "setns".into(),
+                    SetP => "setp".into(),
+                    SetNP => "setnp".into(),
                     SetG => "setg".into(),
                     SetGE => "setge".into(),
                     SetL => "setl".into(),
@@ -122,6 +180,14 @@ pub mod asm {
         Global(String),
         Label(String),
         Stack(StackOffset),
+        // Memory at `base + index * scale`
+        Indexed(Register, Register, usize),
+    }
+
+    impl Src {
+        pub fn is_memory(&self) -> bool {
+            matches!(self, Self::Global(_) | Self::Stack(_) | Self::Indexed(..))
+        }
     }
 
     impl From<Register> for Src {
@@ -137,11 +203,16 @@ pub mod asm {
                 "{}",
                 match self {
                     Self::None => "".into(),
-                    Self::Global(id) => format!("v{}(%rip)", id),
+                    Self::Global(id) => format!("{}(%rip)", id),
+                    Self::Immediate(ConstantNodeValue::Real(r)) =>
+                        format!("${}", r.to_bits() as i64),
+                    // Printed as signed, which the assembler accepts for every operand size
+                    Self::Immediate(ConstantNodeValue::Uint64(i)) => format!("${}", *i as i64),
                     Self::Immediate(i) => format!("${}", i),
                     Self::Register(r) => r.to_string(),
                     Self::Label(l) => l.to_string(),
                     Self::Stack(o) => format!("{}(%rbp)", o),
+                    Self::Indexed(base, index, scale) => format!("({},{},{})", base, index, scale),
                 }
             )
         }
@@ -150,7 +221,7 @@ pub mod asm {
         fn from(loc: &StoredLocation) -> Self {
             match loc {
                 StoredLocation::Global(g) => Src::Global(g.to_string()),
-                StoredLocation::Reg(r) | StoredLocation::TempReg(r, _) => Src::Register(*r),
+                StoredLocation::Reg(r) => Src::Register(*r),
                 StoredLocation::Stack(s) => Src::Stack(*s),
             }
         }
@@ -164,13 +235,20 @@ pub mod asm {
         Global(String),
         Label(String),
         Immediate(ConstantNodeValue),
+        Indexed(Register, Register, usize),
+    }
+
+    impl Dest {
+        pub fn is_memory(&self) -> bool {
+            matches!(self, Self::Global(_) | Self::Stack(_) | Self::Indexed(..))
+        }
     }
 
     impl From<&StoredLocation> for Dest {
         fn from(loc: &StoredLocation) -> Self {
             match loc {
                 StoredLocation::Global(g) => Dest::Global(g.to_string()),
-                StoredLocation::Reg(r) | StoredLocation::TempReg(r, _) => Dest::Register(*r),
+                StoredLocation::Reg(r) => Dest::Register(*r),
                 StoredLocation::Stack(s) => Dest::Stack(*s),
             }
         }
@@ -190,10 +268,12 @@ pub mod asm {
                 match self {
                     Self::None => "".into(),
                     Self::Register(r) => r.to_string(),
-                    Self::Global(s) => format!("v{}(%rip)", s),
+                    Self::Global(s) => format!("{}(%rip)", s),
                     Self::Stack(o) => format!("{}(%rbp)", o),
                     Self::Label(l) => l.to_string(),
+                    Self::Immediate(ConstantNodeValue::Uint64(v)) => format!("${}", *v as i64),
                     Self::Immediate(v) => format!("${}", v),
+                    Self::Indexed(base, index, scale) => format!("({},{},{})", base, index, scale),
                 }
             )
         }
@@ -213,6 +293,7 @@ pub mod asm {
             Src::Label(l) => Dest::Label(l),
             Src::Register(r) => Dest::Register(r),
             Src::Stack(s) => Dest::Stack(s),
+            Src::Indexed(b, i, s) => Dest::Indexed(b, i, s),
             Src::None => Dest::None,
         };
         Instr(op.into(), src1.into(), src2)
@@ -244,6 +325,11 @@ pub mod asm {
         pub fn new(name: impl ToString) -> Self {
             Self(name.to_string())
         }
+
+        /// The label of the string literal at `index` in the symbol table
+        pub fn string(index: usize) -> Self {
+            Self(format!(".LS{}", index))
+        }
     }
 
     impl fmt::Display for Label {
@@ -255,29 +341,77 @@ pub mod asm {
         File(String),
         Def(String),
         Text,
+        Data,
+        // Read-only data, such as string literals
+        Rodata,
         Ascii(String),
+        // A null-terminated string
+        String(String),
         Global(String),
-        Comm { name: String, size: IOperatorSize },
+        // Makes a symbol local to this file
+        Local(String),
+        // Uninitialized data of `size` bytes, aligned to `align` bytes
+        Comm {
+            name: String,
+            size: usize,
+            align: usize,
+        },
+        Align(usize),
+        // Initialized data of 1, 2, 4 or 8 bytes per value
+        Byte(Vec<i64>),
+        Word(Vec<i64>),
+        Long(Vec<i64>),
+        Quad(Vec<i64>),
+        // `size` zero bytes
+        Zero(usize),
     }
 
     impl fmt::Display for Directive {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            write!(
+            writeln!(
                 f,
-                "{}",
-                format!(
-                    "\t.{}\n",
-                    match self {
-                        Directive::File(s) => format!("file\t{}", s),
-                        Directive::Def(s) => format!("def\t{}", s),
-                        Directive::Text => "text".into(),
-                        Directive::Ascii(s) => format!("ascii\t{}", s),
-                        Directive::Global(s) => format!("globl\t{}", s),
-                        Directive::Comm { name, size } =>
-                            format!("comm\tv{}, {}", name, usize::from(*size).to_string()),
-                    }
-                )
+                "\t.{}",
+                match self {
+                    Directive::File(s) => format!("file\t{}", s),
+                    Directive::Def(s) => format!("def\t{}", s),
+                    Directive::Text => "text".into(),
+                    Directive::Data => "data".into(),
+                    Directive::Rodata => "section\t.rodata".into(),
+                    Directive::Ascii(s) => format!("ascii\t{}", s),
+                    Directive::String(s) => format!("string\t\"{}\"", escape(s)),
+                    Directive::Global(s) => format!("globl\t{}", s),
+                    Directive::Local(s) => format!("local\t{}", s),
+                    Directive::Comm { name, size, align } =>
+                        format!("comm\t{}, {}, {}", name, size, align),
+                    Directive::Align(align) => format!("align\t{}", align),
+                    Directive::Byte(values) => format!("byte\t{}", join(values)),
+                    Directive::Word(values) => format!("value\t{}", join(values)),
+                    Directive::Long(values) => format!("long\t{}", join(values)),
+                    Directive::Quad(values) => format!("quad\t{}", join(values)),
+                    Directive::Zero(size) => format!("zero\t{}", size),
+                }
             )
         }
     }
+
+    /// Escapes `string` for a string directive, writing bytes that are not printable in octal
+    fn escape(string: &str) -> String {
+        string
+            .bytes()
+            .map(|b| match b {
+                b'"' => "\\\"".into(),
+                b'\\' => "\\\\".into(),
+                b' '..=b'~' => (b as char).to_string(),
+                _ => format!("\\{:03o}", b),
+            })
+            .collect()
+    }
+
+    fn join(values: &[i64]) -> String {
+        values
+            .iter()
+            .map(i64::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
@@ -1,15 +1,18 @@
 use intermediate_code::{flow_graph::FlowGraph, icode::IntermediateCode};
-use syntax::SymbolTable;
+use syntax::{SymbolTable, FREESTANDING_RUNTIME};
 
+use crate::assembly::asm::{self, Directive};
+use crate::coloring::GraphColoring;
 use crate::emitter::CodeEmitter;
+use crate::linear_scan::LinearScan;
 use crate::output::{self, OutStream};
-use crate::reg_alloc::RegAlloc;
+use crate::reg_alloc::{AllocStrategy, RegAlloc, RegisterAllocator};
 use intermediate_code::ioperator::IOperator::*;
 
 pub struct CodeGenerator<'a> {
     out: OutStream,
     emitter: CodeEmitter<'a>,
-    // table: &'a SymbolTable,
+    table: &'a SymbolTable,
     icode: &'a IntermediateCode,
     // graph: &'a FlowGraph,
 }
@@ -20,12 +23,17 @@ impl<'a> CodeGenerator<'a> {
         table: &'a SymbolTable,
         graph: &'a FlowGraph,
         icode: &'a IntermediateCode,
+        strategy: AllocStrategy,
     ) -> Self {
-        let reg_alloc = RegAlloc::new(out.clone(), table, graph);
-        let emitter = CodeEmitter::new(out.clone(), reg_alloc, table);
+        let reg_alloc = RegAlloc::new(out.clone(), table, graph, icode);
+        let reg_alloc: Box<dyn RegisterAllocator<'a> + 'a> = match strategy {
+            AllocStrategy::GraphColoring => Box::new(GraphColoring::new(reg_alloc)),
+            AllocStrategy::LinearScan => Box::new(LinearScan::new(reg_alloc)),
+        };
+        let emitter = CodeEmitter::new(out.clone(), reg_alloc, table, graph);
         Self {
             out,
-            // table,
+            table,
             icode,
             // graph,
             emitter,
@@ -40,6 +48,20 @@ impl<'a> CodeGenerator<'a> {
         self.emitter.emit_global_decls();
     }
 
+    /// Stores every string literal in the read-only data section
+    pub fn generate_rodata(&mut self) {
+        let strings = self.table.get_strings();
+        if strings.is_empty() {
+            return;
+        }
+        self.write(&Directive::Rodata);
+        for (index, string) in strings.iter().enumerate() {
+            self.write(&asm::Label::string(index));
+            self.write(&Directive::String(string.clone()));
+        }
+        self.write(&Directive::Text);
+    }
+
     pub fn generate_code(&mut self) {
         for (line, stmt) in self.icode {
             log::trace!("{}. {}", line, stmt);
@@ -64,6 +86,11 @@ impl<'a> CodeGenerator<'a> {
                     let (lhs, rhs, ret) = stmt.get_triple();
                     self.emitter.emit_mul(lhs, rhs, &ret.id());
                 }
+                FAdd | FSub | FMul | FDiv => {
+                    let (lhs, rhs, ret) = stmt.get_triple();
+                    self.emitter
+                        .emit_real_arith(&stmt.operator, lhs, rhs, &ret.id());
+                }
                 Coerce => {
                     let src = stmt.operand1.as_ref().unwrap();
                     let dest = stmt.ret_target.as_ref().unwrap().id();
@@ -95,67 +122,78 @@ impl<'a> CodeGenerator<'a> {
                     self.emitter.emit_set(&stmt.operator, l, r, &ret_id);
                 }
                 Label => self.emitter.emit_label(&stmt.label_id()),
-                Mod | Div => {
+                Mod | Div | IDiv | Imod => {
                     let (l, r, ret) = stmt.get_triple();
                     let ret_id = ret.id();
                     self.emitter.emit_div_mod(l, r, &ret_id, &stmt.operator);
                 }
-                _ => todo!("{}", stmt),
+                And | Or => {
+                    let (l, r, ret) = stmt.get_triple();
+                    self.emitter.emit_logical(&stmt.operator, l, r, &ret.id());
+                }
+                Not => {
+                    let src = stmt.operand1.as_ref().unwrap();
+                    let dest = stmt.ret_target.as_ref().unwrap().id();
+                    self.emitter.emit_not(src, &dest);
+                }
+                Minus => {
+                    let src = stmt.operand1.as_ref().unwrap();
+                    let dest = stmt.ret_target.as_ref().unwrap().id();
+                    self.emitter.emit_minus(src, &dest);
+                }
+                BitAnd | BitOr | BitXor => {
+                    let (l, r, ret) = stmt.get_triple();
+                    self.emitter.emit_bitwise(&stmt.operator, l, r, &ret.id());
+                }
+                Shl | Sar | Shr => {
+                    let (l, r, ret) = stmt.get_triple();
+                    self.emitter.emit_shift(&stmt.operator, l, r, &ret.id());
+                }
+                BitNot => {
+                    let src = stmt.operand1.as_ref().unwrap();
+                    let dest = stmt.ret_target.as_ref().unwrap().id();
+                    self.emitter.emit_bit_not(src, &dest);
+                }
+                Array => {
+                    let (array, index, ret) = stmt.get_triple();
+                    self.emitter.emit_array_load(&array.id(), index, &ret.id());
+                }
+                ArrayAssign => {
+                    let (index, value, array) = stmt.get_triple();
+                    self.emitter.emit_array_store(&array.id(), index, value);
+                }
+                Unknown => unreachable!("{}", stmt),
+            }
+        }
+    }
+
+    /// Defines every builtin that has an assembly implementation.
+    /// With `freestanding`, these are the implementations that do not need libc,
+    /// along with the routines they share and the `_start` entry point.
+    pub fn generate_trailer(&self, freestanding: bool) {
+        if freestanding {
+            self.generate_start();
+            self.write(&FREESTANDING_RUNTIME);
+        }
+        for builtin in self.table.get_builtins() {
+            let assembly = if freestanding {
+                &builtin.freestanding_assembly
+            } else {
+                &builtin.assembly
+            };
+            if let Some(assembly) = assembly {
+                self.write(assembly);
             }
         }
     }
 
-    pub fn generate_trailer(&self) {
-        self.write(&concat!(
-            ".LC0:\n",
-            "\t.string \"%d\"\n",
-            "\t.globl readinteger\n",
-            "readinteger:\n",
-            "\tpushq\t%rbp\n",
-            "\tmovq\t%rsp, %rbp\n",
-            "\tsubq\t$16, %rsp\n",
-            "\tleaq\t-12(%rbp), %rsi\n",
-            "\tleaq\t.LC0(%rip), %rdi\n",
-            "\tcall\tscanf\n",
-            "\tmovl\t-12(%rbp), %eax\n",
-            "\tmovq\t%rbp, %rsp\n",
-            "\tpopq\t%rbp\n",
-            "\tret\n",
-            ".LC1:\n",
-            "\t.string \"%d\\n\"\n",
-            "\t.globl writeinteger\n",
-            "writeinteger:\n",
-            "\tpushq\t%rbp\n",
-            "\tmovl\t%edi, %esi\n",
-            "\tleaq\t.LC1(%rip), %rdi\n",
-            "\tcall\tprintf\n",
-            "\tpopq\t%rbp\n",
-            "\tret\n",
-            ".LC2:\n",
-            "\t.string \"%u\"\n",
-            "\t.globl readunsigned\n",
-            "readunsigned:\n",
-            "\tpushq\t%rbp\n",
-            "\tmovq\t%rsp, %rbp\n",
-            "\tsubq\t$16, %rsp\n",
-            "\tleaq\t-12(%rbp), %rsi\n",
-            "\tleaq\t.LC2(%rip), %rdi\n",
-            "\tcall\tscanf\n",
-            "\tmovl\t-12(%rbp), %eax\n",
-            "\tmovq\t%rbp, %rsp\n",
-            "\tpopq\t%rbp\n",
-            "\tret\n",
-            ".LC3:\n",
-            "\t.string \"%u\\n\"\n",
-            "\t.globl writeunsigned\n",
-            "writeunsigned:\n",
-            "\tpushq\t%rbp\n",
-            "\tmovl\t%edi, %esi\n",
-            "\tleaq\t.LC3(%rip), %rdi\n",
-            "\tcall\tprintf\n",
-            "\tpopq\t%rbp\n",
-            "\tret\n"
-        ));
+    /// Defines the entry point of a program without libc, which calls `main`
+    /// and exits with the value it returns
+    fn generate_start(&self) {
+        self.write(&Directive::Global("_start".into()));
+        self.write(&"_start:\n\txorl\t%ebp, %ebp\n\tandq\t$-16, %rsp\n\tcall\tmain\n");
+        // exit, with the value `main` returns as the status
+        self.write(&"\tmovl\t%eax, %edi\n\tmovl\t$60, %eax\n\tsyscall\n");
     }
 
     fn write(&self, contents: &impl ToString) {
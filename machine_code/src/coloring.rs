@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use syntax::SymbolId;
+
+use crate::reg_alloc::{FunctionScope, Placement, RegAlloc, RegisterAllocator, RegisterClass};
+use crate::register::RegisterName;
+
+/// Allocates registers by coloring the interference graph of a function.
+/// Variables that are live at the same time interfere and cannot share a register.
+pub struct GraphColoring<'a> {
+    base: RegAlloc<'a>,
+}
+
+impl<'a> GraphColoring<'a> {
+    pub fn new(base: RegAlloc<'a>) -> Self {
+        Self { base }
+    }
+
+    fn build_graph(
+        &self,
+        scope: &FunctionScope,
+        class: RegisterClass,
+    ) -> (InterferenceGraph, HashMap<SymbolId, Vec<RegisterName>>) {
+        let mut graph = InterferenceGraph::new();
+        let mut preferred: HashMap<SymbolId, Vec<RegisterName>> = HashMap::new();
+        // Callee-saved registers are saved once per function instead of at every call
+        let callee_saved_first: Vec<RegisterName> = class
+            .callee_saved()
+            .iter()
+            .chain(class.caller_saved())
+            .copied()
+            .collect();
+        for id in &scope.candidates {
+            graph.add_node(*id);
+        }
+        for line in &scope.lines {
+            let defs = self.base.get_defs(scope, line);
+            let live_out = self.base.get_live_out(scope, line);
+            for def in &defs {
+                for live in &live_out {
+                    graph.add_edge(*def, *live);
+                }
+            }
+            if self.base.icode().get_statement(*line).is_call() {
+                for live in live_out.iter().filter(|id| !defs.contains(id)) {
+                    preferred.insert(*live, callee_saved_first.clone());
+                }
+            }
+        }
+        // All params are defined on entry
+        let live_at_entry = self.base.get_live_out(scope, &scope.start);
+        for (idx, param) in scope.params.iter().enumerate() {
+            for other in scope.params[idx + 1..].iter().chain(&live_at_entry) {
+                graph.add_edge(*param, *other);
+            }
+        }
+        (graph, preferred)
+    }
+}
+
+impl<'a> RegisterAllocator<'a> for GraphColoring<'a> {
+    fn base(&self) -> &RegAlloc<'a> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut RegAlloc<'a> {
+        &mut self.base
+    }
+
+    fn assign_registers(
+        &mut self,
+        scope: &FunctionScope,
+        class: RegisterClass,
+    ) -> HashMap<SymbolId, Placement> {
+        let (graph, preferred) = self.build_graph(scope, class);
+        let spill_cost = self.base.get_spill_costs(scope);
+        let colors: Vec<RegisterName> = class
+            .caller_saved()
+            .iter()
+            .chain(class.callee_saved())
+            .copied()
+            .collect();
+        let (colored, spilled) = graph.color(&colors, &preferred, &spill_cost);
+        colored
+            .into_iter()
+            .map(|(id, color)| (id, Placement::Register(color)))
+            .chain(spilled.into_iter().map(|id| (id, Placement::Spilled)))
+            .collect()
+    }
+}
+
+/// Undirected graph connecting variables that are live at the same time
+/// and can therefore not share a register.
+#[derive(Default)]
+pub struct InterferenceGraph {
+    edges: HashMap<SymbolId, HashSet<SymbolId>>,
+}
+
+impl InterferenceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, id: SymbolId) {
+        self.edges.entry(id).or_default();
+    }
+
+    pub fn add_edge(&mut self, a: SymbolId, b: SymbolId) {
+        if a == b {
+            return;
+        }
+        self.edges.entry(a).or_default().insert(b);
+        self.edges.entry(b).or_default().insert(a);
+    }
+
+    pub fn contains(&self, id: &SymbolId) -> bool {
+        self.edges.contains_key(id)
+    }
+
+    pub fn degree(&self, id: &SymbolId) -> usize {
+        self.edges.get(id).map_or(0, |e| e.len())
+    }
+
+    /// Assigns one of `colors` to every node such that no two neighbours share a color,
+    /// following Chaitin's algorithm with Briggs' optimistic coloring.
+    /// A node in `preferred` tries its own order of the colors instead.
+    /// When the graph cannot be simplified any further, the node with the lowest
+    /// `spill_cost` relative to its degree is the first candidate to be spilled.
+    /// Returns the colored nodes and the nodes that have to be spilled.
+    pub fn color(
+        &self,
+        colors: &[RegisterName],
+        preferred: &HashMap<SymbolId, Vec<RegisterName>>,
+        spill_cost: &HashMap<SymbolId, f64>,
+    ) -> (HashMap<SymbolId, RegisterName>, Vec<SymbolId>) {
+        // Sorted for deterministic output
+        let mut remaining: Vec<SymbolId> = self.edges.keys().copied().collect();
+        remaining.sort_by_key(|id| id.0);
+        let mut degrees: HashMap<SymbolId, usize> =
+            remaining.iter().map(|id| (*id, self.degree(id))).collect();
+        let mut stack = Vec::with_capacity(remaining.len());
+
+        // Simplify
+        while !remaining.is_empty() {
+            let idx = remaining
+                .iter()
+                .position(|id| degrees[id] < colors.len())
+                .unwrap_or_else(|| {
+                    let cost = |id: &SymbolId| {
+                        spill_cost.get(id).copied().unwrap_or(0.0) / (degrees[id] + 1) as f64
+                    };
+                    let (idx, _) = remaining
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| cost(a).partial_cmp(&cost(b)).unwrap())
+                        .unwrap();
+                    idx
+                });
+            let id = remaining.remove(idx);
+            for neighbour in &self.edges[&id] {
+                if let Some(d) = degrees.get_mut(neighbour) {
+                    *d = d.saturating_sub(1);
+                }
+            }
+            degrees.remove(&id);
+            stack.push(id);
+        }
+
+        // Select
+        let mut colored: HashMap<SymbolId, RegisterName> = HashMap::new();
+        let mut spilled = vec![];
+        while let Some(id) = stack.pop() {
+            let taken: HashSet<RegisterName> = self.edges[&id]
+                .iter()
+                .filter_map(|n| colored.get(n).copied())
+                .collect();
+            let order = preferred.get(&id).map_or(colors, |order| order.as_slice());
+            match order.iter().find(|c| !taken.contains(c)) {
+                Some(color) => {
+                    colored.insert(id, *color);
+                }
+                None => spilled.push(id),
+            }
+        }
+        spilled.sort_by_key(|id| id.0);
+        (colored, spilled)
+    }
+}
@@ -1,4 +1,5 @@
 use intermediate_code::{
+    flow_graph::FlowGraph,
     ic_info::ICLineNumber,
     ioperand::IOperand,
     ioperator::{
@@ -6,14 +7,20 @@ use intermediate_code::{
         IOperatorSize::{self, *},
     },
 };
-use syntax::{ConstantNodeValue, ReturnType, SymbolId, SymbolName, SymbolTable, SymbolType};
+use syntax::{
+    ConstantNodeValue, Linkage, ReturnType, SymbolId, SymbolName, SymbolTable, SymbolType,
+};
 
 use crate::{
     assembly::asm::*,
     output::{self, OutStream},
-    reg_alloc::{AllocType, RegAlloc, StoredLocation},
-    register::{reg, RegisterName::*},
+    reg_alloc::{
+        RegisterAllocator, StackFrame, StoredLocation, ADDRESS_REG, CALLER_SAVED_REGS, SCRATCH_REG,
+        SSE_ACCUMULATOR, SSE_REGS, SSE_SCRATCH_REG, STACK_ALIGN, STACK_ARG_SIZE,
+    },
+    register::{reg, Register, RegisterName, RegisterName::*},
 };
+use std::convert::TryFrom;
 
 pub enum SignChange {
     SignedToUnsigned,
@@ -29,26 +36,42 @@ pub enum CastType {
 }
 pub struct CodeEmitter<'a> {
     out: OutStream,
-    reg_alloc: RegAlloc<'a>,
+    reg_alloc: Box<dyn RegisterAllocator<'a> + 'a>,
     table: &'a SymbolTable,
+    graph: &'a FlowGraph,
     line: ICLineNumber,
     current_func: Option<SymbolId>,
+    call_params: Vec<IOperand>,
+    /// Callee-saved registers used by the current function
+    saved_regs: Vec<RegisterName>,
 }
 
 impl<'a> CodeEmitter<'a> {
-    pub fn new(out: OutStream, reg_alloc: RegAlloc<'a>, table: &'a SymbolTable) -> Self {
+    pub fn new(
+        out: OutStream,
+        reg_alloc: Box<dyn RegisterAllocator<'a> + 'a>,
+        table: &'a SymbolTable,
+        graph: &'a FlowGraph,
+    ) -> Self {
         CodeEmitter {
             out,
             reg_alloc,
             table,
+            graph,
             line: ICLineNumber(1),
             current_func: None,
+            call_params: vec![],
+            saved_regs: vec![],
         }
     }
 
     pub fn set_line(&mut self, line: ICLineNumber) {
         self.line = line;
         self.reg_alloc.set_line(line);
+        for (register, slot) in self.reg_alloc.get_split_stores() {
+            let instr = instr(Op::Mov(register.optype), register, Dest::Stack(slot));
+            self.write(&instr);
+        }
     }
 
     pub fn emit_global_decls(&mut self) {
@@ -89,9 +112,10 @@ impl<'a> CodeEmitter<'a> {
             assert_eq!(op, Op::Jne);
             Src::Immediate(ConstantNodeValue::from(0))
         };
-        if let Src::Immediate(l) = l {
-            if let Src::Immediate(r) = r {
-                // Fold two constants because a cmp instruction cannot operate on two constants
+        if let (Src::Immediate(l), Src::Immediate(r)) = (&l, &r) {
+            // Fold two constants because a cmp instruction cannot operate on two constants.
+            // The ordering of constants does not hold for NaN, which is compared at runtime.
+            if !l.is_nan() && !r.is_nan() {
                 let holds = match op {
                     Op::Je => l == r,
                     Op::Jne => l != r,
@@ -107,12 +131,21 @@ impl<'a> CodeEmitter<'a> {
                 return;
             }
         }
-        // AT&T syntax means l and r are reversed for cmp
-        let comp_instr = instr2(Op::Comp(ret.into()), r, l);
-        self.write(&comp_instr);
-        let label_name = self.get_label_name(label);
-        let jump_instr = instr(op, Src::Label(label_name.to_string()), Dest::None);
-        self.write(&jump_instr);
+        self.emit_compare(l, r, ret);
+        let label_name = Src::Label(self.get_label_name(label).to_string());
+        match op {
+            // A NaN operand sets the parity flag, making == false and != true
+            Op::Je if ret == ReturnType::Real => {
+                self.write(&instr(Op::Jp, Src::Label("1f".into()), Dest::None));
+                self.write(&instr(op, label_name, Dest::None));
+                self.write(&Label::new(1));
+            }
+            Op::Jne if ret == ReturnType::Real => {
+                self.write(&instr(op, label_name.clone(), Dest::None));
+                self.write(&instr(Op::Jp, label_name, Dest::None));
+            }
+            _ => self.write(&instr(op, label_name, Dest::None)),
+        }
     }
 
     pub fn emit_label(&self, id: &SymbolId) {
@@ -129,34 +162,47 @@ impl<'a> CodeEmitter<'a> {
     pub fn emit_func(&mut self, id: &SymbolId) {
         self.current_func = Some(*id);
         let name = self.table.get_symbol(id).unwrap().name.clone().0;
-        self.write(&Directive::Global(name.clone()));
+        if self.table.get_linkage(id) != Some(Linkage::Internal) {
+            self.write(&Directive::Global(name.clone()));
+        }
         self.write(&format!("{}:\n", name));
-        self.emit_prologue();
-        let params = self.table.get_func_param_ids(id).unwrap();
-        let locals_size = self.reg_alloc.alloc_stack_locals(id);
-        if locals_size.0 > 0 {
-            let instr = instr(
-                Op::Sub(Quad),
-                Src::Immediate(ConstantNodeValue::from(locals_size.0 as i64)),
-                reg(Rsp, Quad),
-            );
+        let frame = self.reg_alloc.alloc_function(id, self.line);
+        self.emit_prologue(&frame);
+        self.saved_regs = frame.saved_regs;
+        // A param register can be the location of another param
+        self.emit_parallel_move(frame.reg_params);
+        for (offset, register) in frame.stack_params {
+            let instr = instr(Op::Mov(register.optype), Src::Stack(offset), register);
             self.write(&instr);
         }
-        self.reg_alloc.alloc_func_params(params);
     }
 
-    /// Emits a function prologue
-    pub fn emit_prologue(&self) {
+    /// Emits a function prologue, which sets up the stack frame and saves the callee-saved registers
+    pub fn emit_prologue(&self, frame: &StackFrame) {
         let rbp = reg(Rbp, Quad);
         let rsp = reg(Rsp, Quad);
         let push_base_p = instr(Op::Push(Quad), rbp, Dest::None);
         let save_stack_p = instr(Op::Mov(Quad), rsp, rbp);
         self.write(&push_base_p);
         self.write(&save_stack_p);
+        if frame.size > 0 {
+            let instr = instr(
+                Op::Sub(Quad),
+                Src::Immediate(ConstantNodeValue::from(frame.size as i64)),
+                rsp,
+            );
+            self.write(&instr);
+        }
+        for saved in frame.saved_regs.iter() {
+            self.write(&instr(Op::Push(Quad), reg(*saved, Quad), Dest::None));
+        }
     }
 
-    /// Emits a function epilogue
+    /// Emits a function epilogue, which restores the callee-saved registers and the caller's frame
     pub fn emit_epilogue(&self) {
+        for saved in self.saved_regs.iter().rev() {
+            self.write(&instr(Op::Pop(Quad), reg(*saved, Quad), Dest::None));
+        }
         let leave = instr(Op::Leave, Src::None, Dest::None);
         self.write(&leave);
         self.write(&instr(Op::Ret, Src::None, Dest::None));
@@ -170,200 +216,513 @@ impl<'a> CodeEmitter<'a> {
         };
         let size = ret_type.into();
         if size != IOperatorSize::Void {
-            let rax = reg(Rax, size);
-            let instr = instr(Op::Mov(size), src, rax);
-            self.write(&instr);
+            let accumulator = Self::get_accumulator(ret_type);
+            self.emit_move(size, src, reg(accumulator, size));
+        } else if self.current_func.is_some() && self.current_func == self.table.get_main_id() {
+            // The exit status of a program whose `main` returns nothing is 0
+            self.emit_move(
+                Double,
+                Src::Immediate(ConstantNodeValue::from(0)),
+                reg(Rax, Double),
+            );
         }
         // Every function has an implicit return and will therefore have an epilogue
         self.emit_epilogue();
     }
 
+    /// Params are passed when the call is emitted, since the ones on the stack are pushed in reverse
     pub fn emit_param(&mut self, value: &IOperand) {
-        let size = value.ret_type().into();
-        let dest: Dest = (&self.reg_alloc.alloc_call_param(size)).into();
-        let src = match value {
-            IOperand::Immediate { value, .. } => Src::Immediate(*value),
-            IOperand::Symbol { id, .. } => {
-                let loc = self.reg_alloc.alloc_var(id, AllocType::Read);
-                loc.into()
-            }
-            _ => panic!("Unknown operand value {}", value),
-        };
-        let instr = instr(Op::Mov(size), src, dest);
-        self.write(&instr);
+        self.call_params.push(value.clone());
     }
 
+    /// Emits a call following the System V calling convention:
+    /// the first six integer params and the first eight real params are passed in registers,
+    /// the rest is pushed right-to-left.
     pub fn emit_call(&mut self, id: &SymbolId, ret: &Option<SymbolId>) {
+        let mut reg_params = vec![];
+        let mut stack_params = vec![];
+        for param in std::mem::take(&mut self.call_params) {
+            match self.reg_alloc.alloc_call_param(param.ret_type()) {
+                Some(register) => reg_params.push((param, register)),
+                None => stack_params.push(param),
+            }
+        }
+        // The callee may overwrite caller-saved registers
+        let saved_regs = self.get_live_caller_saved(ret);
+        for saved in saved_regs.iter() {
+            self.emit_push(*saved);
+        }
+        // %rsp must be 16-byte aligned at the call
+        let stack_size = stack_params.len() * STACK_ARG_SIZE;
+        let pushed = stack_size + saved_regs.len() * STACK_ARG_SIZE;
+        let padding = (STACK_ALIGN - (pushed % STACK_ALIGN)) % STACK_ALIGN;
+        if padding > 0 {
+            let padding = Src::Immediate(ConstantNodeValue::from(padding as i64));
+            self.write(&instr(Op::Sub(Quad), padding, reg(Rsp, Quad)));
+        }
+        for param in stack_params.iter().rev() {
+            let rax = reg(Rax, Quad);
+            self.emit_load_param(param, rax);
+            self.write(&instr(Op::Push(Quad), rax, Dest::None));
+        }
+        let sources_in_param_regs = reg_params.iter().any(|(param, _)| match param {
+            IOperand::Symbol { id, .. } => match self.reg_alloc.alloc_var(id) {
+                StoredLocation::Reg(r) => reg_params.iter().any(|(_, dest)| dest.name == r.name),
+                _ => false,
+            },
+            _ => false,
+        });
+        if sources_in_param_regs {
+            // Loading one argument could overwrite the source of another
+            for (param, _) in reg_params.iter() {
+                let rax = reg(Rax, Quad);
+                self.emit_load_param(param, rax);
+                self.write(&instr(Op::Push(Quad), rax, Dest::None));
+            }
+            for (_, register) in reg_params.iter().rev() {
+                self.emit_pop(register.name);
+            }
+        } else {
+            for (param, register) in reg_params.iter() {
+                self.emit_load_param(param, *register);
+            }
+        }
+
         let func_sym = self.table.get_symbol(id).unwrap();
         let func_name = func_sym.name.clone().0;
-        let func_ret_size = func_sym.return_type.into();
+        let func_ret_type = func_sym.return_type;
+        let func_ret_size = func_ret_type.into();
         self.write(&instr(Op::Call, Src::Label(func_name), Dest::None));
+        if stack_size + padding > 0 {
+            let cleanup = Src::Immediate(ConstantNodeValue::from((stack_size + padding) as i64));
+            self.write(&instr(Op::Add(Quad), cleanup, reg(Rsp, Quad)));
+        }
         if let Some(ret) = ret {
             if func_ret_size != IOperatorSize::Void {
                 let size = self.table.get_symbol(ret).unwrap().return_type.into();
-                let dest = self.reg_alloc.alloc_var(ret, AllocType::Write);
-                let rax = reg(Rax, func_ret_size);
-                let instr = instr(Op::Mov(size), rax, &dest);
+                let dest = self.reg_alloc.alloc_var(ret);
+                let accumulator = reg(Self::get_accumulator(func_ret_type), func_ret_size);
+                let instr = instr(Op::Mov(size), accumulator, &dest);
                 self.write(&instr);
             }
         }
+        for saved in saved_regs.iter().rev() {
+            self.emit_pop(*saved);
+        }
         self.reg_alloc.free_param_regs();
     }
 
+    /// The caller-saved registers holding variables that are still needed after the call
+    /// at the current line, excluding the variable receiving the return value
+    fn get_live_caller_saved(&self, ret: &Option<SymbolId>) -> Vec<RegisterName> {
+        let mut live: Vec<RegisterName> = self
+            .graph
+            .get_live_out_at(&self.line)
+            .iter()
+            .filter(|id| Some(**id) != *ret)
+            .filter_map(|id| match self.reg_alloc.alloc_var(id) {
+                StoredLocation::Reg(r)
+                    if CALLER_SAVED_REGS.contains(&r.name) || SSE_REGS.contains(&r.name) =>
+                {
+                    Some(r.name)
+                }
+                _ => None,
+            })
+            .collect();
+        live.sort();
+        live.dedup();
+        live
+    }
+
     /// Emits one of the following casts:
-    /// ```
+    /// ```text
     /// From      To
-    /// UINT      BOOL
-    /// INT       UINT, BOOL
-    /// UINT8     INT, UINT, BOOL
-    /// INT8      UINT8, INT, UINT, BOOL
-    /// BOOL      INT8, UINT8, INT, UINT
+    /// UINT64    BOOL
+    /// INT64     UINT64, BOOL
+    /// UINT      INT64, UINT64, BOOL
+    /// INT       UINT, INT64, UINT64, BOOL
+    /// UINT16    INT, UINT, INT64, UINT64, BOOL
+    /// INT16     UINT16, INT, UINT, INT64, UINT64, BOOL
+    /// UINT8     INT16, UINT16, INT, UINT, INT64, UINT64, BOOL
+    /// INT8      UINT8, INT16, UINT16, INT, UINT, INT64, UINT64, BOOL
+    /// BOOL      INT8, UINT8, INT16, UINT16, INT, UINT, INT64, UINT64, REAL
+    /// REAL      BOOL
     /// ```
+    /// An explicit cast can also narrow, from any of these types to another, by truncating.
+    /// Integers are converted to a real as 64-bit values, so unsigned values keep their value.
+    /// Smaller values are sign- or zero-extended with `movs`/`movz`.
     pub fn emit_cast(&mut self, src: &IOperand, dest: &SymbolId) {
-        let is_immediate = matches!(src, IOperand::Immediate { .. });
+        let immediate = match src {
+            IOperand::Immediate { value, .. } => Some(*value),
+            _ => None,
+        };
         let (src, src_type) = self.get_source(src);
         let dest_type = self.table.get_symbol(dest).unwrap().return_type;
         let src_size = src_type.into();
         let dest_size: IOperatorSize = dest_type.into();
-        let dest = self.reg_alloc.alloc_var(dest, AllocType::Write);
+        let dest = self.reg_alloc.alloc_var(dest);
 
-        if is_immediate {
-            let instr = instr(Op::Mov(dest_size), src, &dest);
-            return self.write(&instr);
+        if let Some(value) = immediate {
+            let value = Src::Immediate(value.cast(dest_type));
+            return self.emit_move(dest_size, value, &dest);
+        }
+        if src_type == ReturnType::Real || dest_type == ReturnType::Real {
+            return self.emit_real_cast(src, src_type, dest_type, &dest);
         }
 
+        if dest_type == ReturnType::Bool {
+            let zero = ConstantNodeValue::from(0);
+            let instr_cmp = instr2(Op::Comp(src_size), Src::Immediate(zero), src);
+            let instr_setne = instr(Op::SetNE, &dest, Dest::None);
+            self.write(&instr_cmp);
+            self.write(&instr_setne);
+            return;
+        }
         match Self::get_cast_type(src_type, dest_type) {
             CastType::Downcast => {
-                // Downcast can only be x -> boolean
-                let zero = ConstantNodeValue::from(0);
-                let instr_cmp = instr(Op::Comp(src_size), src, Dest::Immediate(zero));
-                // let setne_src: Src = dest.into();
-                // let instr_setne = instr(Op::Setne, setne_src, Dest::None);
-                self.write(&instr_cmp);
-                // self.write(&instr_setne);
+                log::trace!("Downcast");
+                // Truncating keeps the lower bytes, which are at the same address in memory
+                let src = match src {
+                    Src::Register(r) => reg(r.name, dest_size).into(),
+                    src => src,
+                };
+                self.emit_move(dest_size, src, &dest);
             }
             CastType::Reinterpret => {
                 log::trace!("Reinterpret cast");
-                if let Src::Immediate(_) = src {
-                    let instr = instr(Op::Mov(src_size), src, &dest);
-                    self.write(&instr);
-                }
+                self.emit_move(src_size, src, &dest);
             }
-            CastType::Upcast(sign_change) => {
+            CastType::Upcast(_) => {
                 log::trace!("Upcast");
-                let instr = match sign_change {
-                    SignChange::UnsignedToUnsigned | SignChange::UnsignedToSigned => {
-                        instr(Op::Movz(src_size, dest_size), src, &dest)
-                    }
-                    SignChange::SignedToSigned | SignChange::SignedToUnsigned => {
-                        instr(Op::Movs(src_size, dest_size), src, &dest)
-                    }
-                };
-                self.write(&instr);
+                // movs/movz can only target a register
+                let rax = reg(Rax, dest_size);
+                self.emit_extend(src, src_type, rax);
+                self.emit_move(dest_size, rax, &dest);
+            }
+        }
+    }
+
+    /// Emits a cast from an integer type to a real, or from a real to any type
+    fn emit_real_cast(
+        &mut self,
+        src: Src,
+        src_type: ReturnType,
+        dest_type: ReturnType,
+        dest: &StoredLocation,
+    ) {
+        let xmm = reg(SSE_ACCUMULATOR, Quad);
+        let rax = reg(Rax, Quad);
+        match (src_type, dest_type) {
+            (ReturnType::Real, ReturnType::Real) => self.emit_move(Quad, src, dest),
+            (ReturnType::Real, ReturnType::Bool) => {
+                // Shifting out the sign bit leaves zero for both 0.0 and -0.0
+                self.emit_move(Quad, src, rax);
+                let one = Src::Immediate(ConstantNodeValue::from(1));
+                self.write(&instr(Op::Shl(Quad), one, rax));
+                self.write(&instr(Op::SetNE, dest, Dest::None));
+            }
+            (ReturnType::Real, ReturnType::Uint64) => {
+                // cvttsd2siq is signed, so values from 2^63 are lowered by 2^63 first,
+                // and get the top bit back after the conversion
+                let two_63 = reg(SSE_SCRATCH_REG, Quad);
+                let limit = Src::Immediate(ConstantNodeValue::Real(9223372036854775808.0));
+                let sign_bit = Src::Immediate(ConstantNodeValue::from(63));
+                self.emit_move(Quad, src, xmm);
+                self.emit_move(Quad, limit, two_63);
+                self.write(&instr2(Op::Ucomisd, two_63, xmm));
+                self.write(&instr(Op::Jae, Src::Label("1f".into()), Dest::None));
+                self.write(&instr(Op::Cvttsd2si, xmm, rax));
+                self.write(&instr(Op::Jmp, Src::Label("2f".into()), Dest::None));
+                self.write(&Label::new(1));
+                self.write(&instr(Op::Subsd, two_63, xmm));
+                self.write(&instr(Op::Cvttsd2si, xmm, rax));
+                self.write(&instr(Op::Btc(Quad), sign_bit, rax));
+                self.write(&Label::new(2));
+                self.emit_move(Quad, rax, dest);
+            }
+            (ReturnType::Real, _) => {
+                let dest_size = dest_type.into();
+                self.emit_move(Quad, src, xmm);
+                self.write(&instr(Op::Cvttsd2si, xmm, rax));
+                self.emit_move(dest_size, reg(Rax, dest_size), dest);
+            }
+            (ReturnType::Uint64, _) => {
+                // cvtsi2sdq is signed, so a value with the top bit set is halved first.
+                // The lowest bit is kept so the halved value rounds the same way.
+                let scratch = reg(SCRATCH_REG, Quad);
+                let one = || Src::Immediate(ConstantNodeValue::from(1));
+                self.emit_move(Quad, src, rax);
+                self.write(&instr(Op::Cvtsi2sd, rax, xmm));
+                self.write(&instr(Op::Test(Quad), rax, rax));
+                self.write(&instr(Op::Jns, Src::Label("1f".into()), Dest::None));
+                self.write(&instr(Op::Mov(Quad), rax, scratch));
+                self.write(&instr(Op::Shr(Quad), one(), scratch));
+                self.write(&instr(Op::And(Quad), one(), rax));
+                self.write(&instr(Op::Or(Quad), rax, scratch));
+                self.write(&instr(Op::Cvtsi2sd, scratch, xmm));
+                self.write(&instr(Op::Addsd, xmm, xmm));
+                self.write(&Label::new(1));
+                self.emit_move(Quad, xmm, dest);
+            }
+            _ => {
+                self.emit_extend(src, src_type, rax);
+                self.write(&instr(Op::Cvtsi2sd, rax, xmm));
+                self.emit_move(Quad, xmm, dest);
             }
         }
     }
 
+    /// Emits a real addition, subtraction, multiplication or division
+    pub fn emit_real_arith(
+        &mut self,
+        op: &IOperator,
+        lhs: &IOperand,
+        rhs: &IOperand,
+        ret: &SymbolId,
+    ) {
+        let (lhs, _) = self.get_source(lhs);
+        let (rhs, _) = self.get_source(rhs);
+        let op = match *op {
+            IOperator::FAdd => Op::Addsd,
+            IOperator::FSub => Op::Subsd,
+            IOperator::FMul => Op::Mulsd,
+            IOperator::FDiv => Op::Divsd,
+            _ => unreachable!(),
+        };
+        let xmm = reg(SSE_ACCUMULATOR, Quad);
+        self.emit_move(Quad, lhs, xmm);
+        let rhs = self.emit_real_operand(rhs);
+        self.write(&instr(op, rhs, xmm));
+        let ret = self.reg_alloc.alloc_var(ret);
+        self.emit_move(Quad, xmm, &ret);
+    }
+
     pub fn emit_add(&mut self, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
         let (lhs, ret_type) = self.get_source(lhs);
         let size = ret_type.into();
         let (rhs, _) = self.get_source(rhs);
-        let ret = self.reg_alloc.alloc_var(ret, AllocType::Write);
+        let ret = self.reg_alloc.alloc_var(ret);
 
-        if let Src::Immediate(x) = lhs {
-            if let Src::Immediate(y) = rhs {
+        match (lhs, rhs) {
+            (Src::Immediate(x), Src::Immediate(y)) => {
                 // Constant-fold two immediates
-                let instr = instr(Op::Mov(size), Src::Immediate(x + y), &ret);
-                self.write(&instr);
+                self.emit_move(size, Src::Immediate(x + y), &ret);
             }
-        } else {
-            let instr1 = instr(Op::Mov(size), lhs, &ret);
-            let instr2 = instr(Op::Add(size), rhs, &ret);
-            self.write(&instr1);
-            self.write(&instr2);
-        };
+            (lhs, rhs) => self.emit_accumulate(Op::Add(size), lhs, rhs, size, &ret),
+        }
     }
 
     pub fn emit_assign(&mut self, src: &IOperand, dest: &SymbolId) {
         let (src, src_ret) = self.get_source(src);
         log::trace!("{} {}", src, src_ret);
-        let dest = self.reg_alloc.alloc_var(dest, AllocType::Write);
-        let instr = instr(Op::Mov(src_ret.into()), src, &dest);
-        self.write(&instr);
+        let dest = self.reg_alloc.alloc_var(dest);
+        self.emit_move(src_ret.into(), src, &dest);
     }
 
     pub fn emit_sub(&mut self, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
         let (lhs, ret_type) = self.get_source(lhs);
         let size = ret_type.into();
         let (rhs, _) = self.get_source(rhs);
-        let ret = self.reg_alloc.alloc_var(ret, AllocType::Write);
+        let ret = self.reg_alloc.alloc_var(ret);
 
-        if let Src::Immediate(x) = lhs {
-            if let Src::Immediate(y) = rhs {
+        match (lhs, rhs) {
+            (Src::Immediate(x), Src::Immediate(y)) => {
                 // Constant-fold two immediates
-                let instr = instr(Op::Mov(size), Src::Immediate(x - y), &ret);
-                self.write(&instr);
+                self.emit_move(size, Src::Immediate(x - y), &ret);
             }
-        } else {
-            let instr1 = instr(Op::Mov(size), lhs, &ret);
-            let instr2 = instr(Op::Sub(size), rhs, &ret);
-            self.write(&instr1);
-            self.write(&instr2);
-        };
+            (lhs, rhs) => self.emit_accumulate(Op::Sub(size), lhs, rhs, size, &ret),
+        }
     }
 
     pub fn emit_mul(&mut self, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
-        let is_unsigned = lhs.ret_type().is_unsigned();
         let (lhs, ret_type) = self.get_source(lhs);
         let size = ret_type.into();
-        let (rhs, _) = self.get_source(rhs);
-        let ret = self.reg_alloc.alloc_var(ret, AllocType::Write);
-        if let Src::Immediate(x) = lhs {
-            if let Src::Immediate(y) = rhs {
+        let (rhs, rhs_type) = self.get_source(rhs);
+        let ret = self.reg_alloc.alloc_var(ret);
+        match (lhs, rhs) {
+            (Src::Immediate(x), Src::Immediate(y)) => {
                 // Constant-fold two immediates
-                let instr = instr(Op::Mov(size), Src::Immediate(x * y), &ret);
-                self.write(&instr);
+                self.emit_move(size, Src::Immediate(x * y), &ret);
             }
-        } else {
-            let op = if is_unsigned {
-                Op::Mul(size)
-            } else {
-                Op::IMul(size)
-            };
-            let instr1 = instr(Op::Mov(size), lhs, &ret);
-            let instr2 = instr(op, rhs, &ret);
-            self.write(&instr1);
-            self.write(&instr2);
-        };
+            (lhs, rhs) => {
+                // The lower half of the product is the same for signed and unsigned operands,
+                // but imul has no two-operand form for bytes.
+                let mul_size = size.max(Double);
+                let rax = reg(Rax, mul_size);
+                let factor = reg(SCRATCH_REG, mul_size);
+                self.emit_extend(lhs, ret_type, rax);
+                self.emit_extend(rhs, rhs_type, factor);
+                self.write(&instr(Op::IMul(mul_size), factor, rax));
+                self.emit_move(size, reg(Rax, size), &ret);
+            }
+        }
     }
 
+    /// Emits a (signed) division or modulo. Operands smaller than 32 bits are extended first.
     pub fn emit_div_mod(&mut self, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId, op: &IOperator) {
-        let (lhs, _) = self.get_source(lhs);
+        let (lhs, lhs_type) = self.get_source(lhs);
+        let (rhs, rhs_type) = self.get_source(rhs);
+        let size = IOperatorSize::from(lhs_type).max(Double);
+        let rax = reg(Rax, size);
+        let rdx = reg(Rdx, size);
         // divisor must be in a register
-        let (rhs, _) = self.get_reg_source(rhs);
-
-        let mov_instr = instr(Op::Mov(Double), lhs, reg(Rax, Double));
-        self.write(&mov_instr);
-        let edx = reg(Rdx, Double);
-        let xor_instr = instr(Op::Xor(Double), edx, edx);
-        self.write(&xor_instr);
-        let div_instr = instr(Op::Div, &rhs, Dest::None);
+        let divisor = reg(SCRATCH_REG, size);
+        self.emit_extend(lhs, lhs_type, rax);
+        self.emit_extend(rhs, rhs_type, divisor);
+
+        // %rdx is clobbered by the division but may hold a parameter
+        let saved_rdx = reg(ADDRESS_REG, Quad);
+        self.write(&instr(Op::Mov(Quad), reg(Rdx, Quad), saved_rdx));
+        let div_instr = match *op {
+            IOperator::IDiv | IOperator::Imod => {
+                self.write(&instr(Op::Cqto(size), Src::None, Dest::None));
+                instr(Op::IDiv(size), divisor, Dest::None)
+            }
+            IOperator::Div | IOperator::Mod => {
+                self.write(&instr(Op::Xor(size), rdx, rdx));
+                instr(Op::Div(size), divisor, Dest::None)
+            }
+            _ => unreachable!(),
+        };
         self.write(&div_instr);
         let ret_size = self.table.get_symbol(ret).unwrap().return_type.into();
-        let dest = self.reg_alloc.alloc_var(ret, AllocType::Write);
-        let ret_val = if *op == IOperator::Mod {
-            reg(Rdx, ret_size)
-        } else if *op == IOperator::Div {
-            reg(Rax, ret_size)
+        let result = if matches!(*op, IOperator::Mod | IOperator::Imod) {
+            Rdx
         } else {
-            unreachable!()
+            Rax
+        };
+        self.write(&instr(
+            Op::Mov(ret_size),
+            reg(result, ret_size),
+            reg(SCRATCH_REG, ret_size),
+        ));
+        self.write(&instr(Op::Mov(Quad), saved_rdx, reg(Rdx, Quad)));
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(ret_size, reg(SCRATCH_REG, ret_size), &dest);
+    }
+
+    /// Emits a logical AND or OR, which results in either 0 or 1.
+    pub fn emit_logical(&mut self, op: &IOperator, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
+        let l = self.emit_to_bool(lhs, Rax);
+        let r = self.emit_to_bool(rhs, SCRATCH_REG);
+        let op = match *op {
+            IOperator::And => Op::And(Byte),
+            IOperator::Or => Op::Or(Byte),
+            _ => unreachable!(),
         };
-        let mov_to_ret = instr(Op::Mov(ret_size), ret_val, &dest);
-        self.write(&mov_to_ret);
+        self.write(&instr(op, r, l));
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(Byte, l, &dest);
+    }
 
-        if let StoredLocation::TempReg(_, id) = rhs {
-            self.reg_alloc.free_temp(&id);
+    /// Emits a logical NOT, which results in either 0 or 1.
+    pub fn emit_not(&mut self, src: &IOperand, ret: &SymbolId) {
+        let (src, src_type) = self.get_source(src);
+        let size = src_type.into();
+        let rax = reg(Rax, size);
+        self.emit_move(size, src, rax);
+        self.write(&instr(Op::Test(size), rax, rax));
+        self.write(&instr(Op::SetE, reg(Rax, Byte), Dest::None));
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(Byte, reg(Rax, Byte), &dest);
+    }
+
+    pub fn emit_minus(&mut self, src: &IOperand, ret: &SymbolId) {
+        let (src, src_type) = self.get_source(src);
+        let size = src_type.into();
+        let rax = reg(Rax, size);
+        self.emit_move(size, src, rax);
+        if src_type == ReturnType::Real {
+            // Negating a real flips its sign bit
+            let sign_bit = Src::Immediate(ConstantNodeValue::from(63));
+            self.write(&instr(Op::Btc(Quad), sign_bit, rax));
+        } else {
+            self.write(&instr(Op::Neg(size), rax, Dest::None));
+        }
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(size, rax, &dest);
+    }
+
+    pub fn emit_bit_not(&mut self, src: &IOperand, ret: &SymbolId) {
+        let (src, src_type) = self.get_source(src);
+        let size = src_type.into();
+        let rax = reg(Rax, size);
+        self.emit_move(size, src, rax);
+        self.write(&instr(Op::Not(size), rax, Dest::None));
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(size, rax, &dest);
+    }
+
+    /// Emits a bitwise AND, OR or XOR
+    pub fn emit_bitwise(&mut self, op: &IOperator, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
+        let (lhs, ret_type) = self.get_source(lhs);
+        let size = ret_type.into();
+        let (rhs, _) = self.get_source(rhs);
+        let ret = self.reg_alloc.alloc_var(ret);
+        let op = match *op {
+            IOperator::BitAnd => Op::And(size),
+            IOperator::BitOr => Op::Or(size),
+            IOperator::BitXor => Op::Xor(size),
+            _ => unreachable!(),
+        };
+        self.emit_accumulate(op, lhs, rhs, size, &ret);
+    }
+
+    /// Emits a left shift, or an arithmetic or logical right shift.
+    /// A shift count that is not an immediate has to be in %cl.
+    pub fn emit_shift(&mut self, op: &IOperator, lhs: &IOperand, rhs: &IOperand, ret: &SymbolId) {
+        let (lhs, ret_type) = self.get_source(lhs);
+        let size = ret_type.into();
+        let (rhs, rhs_type) = self.get_source(rhs);
+        let op = match *op {
+            IOperator::Shl => Op::Shl(size),
+            IOperator::Sar => Op::Sar(size),
+            IOperator::Shr => Op::Shr(size),
+            _ => unreachable!(),
+        };
+        let rax = reg(Rax, size);
+        self.emit_move(size, lhs, rax);
+        if let Src::Immediate(_) = rhs {
+            self.write(&instr(op, rhs, rax));
+        } else {
+            // %rcx may hold a variable or a parameter
+            let saved_rcx = reg(ADDRESS_REG, Quad);
+            self.write(&instr(Op::Mov(Quad), reg(Rcx, Quad), saved_rcx));
+            self.emit_extend(rhs, rhs_type, reg(Rcx, Double));
+            self.write(&instr(op, reg(Rcx, Byte), rax));
+            self.write(&instr(Op::Mov(Quad), saved_rcx, reg(Rcx, Quad)));
         }
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(size, rax, &dest);
+    }
+
+    /// Emits `ret = array[index]`
+    pub fn emit_array_load(&mut self, array: &SymbolId, index: &IOperand, ret: &SymbolId) {
+        let size: IOperatorSize = self.table.get_symbol(ret).unwrap().return_type.into();
+        let index = self.emit_array_index(index);
+        let base = self.emit_array_base(array);
+        let element = Src::Indexed(base, index, size.into());
+        let dest = self.reg_alloc.alloc_var(ret);
+        self.emit_move(size, element, &dest);
+    }
+
+    /// Emits `array[index] = value`
+    pub fn emit_array_store(&mut self, array: &SymbolId, index: &IOperand, value: &IOperand) {
+        let elem_type = self.table.get_symbol(array).unwrap().return_type;
+        let size: IOperatorSize = elem_type.to_base_type().into();
+        let (value, _) = self.get_source(value);
+        let value = match value {
+            Src::Immediate(_) if !Self::is_wide_immediate(&value, size) => value,
+            _ => {
+                let rax = reg(Rax, size);
+                self.emit_move(size, value, rax);
+                rax.into()
+            }
+        };
+        let index = self.emit_array_index(index);
+        let base = self.emit_array_base(array);
+        let element = Dest::Indexed(base, index, size.into());
+        self.write(&instr(Op::Mov(size), value, element));
     }
 
     pub fn emit_set(
@@ -375,8 +734,7 @@ impl<'a> CodeEmitter<'a> {
     ) {
         let (l, ret) = self.get_source(lhs);
         let (r, _) = self.get_source(rhs);
-        let size = ret.into();
-        let dest = self.reg_alloc.alloc_var(dest, AllocType::Write);
+        let dest = self.reg_alloc.alloc_var(dest);
         let op = match *set_type {
             IOperator::SetE => Op::SetE,
             IOperator::SetNE => Op::SetNE,
@@ -391,35 +749,264 @@ impl<'a> CodeEmitter<'a> {
             _ => unreachable!(),
         };
         log::trace!("COMP; {} {}", l, r);
-        let cmp_instr = instr2(Op::Comp(size), r, l);
-        self.write(&cmp_instr);
+        self.emit_compare(l, r, ret);
+        // A NaN operand sets the parity flag, making == false and != true
+        let parity_check = match op {
+            Op::SetE if ret == ReturnType::Real => Some((Op::SetNP, Op::And(Byte))),
+            Op::SetNE if ret == ReturnType::Real => Some((Op::SetP, Op::Or(Byte))),
+            _ => None,
+        };
         let set_instr = instr(op, &dest, Dest::None);
         self.write(&set_instr);
+        if let Some((set_parity, combine)) = parity_check {
+            let parity = reg(SCRATCH_REG, Byte);
+            self.write(&instr(set_parity, parity, Dest::None));
+            self.write(&instr(combine, parity, &dest));
+        }
     }
 
     fn get_source(&mut self, src: &IOperand) -> (Src, ReturnType) {
         match *src {
             IOperand::Immediate { value, ret_type } => (Src::Immediate(value), ret_type),
             IOperand::Symbol { id, ret_type } => {
-                let r = self.reg_alloc.alloc_var(&id, AllocType::Read);
+                let r = self.reg_alloc.alloc_var(&id);
                 (r.into(), ret_type)
             }
-            IOperand::Unknown => unreachable!(),
+            IOperand::String { .. } | IOperand::Unknown => unreachable!(),
         }
     }
 
-    fn get_reg_source(&mut self, src: &IOperand) -> (StoredLocation, ReturnType) {
-        match *src {
-            IOperand::Immediate { ret_type, .. } => {
-                let (id, regname) = self.reg_alloc.alloc_temp();
-                let reg = reg(regname, ret_type.into());
-                (StoredLocation::TempReg(reg, id), ret_type)
+    /// Loads the value of a call param into register `to`
+    fn emit_load_param(&mut self, param: &IOperand, to: Register) {
+        // String literals are passed by their address
+        if let IOperand::String { index } = param {
+            let label = Src::Global(Label::string(*index).0);
+            self.write(&instr(Op::Lea(Quad), label, reg(to.name, Quad)));
+            return;
+        }
+        if let IOperand::Symbol { id, .. } = param {
+            let sym = self.table.get_symbol(id).unwrap();
+            // Arrays are passed by reference
+            if sym.is_array() && !sym.is_param() {
+                let loc = self.reg_alloc.alloc_var(id);
+                self.write(&instr(Op::Lea(Quad), &loc, reg(to.name, Quad)));
+                return;
             }
-            IOperand::Symbol { id, ret_type } => {
-                let reg = self.reg_alloc.alloc_var(&id, AllocType::ReadReg);
-                (reg, ret_type)
+        }
+        let (src, src_type) = self.get_source(param);
+        self.emit_extend(src, src_type, to);
+    }
+
+    /// Moves every register in `moves` to its location.
+    /// Goes through the stack when a location is one of the source registers.
+    fn emit_parallel_move(&self, moves: Vec<(Register, StoredLocation)>) {
+        let overlaps = moves.iter().any(|(_, location)| match location {
+            StoredLocation::Reg(r) => moves.iter().any(|(src, _)| src.name == r.name),
+            _ => false,
+        });
+        if !overlaps {
+            for (src, location) in moves {
+                self.emit_move(src.optype, src, &location);
+            }
+            return;
+        }
+        for (src, _) in moves.iter() {
+            self.emit_push(src.name);
+        }
+        for (src, location) in moves.iter().rev() {
+            self.write(&instr(Op::Pop(Quad), reg(Rax, Quad), Dest::None));
+            self.emit_move(src.optype, reg(Rax, src.optype), location);
+        }
+    }
+
+    /// Pushes all 64 bits of register `name`.
+    /// SSE registers cannot be pushed, so they go through the accumulator.
+    fn emit_push(&self, name: RegisterName) {
+        let register = reg(name, Quad);
+        if register.is_sse() {
+            let rax = reg(Rax, Quad);
+            self.write(&instr(Op::Mov(Quad), register, rax));
+            self.write(&instr(Op::Push(Quad), rax, Dest::None));
+        } else {
+            self.write(&instr(Op::Push(Quad), register, Dest::None));
+        }
+    }
+
+    /// Pops 64 bits into register `name`, the reverse of [CodeEmitter::emit_push]
+    fn emit_pop(&self, name: RegisterName) {
+        let register = reg(name, Quad);
+        if register.is_sse() {
+            let rax = reg(Rax, Quad);
+            self.write(&instr(Op::Pop(Quad), rax, Dest::None));
+            self.write(&instr(Op::Mov(Quad), rax, register));
+        } else {
+            self.write(&instr(Op::Pop(Quad), register, Dest::None));
+        }
+    }
+
+    /// Moves `src` to `dest`, going through the scratch register if both are in memory.
+    /// Only `movabsq` takes a 64-bit immediate, which has to go through the accumulator
+    /// unless `dest` is a general purpose register.
+    fn emit_move(&self, size: IOperatorSize, src: impl Into<Src>, dest: impl Into<Dest>) {
+        let (src, dest) = (src.into(), dest.into());
+        if Self::is_wide_immediate(&src, size) {
+            if let Dest::Register(d) = &dest {
+                if !d.is_sse() {
+                    return self.write(&instr(Op::Movabs, src, reg(d.name, Quad)));
+                }
+            }
+            let rax = reg(Rax, Quad);
+            self.write(&instr(Op::Movabs, src, rax));
+            return self.emit_move(Quad, rax, dest);
+        }
+        if let (Src::Register(s), Dest::Register(d)) = (&src, &dest) {
+            if s.name == d.name {
+                return;
             }
-            IOperand::Unknown => unreachable!(),
+        }
+        if src.is_memory() && dest.is_memory() {
+            let scratch = reg(SCRATCH_REG, size);
+            self.write(&instr(Op::Mov(size), src, scratch));
+            self.write(&instr(Op::Mov(size), scratch, dest));
+        } else {
+            self.write(&instr(Op::Mov(size), src, dest));
+        }
+    }
+
+    /// Loads `src` into register `to`, sign- or zero-extending it to the size of `to`,
+    /// or truncating it if it is wider
+    fn emit_extend(&self, src: Src, src_type: ReturnType, to: Register) {
+        let from: IOperatorSize = src_type.into();
+        let is_unsigned = src_type.is_unsigned() || src_type == ReturnType::Bool;
+        if from > to.optype {
+            // Only the lower part is loaded, which for a register means using its narrower name
+            let src = match src {
+                Src::Register(r) => reg(r.name, to.optype).into(),
+                src => src,
+            };
+            return self.emit_move(to.optype, src, to);
+        }
+        if matches!(src, Src::Immediate(_)) || from == to.optype {
+            return self.emit_move(to.optype, src, to);
+        }
+        let instr = if is_unsigned && from == Double {
+            // Writing a 32-bit register clears the upper half
+            instr(Op::Mov(Double), src, reg(to.name, Double))
+        } else if is_unsigned {
+            instr(Op::Movz(from, to.optype), src, to)
+        } else {
+            instr(Op::Movs(from, to.optype), src, to)
+        };
+        self.write(&instr);
+    }
+
+    /// Compares `l` to `r` of type `ret_type` so that a following jump or set tests `l <cond> r`
+    fn emit_compare(&self, l: Src, r: Src, ret_type: ReturnType) {
+        if ret_type == ReturnType::Real {
+            let xmm = reg(SSE_ACCUMULATOR, Quad);
+            self.emit_move(Quad, l, xmm);
+            let r = self.emit_real_operand(r);
+            self.write(&instr2(Op::Ucomisd, r, xmm));
+            return;
+        }
+        // AT&T syntax means l and r are reversed for cmp,
+        // and the second operand of cmp cannot be an immediate.
+        let size = ret_type.into();
+        let rax = reg(Rax, size);
+        self.emit_move(size, l, rax);
+        let r = self.emit_wide_operand(r, size);
+        self.write(&instr2(Op::Comp(size), r, rax));
+    }
+
+    /// Loads a real immediate into the SSE scratch register, as SSE instructions take no immediates
+    fn emit_real_operand(&self, src: Src) -> Src {
+        if let Src::Immediate(_) = src {
+            let xmm = reg(SSE_SCRATCH_REG, Quad);
+            self.emit_move(Quad, src, xmm);
+            xmm.into()
+        } else {
+            src
+        }
+    }
+
+    /// Loads an immediate that does not fit in the 32 bits of an instruction operand into the
+    /// scratch register
+    fn emit_wide_operand(&self, src: Src, size: IOperatorSize) -> Src {
+        if Self::is_wide_immediate(&src, size) {
+            let scratch = reg(SCRATCH_REG, size);
+            self.emit_move(size, src, scratch);
+            scratch.into()
+        } else {
+            src
+        }
+    }
+
+    /// Emits `computed = lhs <op> rhs` in the accumulator and moves the result to `ret`
+    fn emit_accumulate(
+        &self,
+        op: Op,
+        lhs: Src,
+        rhs: Src,
+        size: IOperatorSize,
+        ret: &StoredLocation,
+    ) {
+        let rax = reg(Rax, size);
+        self.emit_move(size, lhs, rax);
+        let rhs = self.emit_wide_operand(rhs, size);
+        self.write(&instr(op, rhs, rax));
+        self.emit_move(size, rax, ret);
+    }
+
+    /// Sets the lower byte of register `to` to 1 if `operand` is non-zero, otherwise 0
+    fn emit_to_bool(&mut self, operand: &IOperand, to: RegisterName) -> Register {
+        let (src, src_type) = self.get_source(operand);
+        let size = src_type.into();
+        let register = reg(to, size);
+        self.emit_move(size, src, register);
+        self.write(&instr(Op::Test(size), register, register));
+        let byte = reg(to, Byte);
+        self.write(&instr(Op::SetNE, byte, Dest::None));
+        byte
+    }
+
+    /// Loads the address of the first element of `array` into the address register
+    fn emit_array_base(&mut self, array: &SymbolId) -> Register {
+        let base = reg(ADDRESS_REG, Quad);
+        // Array parameters hold a pointer, other arrays are stored in place
+        let op = if self.table.get_symbol(array).unwrap().is_param() {
+            Op::Mov(Quad)
+        } else {
+            Op::Lea(Quad)
+        };
+        let loc = self.reg_alloc.alloc_var(array);
+        self.write(&instr(op, &loc, base));
+        base
+    }
+
+    /// Loads an array index into the scratch register as a 64-bit value
+    fn emit_array_index(&mut self, index: &IOperand) -> Register {
+        let (src, src_type) = self.get_source(index);
+        let index = reg(SCRATCH_REG, Quad);
+        self.emit_extend(src, src_type, index);
+        index
+    }
+
+    /// Whether `src` is an immediate that only `movabsq` can take, which are reals and
+    /// 64-bit values outside the range of a sign-extended 32-bit immediate
+    fn is_wide_immediate(src: &Src, size: IOperatorSize) -> bool {
+        match src {
+            Src::Immediate(ConstantNodeValue::Real(_)) => true,
+            Src::Immediate(value) => size == Quad && i32::try_from(i64::from(*value)).is_err(),
+            _ => false,
+        }
+    }
+
+    /// The register that holds a value of type `ret_type` when it is returned
+    fn get_accumulator(ret_type: ReturnType) -> RegisterName {
+        if ret_type == ReturnType::Real {
+            SSE_ACCUMULATOR
+        } else {
+            Rax
         }
     }
 
@@ -1,55 +1,227 @@
 use std::cell::RefCell;
+use std::path::Path;
 use std::rc::Rc;
 
 use crate::output::OutStream;
 use code_generator::CodeGenerator;
 use intermediate_code::ic_generator::Intermediate;
 use intermediate_code::ic_generator::OptLevel;
+use reg_alloc::AllocStrategy;
 use syntax::SymbolTable;
-use syntax::SyntaxAnalysisResult;
+use syntax::{BuiltinRegistry, Linkage, Source, SyntaxAnalysisResult};
+use toolchain::Emit;
 
 pub mod assembly;
 pub mod code_generator;
+pub mod coloring;
 pub mod emitter;
+pub mod linear_scan;
 pub mod output;
 pub mod reg_alloc;
 pub mod register;
+pub mod toolchain;
 
+/// Compiles the C-minus file at `input_path` with the default builtins.
+/// Without an `output_path`, the output is named after the input file.
 pub fn compile_file(
     input_path: &str,
     output_path: Option<&str>,
     opt: OptLevel,
+    strategy: AllocStrategy,
+    emit: Emit,
 ) -> Result<(), String> {
-    let file = std::fs::read_to_string(input_path).unwrap();
+    compile_files(
+        &[input_path],
+        &[],
+        output_path,
+        opt,
+        strategy,
+        emit,
+        false,
+        false,
+        false,
+        &BuiltinRegistry::default(),
+    )
+}
+
+/// Compiles the C-minus files at `input_paths`, which may refer to each other's globals and
+/// functions through `extern` declarations.
+/// Only the first file includes the runtime of the builtins, and one of the files has to define
+/// `main`, unless `relocatable` is set, in which case another file has to provide both.
+/// For [Emit::Executable], the files are linked together with the files at `link_paths`,
+/// which can be anything the C compiler accepts, such as C sources or object files.
+/// Without an `output_path`, the output is named after the (first) input file.
+/// With `warn_narrowing`, explicit casts that narrow their operand are shown as warnings.
+/// With `freestanding`, the output does not use libc: the builtins make Linux system calls,
+/// and the program starts at `_start`, which exits with the value `main` returns.
+/// The files can call every builtin in `builtins`, which is usually [BuiltinRegistry::default].
+#[allow(clippy::too_many_arguments)]
+pub fn compile_files(
+    input_paths: &[&str],
+    link_paths: &[&str],
+    output_path: Option<&str>,
+    opt: OptLevel,
+    strategy: AllocStrategy,
+    emit: Emit,
+    relocatable: bool,
+    warn_narrowing: bool,
+    freestanding: bool,
+    builtins: &BuiltinRegistry,
+) -> Result<(), String> {
+    let first_input = input_paths.first().ok_or("No input files given")?;
+    if emit == Emit::Assembly && output_path.is_some() && input_paths.len() > 1 {
+        return Err("Cannot name the output of several assembly files".into());
+    }
+    let stem = |path: &str| {
+        Path::new(path)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string()
+    };
+    let exe_path = output_path.map_or_else(|| stem(first_input), String::from);
+    let mut asm_paths = vec![];
+    let mut has_main = false;
+    for (idx, input_path) in input_paths.iter().enumerate() {
+        let asm_path = match emit {
+            Emit::Assembly => output_path.map_or(format!("{}.S", stem(input_path)), String::from),
+            Emit::Executable if idx == 0 => format!("{}.S", exe_path),
+            Emit::Executable => {
+                let file_stem = Path::new(input_path).file_stem().unwrap().to_string_lossy();
+                format!("{}-{}.S", exe_path, file_stem)
+            }
+        };
+        let with_runtime = idx == 0 && !relocatable;
+        has_main |= compile_to_assembly(
+            input_path,
+            &asm_path,
+            opt,
+            strategy,
+            with_runtime,
+            warn_narrowing,
+            freestanding,
+            builtins,
+        )?;
+        asm_paths.push(asm_path);
+    }
+    if !has_main && !relocatable {
+        return Err("None of the input files defines `main`".into());
+    }
+    if emit == Emit::Executable {
+        let inputs = asm_paths
+            .iter()
+            .map(String::as_str)
+            .chain(link_paths.iter().copied())
+            .collect::<Vec<_>>();
+        toolchain::link_executable(&inputs, &exe_path, freestanding)?;
+    }
+    Ok(())
+}
+
+/// Compiles the C-minus file at `input_path` into the assembly file at `asm_path`,
+/// and returns whether the file defines `main`
+#[allow(clippy::too_many_arguments)]
+fn compile_to_assembly(
+    input_path: &str,
+    asm_path: &str,
+    opt: OptLevel,
+    strategy: AllocStrategy,
+    with_runtime: bool,
+    warn_narrowing: bool,
+    freestanding: bool,
+    builtins: &BuiltinRegistry,
+) -> Result<bool, String> {
+    let file = std::fs::read_to_string(input_path)
+        .map_err(|e| format!("Could not read {}: {}", input_path, e))?;
+    let source = Source {
+        name: input_path,
+        text: &file,
+    };
     let SyntaxAnalysisResult {
         errors,
         warnings,
+        narrowing_casts,
         mut tree,
         mut symbol_table,
-    } = syntax::generate(&file).unwrap_or_else(|e| panic!("Error parsing file:\n{}", e));
+    } = syntax::generate_linked(source, builtins)
+        .map_err(|e| format!("Could not parse {}:\n{}", input_path, e))?;
     let has_errors = !errors.is_empty();
     if has_errors {
         syntax::display_errors(&errors);
-        return Err("Syntax errors encountered".into());
+        return Err(format!("Syntax errors encountered in {}", input_path));
     }
     syntax::display_warnings(&warnings);
+    if warn_narrowing {
+        syntax::display_warnings(&narrowing_casts);
+    }
+    // Any of the files may define `main`, so it is only checked once all of them are compiled
+    let main_linkage = symbol_table
+        .get_main_id()
+        .and_then(|id| symbol_table.get_linkage(&id));
+    if main_linkage == Some(Linkage::Internal) {
+        return Err(format!("`main` cannot be static in {}", input_path));
+    }
     let intermediate = intermediate_code::generate(&mut tree, &mut symbol_table, opt).unwrap();
-    let out = if let Some(path) = output_path {
-        std::fs::File::create(path).unwrap()
-    } else {
-        let path = input_path.split('.').next().unwrap();
-        std::fs::File::create(format!("{}.S", path)).unwrap()
-    };
+    if freestanding {
+        check_freestanding(&intermediate, &symbol_table)
+            .map_err(|e| format!("{} in {}", e, input_path))?;
+    }
+    let out = std::fs::File::create(asm_path)
+        .map_err(|e| format!("Could not create {}: {}", asm_path, e))?;
     let out = Rc::new(RefCell::new(out)) as OutStream;
-    generate(&intermediate, &symbol_table, out);
+    generate(
+        &intermediate,
+        &symbol_table,
+        out,
+        strategy,
+        with_runtime,
+        freestanding,
+    );
+    Ok(main_linkage == Some(Linkage::External))
+}
+
+/// Checks that every builtin that `intermediate` calls can be used without libc
+fn check_freestanding(intermediate: &Intermediate, table: &SymbolTable) -> Result<(), String> {
+    for (_, stmt) in &intermediate.icode {
+        if !stmt.is_call() {
+            continue;
+        }
+        match table.get_builtin(&stmt.label_id()) {
+            Some(builtin) if builtin.freestanding_assembly.is_none() => {
+                return Err(format!(
+                    "Builtin `{}` is not available in freestanding mode",
+                    builtin.name
+                ))
+            }
+            _ => {}
+        }
+    }
     Ok(())
 }
 
-pub fn generate(intermediate: &Intermediate, table: &SymbolTable, out: OutStream) {
+/// Generates the assembly for `intermediate`.
+/// Without `with_runtime`, the builtins are left out, so they must be linked in from another file.
+/// With `freestanding`, the runtime does not use libc.
+pub fn generate(
+    intermediate: &Intermediate,
+    table: &SymbolTable,
+    out: OutStream,
+    strategy: AllocStrategy,
+    with_runtime: bool,
+    freestanding: bool,
+) {
     log::trace!("\n{}", intermediate.icode);
-    let mut cg = CodeGenerator::new(out, table, &intermediate.graph, &intermediate.icode);
+    let mut cg = CodeGenerator::new(
+        out,
+        table,
+        &intermediate.graph,
+        &intermediate.icode,
+        strategy,
+    );
     cg.generate_header();
     cg.generate_global_decls();
+    cg.generate_rodata();
     cg.generate_code();
-    cg.generate_trailer();
+    if with_runtime {
+        cg.generate_trailer(freestanding);
+    }
 }
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use syntax::SymbolId;
+
+use crate::reg_alloc::{FunctionScope, Placement, RegAlloc, RegisterAllocator, RegisterClass};
+use crate::register::RegisterName;
+
+/// Positions in a function during which a variable is live.
+/// Line `n` of the function has position `2n` before and `2n + 1` after it executes,
+/// so a variable can take over the register of a variable that dies on the same line.
+#[derive(Debug)]
+struct Interval {
+    id: SymbolId,
+    start: usize,
+    end: usize,
+    /// Live across a call, so a callee-saved register avoids saving it at the call
+    crosses_call: bool,
+}
+
+/// Allocates registers in a single pass over the live intervals of a function, ordered by start.
+/// When no register is free, the interval that ends last is split:
+/// it keeps its register up to the current position and lives on the stack afterwards.
+pub struct LinearScan<'a> {
+    base: RegAlloc<'a>,
+}
+
+impl<'a> LinearScan<'a> {
+    pub fn new(base: RegAlloc<'a>) -> Self {
+        Self { base }
+    }
+
+    fn build_intervals(&self, scope: &FunctionScope) -> Vec<Interval> {
+        let mut ranges: HashMap<SymbolId, (usize, usize)> = HashMap::new();
+        let mut extend = |id: SymbolId, pos: usize| {
+            let range = ranges.entry(id).or_insert((pos, pos));
+            range.0 = range.0.min(pos);
+            range.1 = range.1.max(pos);
+        };
+        // All params are defined on entry
+        for param in &scope.params {
+            extend(*param, 1);
+        }
+        for (idx, line) in scope.lines.iter().enumerate() {
+            for id in self.base.get_live_in(scope, line) {
+                extend(id, 2 * idx);
+            }
+            let defs = self.base.get_defs(scope, line);
+            for id in defs.iter().chain(&self.base.get_live_out(scope, line)) {
+                extend(*id, 2 * idx + 1);
+            }
+        }
+        let calls: Vec<usize> = scope
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.base.icode().get_statement(**line).is_call())
+            .map(|(idx, _)| idx)
+            .collect();
+        let mut intervals: Vec<Interval> = scope
+            .candidates
+            .iter()
+            .map(|id| {
+                let (start, end) = ranges.get(id).copied().unwrap_or((0, 0));
+                let crosses_call = calls.iter().any(|call| {
+                    let line = &scope.lines[*call];
+                    start <= 2 * call
+                        && 2 * call < end
+                        && !self.base.get_defs(scope, line).contains(id)
+                });
+                Interval {
+                    id: *id,
+                    start,
+                    end,
+                    crosses_call,
+                }
+            })
+            .collect();
+        intervals.sort_by_key(|i| (i.start, i.id.0));
+        intervals
+    }
+
+    /// The line index at which `interval` moves to the stack if it is split at position `pos`.
+    /// A loop that contains the split would reach the part in the register with a stale value,
+    /// so the split moves back to the start of such loops.
+    fn get_split_index(&self, loops: &[(usize, usize)], interval: &Interval, pos: usize) -> usize {
+        let mut idx = pos / 2;
+        while let Some((start, _)) = loops
+            .iter()
+            .find(|(start, end)| *start < idx && idx <= *end)
+        {
+            idx = *start;
+        }
+        idx.max(interval.start / 2)
+    }
+}
+
+impl<'a> RegisterAllocator<'a> for LinearScan<'a> {
+    fn base(&self) -> &RegAlloc<'a> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut RegAlloc<'a> {
+        &mut self.base
+    }
+
+    fn assign_registers(
+        &mut self,
+        scope: &FunctionScope,
+        class: RegisterClass,
+    ) -> HashMap<SymbolId, Placement> {
+        let loops = self.base.get_loops(scope);
+        let mut placements = HashMap::new();
+        let mut free: Vec<RegisterName> = class
+            .caller_saved()
+            .iter()
+            .chain(class.callee_saved())
+            .copied()
+            .collect();
+        let mut active: Vec<(Interval, RegisterName)> = vec![];
+
+        for interval in self.build_intervals(scope) {
+            // Expire intervals that ended before this one starts
+            let (expired, still_active) = std::mem::take(&mut active)
+                .into_iter()
+                .partition(|(i, _)| i.end < interval.start);
+            active = still_active;
+            for (_, reg) in expired {
+                free.push(reg);
+            }
+            free.sort_by_key(|reg| (class.callee_saved().contains(reg), *reg));
+
+            // Callee-saved registers come last and are preferred by intervals crossing a call
+            let free_reg = if interval.crosses_call {
+                free.pop()
+            } else if !free.is_empty() {
+                Some(free.remove(0))
+            } else {
+                None
+            };
+            if let Some(reg) = free_reg {
+                placements.insert(interval.id, Placement::Register(reg));
+                active.push((interval, reg));
+                continue;
+            }
+
+            let victim = active
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (i, _))| (i.end, i.id.0))
+                .map(|(idx, _)| idx);
+            match victim {
+                Some(victim) if active[victim].0.end > interval.end => {
+                    let (split, reg) = active.remove(victim);
+                    let idx = self.get_split_index(&loops, &split, interval.start);
+                    let placement = if idx <= split.start / 2 {
+                        Placement::Spilled
+                    } else {
+                        Placement::Split(reg, scope.lines[idx])
+                    };
+                    log::trace!("Split {} at {:?}", split.id, placement);
+                    placements.insert(split.id, placement);
+                    placements.insert(interval.id, Placement::Register(reg));
+                    active.push((interval, reg));
+                }
+                _ => {
+                    placements.insert(interval.id, Placement::Spilled);
+                }
+            }
+        }
+        placements
+    }
+}
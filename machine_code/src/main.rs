@@ -1,26 +1,46 @@
 pub mod assembly;
 pub mod code_generator;
+pub mod coloring;
 pub mod emitter;
+pub mod linear_scan;
 pub mod output;
 pub mod reg_alloc;
 pub mod register;
+pub mod toolchain;
 
 use clap::clap_app;
-use general::logging::init_logger_from_env;
+use general::logging::{init_logger_from_env, init_logger_from_env_or};
 use intermediate_code::ic_generator::OptLevel;
-use machine_code::compile_file;
+use log::LevelFilter;
+use machine_code::compile_files;
+use machine_code::reg_alloc::AllocStrategy;
+use machine_code::toolchain::Emit;
 use std::error::Error;
+use syntax::BuiltinRegistry;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let matches = clap_app!(myapp =>
         (version: "1.0")
-        (about: "Produce x86 assembly code for the given input C-minus file")
-        (@arg INPUT: +required "Sets the input")
+        (about: "Produce x86 assembly code for the given input C-minus files")
+        (@arg INPUT: +required ... "Sets the inputs. Several files can refer to each other's globals and functions with `extern`.")
         (@arg OUTPUT: -o +takes_value "Sets the output")
+        (@arg EXECUTABLE: -c "Assemble and link the output into an executable. Same as --emit=exe.")
+        (@arg EMIT: --emit +takes_value possible_value[asm exe] "Output assembly (the default) or an executable. Linking uses `cc`, or the compiler in the CC environment variable.")
         (@arg OPTIMIZE: -O +takes_value "Optimize compiler output. Takes a value between 0 and 3 (inclusive).")
+        (@arg RELOCATABLE: --relocatable "Leave out the builtins, so the output can be linked with a file that includes them")
+        (@arg LINK: --link +takes_value ... number_of_values(1) "Also link this file into the executable, e.g. a C source or an object file")
+        (@arg WARN_NARROWING: -W --warn_narrowing "Warn about explicit casts that narrow their operand, such as `(uint8_t) i`")
+        (@arg FREESTANDING: --freestanding "Do not use libc. The builtins make Linux system calls, and the exit status is the value `main` returns. Reals cannot be read or written.")
+        (@arg REGALLOC: --regalloc +takes_value possible_value[coloring linear] "Register allocation strategy. Linear scan compiles faster, coloring (the default) produces better code.")
     )
     .get_matches();
-    init_logger_from_env();
+    let warn_narrowing = matches.is_present("WARN_NARROWING");
+    // The narrowing casts are logged as warnings, which are hidden by default
+    if warn_narrowing {
+        init_logger_from_env_or(LevelFilter::Warn);
+    } else {
+        init_logger_from_env();
+    }
     let opt_level = match matches.value_of("OPTIMIZE") {
         Some("1") => OptLevel::Pre,
         Some("2") => OptLevel::Post,
@@ -29,9 +49,36 @@ fn main() -> Result<(), Box<dyn Error>> {
         _ => unreachable!(),
     };
 
-    let input = matches.value_of("INPUT").unwrap();
+    let strategy = match matches.value_of("REGALLOC") {
+        Some("linear") => AllocStrategy::LinearScan,
+        None | Some("coloring") => AllocStrategy::GraphColoring,
+        _ => unreachable!(),
+    };
+
+    let emit = match matches.value_of("EMIT") {
+        Some("exe") => Emit::Executable,
+        _ if matches.is_present("EXECUTABLE") => Emit::Executable,
+        None | Some("asm") => Emit::Assembly,
+        _ => unreachable!(),
+    };
+
+    let inputs: Vec<&str> = matches.values_of("INPUT").unwrap().collect();
+    let link: Vec<&str> = matches.values_of("LINK").unwrap_or_default().collect();
     let output = matches.value_of("OUTPUT");
-    compile_file(input, output, opt_level)?;
+    let relocatable = matches.is_present("RELOCATABLE");
+    let freestanding = matches.is_present("FREESTANDING");
+    compile_files(
+        &inputs,
+        &link,
+        output,
+        opt_level,
+        strategy,
+        emit,
+        relocatable,
+        warn_narrowing,
+        freestanding,
+        &BuiltinRegistry::default(),
+    )?;
     log::info!("Compilation successful");
     Ok(())
 }
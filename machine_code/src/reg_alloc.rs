@@ -1,12 +1,13 @@
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 
-use intermediate_code::flow_graph::FlowGraph;
+use intermediate_code::flow_graph::{FlowGraph, Liveness};
+use intermediate_code::icode::IntermediateCode;
+use intermediate_code::ioperand::IOperand;
 use intermediate_code::{ic_info::ICLineNumber, ioperator::IOperatorSize};
-use syntax::{SymbolId, SymbolTable};
+use syntax::{ConstantNodeValue, Linkage, ReturnType, SymbolId, SymbolTable, SymbolType};
 
-use crate::assembly::asm::{instr, Directive, Label, Op};
-use crate::register::reg;
+use crate::assembly::asm::{Directive, Label};
 use crate::{assembly::asm::Src, output};
 use crate::{
     output::OutStream,
@@ -18,53 +19,48 @@ pub enum StoredLocation {
     Reg(Register),
     Stack(StackOffset),
     Global(String),
-    TempReg(Register, AllocId),
 }
 
 impl From<StoredLocation> for Src {
     fn from(loc: StoredLocation) -> Self {
         match loc {
             StoredLocation::Global(l) => Src::Global(l),
-            StoredLocation::Reg(r) | StoredLocation::TempReg(r, _) => Src::Register(r),
+            StoredLocation::Reg(r) => Src::Register(r),
             StoredLocation::Stack(s) => Src::Stack(s),
         }
     }
 }
 
-#[derive(PartialEq)]
-pub enum AllocType {
-    Read,
-    Write,
-    ReadReg, // var must be read from a register
-}
-
-#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
-pub enum AllocId {
-    Sym(SymbolId),
-    Temp(usize),
-}
-
-impl AllocId {
-    pub fn to_id(&self) -> Option<SymbolId> {
-        if let Self::Sym(id) = self {
-            Some(*id)
-        } else {
-            None
-        }
-    }
-}
-
-impl From<SymbolId> for AllocId {
-    fn from(id: SymbolId) -> Self {
-        Self::Sym(id)
-    }
-}
-
 const N_PARAM_REGS: usize = 6;
 const PARAM_REGS: [RegisterName; N_PARAM_REGS] = [R9, R8, Rcx, Rdx, Rsi, Rdi];
+/// Never allocated to variables. Free for the emitter to use within a single statement.
+/// Only holds an argument of a call once all arguments are evaluated.
+pub const SCRATCH_REG: RegisterName = Rsi;
+/// Never allocated to variables. Holds the base address for indexed memory accesses.
+pub const ADDRESS_REG: RegisterName = Rdi;
+/// Clobbered by calls, so the emitter saves the ones that are live across a call
+pub const CALLER_SAVED_REGS: [RegisterName; 6] = [Rcx, Rdx, R8, R9, R10, R11];
+/// Preserved across calls, so a function using them has to save them first
+pub const CALLEE_SAVED_REGS: [RegisterName; 5] = [Rbx, R12, R13, R14, R15];
+const N_SSE_PARAM_REGS: usize = 8;
+const SSE_PARAM_REGS: [RegisterName; N_SSE_PARAM_REGS] =
+    [Xmm7, Xmm6, Xmm5, Xmm4, Xmm3, Xmm2, Xmm1, Xmm0];
+/// Never allocated to variables. Holds reals like `%rax` holds integers, and the returned real.
+pub const SSE_ACCUMULATOR: RegisterName = Xmm0;
+/// Never allocated to variables. Free for the emitter to use as the second operand of a real.
+pub const SSE_SCRATCH_REG: RegisterName = Xmm1;
+/// All SSE registers are clobbered by calls. Only those that do not pass params hold variables.
+pub const SSE_REGS: [RegisterName; 8] = [Xmm8, Xmm9, Xmm10, Xmm11, Xmm12, Xmm13, Xmm14, Xmm15];
+/// Size of a register pushed on the stack
+const SAVED_REG_SIZE: usize = 8;
+/// A def or use inside a loop counts this many times more towards the spill cost
+const LOOP_WEIGHT: f64 = 10.0;
+const MAX_LOOP_DEPTH: i32 = 6;
 
+/// Offset relative to `%rbp`. Locals are at negative offsets,
+/// parameters passed on the stack are at positive offsets.
 #[derive(Clone, Copy, PartialEq, Debug)]
-pub struct StackOffset(pub usize);
+pub struct StackOffset(pub isize);
 
 impl std::fmt::Display for StackOffset {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -73,258 +69,654 @@ impl std::fmt::Display for StackOffset {
             "{}",
             match self.0 {
                 0 => "".into(),
-                _ => format!("-{}", self.0),
+                _ => format!("{}", self.0),
             }
         )
     }
 }
 
-const STACK_ALIGN: usize = 16;
+pub const STACK_ALIGN: usize = 16;
+/// Size of every argument pushed on the stack
+pub const STACK_ARG_SIZE: usize = 8;
+/// Stack arguments start above the saved `%rbp` and the return address
+const STACK_ARGS_OFFSET: usize = 16;
+/// The System V ABI aligns arrays of at least 16 bytes to 16 bytes
+const ARRAY_ALIGN: usize = 16;
+/// Layout of a function's stack frame and the moves needed to set it up on entry
+pub struct StackFrame {
+    /// Bytes to reserve below `%rbp`.
+    /// Together with the saved registers this is a multiple of [STACK_ALIGN].
+    pub size: usize,
+    /// Callee-saved registers used by the function, pushed below the reserved bytes
+    pub saved_regs: Vec<RegisterName>,
+    /// Parameters passed in registers that are used, with their location in the function
+    pub reg_params: Vec<(Register, StoredLocation)>,
+    /// Parameters passed on the stack that were assigned a register
+    pub stack_params: Vec<(StackOffset, Register)>,
+}
+
+/// Reals are kept in SSE registers, all other variables in general purpose registers
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RegisterClass {
+    General,
+    Sse,
+}
+
+impl RegisterClass {
+    /// Registers of this class that a callee may overwrite
+    pub fn caller_saved(&self) -> &'static [RegisterName] {
+        match self {
+            Self::General => &CALLER_SAVED_REGS,
+            Self::Sse => &SSE_REGS,
+        }
+    }
+
+    /// Registers of this class that a callee preserves
+    pub fn callee_saved(&self) -> &'static [RegisterName] {
+        match self {
+            Self::General => &CALLEE_SAVED_REGS,
+            Self::Sse => &[],
+        }
+    }
+}
+
+impl From<ReturnType> for RegisterClass {
+    fn from(ret_type: ReturnType) -> Self {
+        match ret_type {
+            ReturnType::Real => Self::Sse,
+            _ => Self::General,
+        }
+    }
+}
+
+/// Where a [RegisterAllocator] decided to keep a variable
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Placement {
+    Register(RegisterName),
+    Spilled,
+    /// Kept in the register before the given line and on the stack from that line on
+    Split(RegisterName, ICLineNumber),
+}
+
+/// The variables of a single function that need a location
+pub struct FunctionScope {
+    pub start: ICLineNumber,
+    /// All lines of the function, starting with its FUNC statement
+    pub lines: Vec<ICLineNumber>,
+    pub params: Vec<SymbolId>,
+    pub candidates: HashSet<SymbolId>,
+}
+
+#[derive(Clone, Debug)]
+enum Home {
+    Fixed(StoredLocation),
+    Split {
+        register: Register,
+        slot: StackOffset,
+        at: ICLineNumber,
+    },
+}
+
+/// Strategy to select registers for the variables of a function.
+/// Only [RegisterAllocator::assign_registers] differs between strategies,
+/// the stack frame and data segment are laid out by the shared [RegAlloc].
+pub trait RegisterAllocator<'a> {
+    fn base(&self) -> &RegAlloc<'a>;
+
+    fn base_mut(&mut self) -> &mut RegAlloc<'a>;
+
+    /// Places every candidate of `scope` in a register of `class` or on the stack
+    fn assign_registers(
+        &mut self,
+        scope: &FunctionScope,
+        class: RegisterClass,
+    ) -> HashMap<SymbolId, Placement>;
+
+    fn generate_data_segment(&mut self) {
+        self.base_mut().generate_data_segment();
+    }
+
+    fn set_line(&mut self, line: ICLineNumber) {
+        self.base_mut().set_line(line);
+    }
+
+    fn alloc_var(&self, id: &SymbolId) -> StoredLocation {
+        self.base().alloc_var(id)
+    }
+
+    /// Assigns a register or a stack slot to every variable of the function `func`,
+    /// whose code starts at line `start`.
+    fn alloc_function(&mut self, func: &SymbolId, start: ICLineNumber) -> StackFrame {
+        let scope = self.base_mut().begin_function(func, start);
+        let mut placements = HashMap::new();
+        // The classes have separate registers, so their variables never compete for one
+        for class in [RegisterClass::General, RegisterClass::Sse] {
+            let class_scope = self.base().filter_scope(&scope, class);
+            placements.extend(self.assign_registers(&class_scope, class));
+        }
+        self.base_mut().finish_function(&scope, placements)
+    }
+
+    fn alloc_call_param(&mut self, ret_type: ReturnType) -> Option<Register> {
+        self.base_mut().alloc_call_param(ret_type)
+    }
+
+    fn free_param_regs(&mut self) {
+        self.base_mut().free_param_regs();
+    }
+
+    /// Registers of split variables that must be stored to their stack slot before the current line
+    fn get_split_stores(&self) -> Vec<(Register, StackOffset)> {
+        self.base().get_split_stores()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AllocStrategy {
+    GraphColoring,
+    LinearScan,
+}
+
 pub struct RegAlloc<'a> {
     out: OutStream,
-    reg_locals: HashMap<AllocId, RegisterName>,
-    stack_locals: HashMap<AllocId, StackOffset>,
+    homes: HashMap<SymbolId, Home>,
+    split_stores: HashMap<ICLineNumber, Vec<(Register, StackOffset)>>,
     param_regs: Vec<RegisterName>,
-    gpurpose_regs: BinaryHeap<RegisterName>,
+    sse_param_regs: Vec<RegisterName>,
     table: &'a SymbolTable,
     graph: &'a FlowGraph,
+    icode: &'a IntermediateCode,
     current_line: ICLineNumber,
     globals: HashMap<SymbolId, (String, IOperatorSize)>,
-    temp_counter: usize,
+    frame_size: usize,
 }
 
 impl<'a> RegAlloc<'a> {
-    pub fn new(out: OutStream, table: &'a SymbolTable, graph: &'a FlowGraph) -> Self {
+    pub fn new(
+        out: OutStream,
+        table: &'a SymbolTable,
+        graph: &'a FlowGraph,
+        icode: &'a IntermediateCode,
+    ) -> Self {
         Self {
             out,
-            reg_locals: HashMap::new(),
-            stack_locals: HashMap::new(),
+            homes: HashMap::new(),
+            split_stores: HashMap::new(),
             param_regs: Vec::from_iter(PARAM_REGS),
-            gpurpose_regs: BinaryHeap::from_iter([
-                R15, R14, R13, R12, /* R11, R10 are caller-saved and get clobbered */
-            ]),
+            sse_param_regs: Vec::from_iter(SSE_PARAM_REGS),
             table,
             graph,
+            icode,
             current_line: ICLineNumber(1),
             globals: HashMap::new(),
-            temp_counter: 0,
+            frame_size: 0,
         }
     }
 
+    /// Reserves space for the globals defined in this file.
+    /// `static` globals are local to the file, `extern` globals are stored in another file.
+    /// Initialized globals go in the data section, the other globals are zeroed by the loader.
     pub fn generate_data_segment(&mut self) {
         let globals = self.table.get_globals();
         if globals.is_empty() {
             return;
         }
         self.write(&Label::new(".LCX"));
+        let mut globals: Vec<_> = globals.into_iter().collect();
+        globals.sort_by_key(|(id, _)| id.0);
+        let mut initialized = vec![];
         for (id, sym) in globals {
             let size = sym.return_type.into();
             self.globals.insert(id, (sym.name.to_string(), size));
-            if sym.is_array() {
-                todo!("Implement array globals")
-            } else {
-                self.write(&Directive::Comm {
-                    name: sym.name.to_string(),
-                    size,
+            let elem_size = usize::from(IOperatorSize::from(sym.return_type.to_base_type()));
+            let (size, align) = match sym.symbol_type {
+                SymbolType::ArrayVariable { size } => {
+                    let total = size * elem_size;
+                    let align = if total >= ARRAY_ALIGN {
+                        ARRAY_ALIGN
+                    } else {
+                        elem_size
+                    };
+                    (total, align)
+                }
+                _ => (elem_size, elem_size),
+            };
+            let linkage = self.table.get_linkage(&id);
+            if linkage == Some(Linkage::Imported) {
+                continue;
+            }
+            if let Some(values) = self.table.get_initializer(&id) {
+                initialized.push((sym.name, linkage, values, elem_size, size, align));
+                continue;
+            }
+            if linkage == Some(Linkage::Internal) {
+                self.write(&Directive::Local(sym.name.to_string()));
+            }
+            self.write(&Directive::Comm {
+                name: sym.name.to_string(),
+                size,
+                align,
+            });
+        }
+        if initialized.is_empty() {
+            return;
+        }
+        self.write(&Directive::Data);
+        for (name, linkage, values, elem_size, size, align) in initialized {
+            if linkage != Some(Linkage::Internal) {
+                self.write(&Directive::Global(name.to_string()));
+            }
+            self.write(&Directive::Align(align));
+            self.write(&Label::new(&name));
+            // Reals are stored as the bits of their `f64`
+            let values: Vec<i64> = values
+                .iter()
+                .map(|v| match v {
+                    ConstantNodeValue::Real(r) => r.to_bits() as i64,
+                    v => i64::from(*v),
+                })
+                .collect();
+            let rest = size - values.len() * elem_size;
+            if !values.is_empty() {
+                self.write(&match elem_size {
+                    1 => Directive::Byte(values),
+                    2 => Directive::Word(values),
+                    4 => Directive::Long(values),
+                    _ => Directive::Quad(values),
                 });
             }
+            if rest > 0 {
+                self.write(&Directive::Zero(rest));
+            }
         }
+        self.write(&Directive::Text);
     }
 
     pub fn set_line(&mut self, line: ICLineNumber) {
         self.current_line = line;
     }
 
-    pub fn alloc_var(&mut self, id: &SymbolId, alloc_type: AllocType) -> StoredLocation {
-        let aid = AllocId::from(*id);
-        let sym = self.table.get_symbol(id).unwrap();
-        let size = IOperatorSize::from(sym.return_type);
-        let is_global = self.globals.contains_key(id);
-
-        let stored_location = if is_global {
-            match alloc_type {
-                AllocType::ReadReg => {
-                    // if let Some((_, alias)) =
-                    //     self.get_non_temp_regs().iter().find(|(k, _)| *k == *id)
-                    // {
-                    //     StoredLocation::Reg(reg(**alias, size))
-                    // } else {
-                    let regname = self
-                        .alloc_register(&aid)
-                        .expect("No registers free for global alloc");
-                    let (var, size) = self.globals.get(id).unwrap();
-                    let register = reg(regname, *size);
-                    let mov_instr = instr(Op::Mov(*size), Src::Global(var.clone()), register);
-                    self.write(&mov_instr);
-                    StoredLocation::Reg(register)
-                    // }
-                }
-                _ => {
-                    let global_var = self.globals.get(id).unwrap().0.clone();
-                    StoredLocation::Global(global_var)
-                }
-            }
-        } else {
-            match alloc_type {
-                AllocType::ReadReg => {
-                    let reg = if let Some(regname) = self.reg_locals.get(&aid) {
-                        reg(*regname, size)
-                    } else {
-                        let regname = self.alloc_register(&aid).expect("No free regs");
-                        let size = self.table.get_symbol(id).unwrap().return_type.into();
-                        let offset = self.get_stack_local(&aid).expect("Var not on stack");
-                        let register = reg(regname, size);
-                        let mov_instr = instr(Op::Mov(size), Src::Stack(offset), register);
-                        self.write(&mov_instr);
-                        register
-                    };
-                    StoredLocation::Reg(reg)
-                }
-                _ => {
-                    if let Some(regname) = self.reg_locals.get(&aid) {
-                        StoredLocation::Reg(reg(*regname, size))
-                    } else if let Some(offset) = self.get_stack_local(&aid) {
-                        StoredLocation::Stack(offset)
-                    } else {
-                        let regname = self.alloc_register(&aid).expect("No free regs");
-                        StoredLocation::Reg(reg(regname, size))
-                    }
+    /// Returns where the variable `id` is stored at the current line
+    pub fn alloc_var(&self, id: &SymbolId) -> StoredLocation {
+        if let Some((name, _)) = self.globals.get(id) {
+            return StoredLocation::Global(name.clone());
+        }
+        match self.homes.get(id) {
+            Some(Home::Fixed(location)) => location.clone(),
+            Some(Home::Split { register, slot, at }) => {
+                if self.current_line < *at {
+                    StoredLocation::Reg(*register)
+                } else {
+                    StoredLocation::Stack(*slot)
                 }
             }
-        };
-        stored_location
+            None => unreachable!("{} was not allocated", id),
+        }
     }
 
-    pub fn alloc_func_params(&mut self, params: &[SymbolId]) {
-        if params.len() > N_PARAM_REGS {
-            todo!("Allocate stack space for param passing");
-        }
-        for (idx, param) in params.iter().enumerate() {
-            let reg = PARAM_REGS[N_PARAM_REGS - idx - 1];
-            self.reg_locals.insert((*param).into(), reg);
-            log::trace!("Alloc {} to {:#?}", param, reg);
-        }
+    pub fn get_split_stores(&self) -> Vec<(Register, StackOffset)> {
+        self.split_stores
+            .get(&self.current_line)
+            .cloned()
+            .unwrap_or_default()
     }
 
-    pub fn alloc_call_param(&mut self, size: IOperatorSize) -> StoredLocation {
-        // Params only go into the specified param registers
-        match self.param_regs.pop() {
-            Some(reg) => StoredLocation::Reg(Register::new(reg, size)),
-            None => {
-                todo!("Stack offset for params");
-            }
-        }
+    /// Returns the register for the next argument of a call,
+    /// or `None` if the argument has to be pushed on the stack.
+    pub fn alloc_call_param(&mut self, ret_type: ReturnType) -> Option<Register> {
+        // The first params of each class go into the specified param registers
+        let param_regs = match RegisterClass::from(ret_type) {
+            RegisterClass::General => &mut self.param_regs,
+            RegisterClass::Sse => &mut self.sse_param_regs,
+        };
+        param_regs
+            .pop()
+            .map(|reg| Register::new(reg, ret_type.into()))
     }
 
     pub fn free_param_regs(&mut self) {
         log::debug!("Freeing param regs");
         self.param_regs = Vec::from_iter(PARAM_REGS);
+        self.sse_param_regs = Vec::from_iter(SSE_PARAM_REGS);
     }
 
-    pub fn alloc_stack_locals(&mut self, func: &SymbolId) -> StackOffset {
-        let func = self.table.get_func_var_symbols(func).unwrap();
-        let mut top_of_stack = 0_usize;
-        for (id, sym) in func {
-            let size: IOperatorSize = sym.return_type.into();
-            let size = usize::from(size);
-            let offset = top_of_stack + size;
-            self.stack_locals.insert(id.into(), StackOffset(offset));
-            top_of_stack += size;
-        }
-        let padding = (STACK_ALIGN - (top_of_stack % STACK_ALIGN)) % STACK_ALIGN;
-        StackOffset(top_of_stack + padding)
+    pub fn icode(&self) -> &IntermediateCode {
+        self.icode
     }
 
-    pub fn get_stack_local(&mut self, sym: &AllocId) -> Option<StackOffset> {
-        log::trace!("Getting {:?}", sym);
-        log::trace!("{:#?}", self.stack_locals);
-        self.stack_locals.get(sym).copied()
+    pub fn liveness(&self) -> &Liveness {
+        self.graph.liveness()
     }
 
-    pub fn alloc_temp(&mut self) -> (AllocId, RegisterName) {
-        let aid = AllocId::Temp(self.temp_counter);
-        self.temp_counter += 1;
-        let reg = self.alloc_register(&aid).expect("Could not get register");
-        (aid, reg)
+    /// The candidates of `scope` that are live after `line`
+    pub fn get_live_out(&self, scope: &FunctionScope, line: &ICLineNumber) -> Vec<SymbolId> {
+        self.filter_candidates(scope, self.liveness().live_out.get(line))
     }
 
-    pub fn free_temp(&mut self, id: &AllocId) {
-        let regname = self
-            .reg_locals
-            .remove(id)
-            .unwrap_or_else(|| panic!("No such temp allocated: {:#?}", id));
-        self.gpurpose_regs.push(regname);
+    /// The candidates of `scope` that are live before `line`
+    pub fn get_live_in(&self, scope: &FunctionScope, line: &ICLineNumber) -> Vec<SymbolId> {
+        self.filter_candidates(scope, self.liveness().live_in.get(line))
     }
 
-    /// Assigns a register to a single [SymbolId] `sym` based on the following rules:
-    /// 1. If `sym` is in a register R, return R and do not allocate any other register
-    /// 2. If an empty register is available, return that
-    /// 3. Reclaim an occupied register from a dead variable, use that
-    /// 4. Return None (should allocate on stack instead)
-    fn alloc_register(&mut self, id: &AllocId) -> Option<RegisterName> {
-        self.assign_empty_reg(*id)
-            .or_else(|| self.reclaim_dead_reg(*id))
-        // .or_else(|| self.reclaim_temp_reg(*id))
+    /// The candidates of `scope` that are assigned a value by `line`
+    pub fn get_defs(&self, scope: &FunctionScope, line: &ICLineNumber) -> Vec<SymbolId> {
+        self.filter_candidates(scope, self.liveness().def.get(line))
     }
 
-    fn assign_empty_reg(&mut self, id: AllocId) -> Option<RegisterName> {
-        if let Some(reg) = self.gpurpose_regs.pop() {
-            self.reg_locals.insert(id, reg);
-            log::debug!("Allocating reg {:?}", reg);
-            Some(reg)
-        } else {
-            None
+    /// Every def and use of a variable adds to its cost of being spilled,
+    /// weighted by how deeply nested in loops it happens.
+    pub fn get_spill_costs(&self, scope: &FunctionScope) -> HashMap<SymbolId, f64> {
+        let liveness = self.liveness();
+        let mut depths = vec![0; scope.lines.len()];
+        for (start, end) in self.get_loops(scope) {
+            for depth in &mut depths[start..=end] {
+                *depth += 1;
+            }
         }
+        let mut costs = HashMap::new();
+        for (line, depth) in scope.lines.iter().zip(depths) {
+            let weight = LOOP_WEIGHT.powi(depth.min(MAX_LOOP_DEPTH));
+            let defs = liveness.def.get(line).into_iter().flatten();
+            let uses = liveness.used.get(line).into_iter().flatten();
+            for id in defs.chain(uses).filter(|id| scope.candidates.contains(id)) {
+                *costs.entry(*id).or_insert(0.0) += weight;
+            }
+        }
+        costs
     }
 
-    fn reclaim_dead_reg(&mut self, aid: AllocId) -> Option<RegisterName> {
-        let live_out = self.graph.get_live_out_at(&self.current_line);
-        let live_in = self.graph.get_live_at(&self.current_line);
-        log::debug!("Liveout: {:#?}\nLive: {:#?}", live_out, live_in);
-        if let Some(r) = self
-            .get_non_temp_regs()
+    /// Loops as ranges of indices into the lines of `scope`.
+    /// A jump back to an earlier label closes a loop.
+    pub fn get_loops(&self, scope: &FunctionScope) -> Vec<(usize, usize)> {
+        let labels = self.get_label_indices(scope);
+        scope
+            .lines
             .iter()
-            .find(|(id, _)| !live_out.contains(id))
-            .map(|(_, r)| **r)
-        {
-            self.reg_locals.insert(aid, r);
-            log::debug!("Overwriting reg {:?}", r);
-            Some(r)
-        } else {
-            None
+            .enumerate()
+            .filter_map(|(idx, line)| {
+                let stmt = self.icode.get_statement(*line);
+                if !stmt.is_conditional_jump() && !stmt.is_unconditional_jump() {
+                    return None;
+                }
+                labels
+                    .get(&stmt.label_id())
+                    .filter(|target| **target <= idx)
+                    .map(|target| (*target, idx))
+            })
+            .collect()
+    }
+
+    fn get_label_indices(&self, scope: &FunctionScope) -> HashMap<SymbolId, usize> {
+        scope
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.icode.get_statement(**line).is_label())
+            .map(|(idx, line)| (self.icode.get_statement(*line).label_id(), idx))
+            .collect()
+    }
+
+    fn filter_candidates(
+        &self,
+        scope: &FunctionScope,
+        ids: Option<&HashSet<SymbolId>>,
+    ) -> Vec<SymbolId> {
+        let mut ids: Vec<SymbolId> = ids
+            .into_iter()
+            .flatten()
+            .filter(|id| scope.candidates.contains(id))
+            .copied()
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    /// Starts a new stack frame for `func` and collects the variables that need a location
+    fn begin_function(&mut self, func: &SymbolId, start: ICLineNumber) -> FunctionScope {
+        self.frame_size = 0;
+        self.homes.clear();
+        self.split_stores.clear();
+        // Arrays are stored in place, so they always live on the stack
+        for (id, sym) in self.table.get_func_var_symbols(func).unwrap() {
+            if let SymbolType::ArrayVariable { size } = sym.symbol_type {
+                let elem_size = usize::from(IOperatorSize::from(sym.return_type.to_base_type()));
+                let offset = self.alloc_stack_slot(size * elem_size, elem_size);
+                self.homes
+                    .insert(id, Home::Fixed(StoredLocation::Stack(offset)));
+            }
+        }
+        let lines = self.get_function_lines(start);
+        let params = self.table.get_func_param_ids(func).unwrap().clone();
+        let candidates = self.get_candidates(&params, &lines);
+        FunctionScope {
+            start,
+            lines,
+            params,
+            candidates,
         }
     }
 
-    // fn reclaim_temp_reg(&mut self, aid: AllocId) -> Option<RegisterName> {
-    //     if let Some((_, temp)) = self.get_temp_regs().first() {
-    //         let temp = **temp;
-    //         self.reg_locals.insert(aid, temp);
-    //         Some(temp)
-    //     } else {
-    //         None
-    //     }
-    // }
+    /// The part of `scope` with the params and candidates that belong in registers of `class`
+    fn filter_scope(&self, scope: &FunctionScope, class: RegisterClass) -> FunctionScope {
+        let in_class = |id: &SymbolId| {
+            RegisterClass::from(self.table.get_symbol(id).unwrap().return_type) == class
+        };
+        FunctionScope {
+            start: scope.start,
+            lines: scope.lines.clone(),
+            params: scope.params.iter().copied().filter(in_class).collect(),
+            candidates: scope.candidates.iter().copied().filter(in_class).collect(),
+        }
+    }
 
-    fn get_non_temp_regs(&self) -> Vec<(SymbolId, &RegisterName)> {
-        let vec: Vec<_> = self
-            .reg_locals
+    /// Where the caller passes each of `params`: in the next free param register of its class,
+    /// or on the stack once those run out
+    fn get_param_locations(&self, params: &[SymbolId]) -> Vec<StoredLocation> {
+        let mut param_regs = Vec::from_iter(PARAM_REGS);
+        let mut sse_param_regs = Vec::from_iter(SSE_PARAM_REGS);
+        let mut stack_params = 0;
+        params
             .iter()
-            .filter_map(|(k, r)| k.to_id().map(|k| (k, r)))
+            .map(|id| {
+                let ret_type = self.table.get_symbol(id).unwrap().return_type;
+                let regs = match RegisterClass::from(ret_type) {
+                    RegisterClass::General => &mut param_regs,
+                    RegisterClass::Sse => &mut sse_param_regs,
+                };
+                match regs.pop() {
+                    Some(name) => StoredLocation::Reg(Register::new(name, ret_type.into())),
+                    None => {
+                        stack_params += 1;
+                        StoredLocation::Stack(Self::get_stack_param_offset(stack_params - 1))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Gives every candidate its final location and lays out the stack frame
+    fn finish_function(
+        &mut self,
+        scope: &FunctionScope,
+        placements: HashMap<SymbolId, Placement>,
+    ) -> StackFrame {
+        let mut placements: Vec<_> = placements.into_iter().collect();
+        placements.sort_by_key(|(id, _)| id.0);
+        for (id, placement) in placements {
+            let size = self.get_size(&id);
+            log::trace!("Place {} in {:?}", id, placement);
+            let home = match placement {
+                Placement::Register(name) => {
+                    Home::Fixed(StoredLocation::Reg(Register::new(name, size)))
+                }
+                Placement::Spilled => Home::Fixed(StoredLocation::Stack(self.get_slot(scope, &id))),
+                Placement::Split(name, at) => {
+                    let register = Register::new(name, size);
+                    let slot = self.get_slot(scope, &id);
+                    self.add_split_stores(scope, id, register, slot, at);
+                    Home::Split { register, slot, at }
+                }
+            };
+            self.homes.insert(id, home);
+        }
+
+        let mut saved_regs: Vec<RegisterName> = self
+            .homes
+            .values()
+            .filter_map(|home| match home {
+                Home::Fixed(StoredLocation::Reg(r)) | Home::Split { register: r, .. } => {
+                    Some(r.name)
+                }
+                _ => None,
+            })
+            .filter(|name| CALLEE_SAVED_REGS.contains(name))
+            .collect();
+        saved_regs.sort();
+        saved_regs.dedup();
+        let size = self.frame_size + saved_regs.len() * SAVED_REG_SIZE;
+        let padding = (STACK_ALIGN - (size % STACK_ALIGN)) % STACK_ALIGN;
+        let mut frame = StackFrame {
+            size: self.frame_size + padding,
+            saved_regs,
+            reg_params: vec![],
+            stack_params: vec![],
+        };
+        self.current_line = scope.start;
+        let live_at_entry = self.get_live_out(scope, &scope.start);
+        let passed = self.get_param_locations(&scope.params);
+        for (param, passed) in scope.params.iter().zip(passed) {
+            if !live_at_entry.contains(param) {
+                continue;
+            }
+            let location = self.alloc_var(param);
+            match (passed, location) {
+                (StoredLocation::Reg(register), location) => {
+                    frame.reg_params.push((register, location))
+                }
+                (StoredLocation::Stack(offset), StoredLocation::Reg(register)) => {
+                    frame.stack_params.push((offset, register))
+                }
+                _ => {}
+            }
+        }
+        frame
+    }
+
+    /// A split variable is stored to its slot when control reaches the split
+    /// by falling through or by jumping over it.
+    fn add_split_stores(
+        &mut self,
+        scope: &FunctionScope,
+        id: SymbolId,
+        register: Register,
+        slot: StackOffset,
+        at: ICLineNumber,
+    ) {
+        let liveness = self.graph.liveness();
+        let is_live_at = |line: &ICLineNumber| liveness.live_in.get(line).unwrap().contains(&id);
+        let mut stores = vec![];
+        if is_live_at(&at) {
+            stores.push(at);
+        }
+        let labels: HashMap<SymbolId, ICLineNumber> = self
+            .get_label_indices(scope)
+            .into_iter()
+            .map(|(label, idx)| (label, scope.lines[idx]))
             .collect();
-        vec
-    }
-
-    // fn get_temp_regs(&self) -> Vec<(AllocId, &RegisterName)> {
-    //     let vec: Vec<_> = self
-    //         .reg_locals
-    //         .iter()
-    //         .filter_map(|(k, v)| match k {
-    //             AllocId::Temp(_) => Some((*k, v)),
-    //             _ => None,
-    //         })
-    //         .collect();
-    //     vec
-    // }
+        for line in scope.lines.iter().filter(|line| **line < at) {
+            let stmt = self.icode.get_statement(*line);
+            if !stmt.is_conditional_jump() && !stmt.is_unconditional_jump() {
+                continue;
+            }
+            let target = labels[&stmt.label_id()];
+            if target >= at && is_live_at(&target) {
+                stores.push(*line);
+            }
+        }
+        for line in stores {
+            self.split_stores
+                .entry(line)
+                .or_default()
+                .push((register, slot));
+        }
+    }
+
+    /// The stack slot of a spilled variable. Params passed on the stack already have one.
+    fn get_slot(&mut self, scope: &FunctionScope, id: &SymbolId) -> StackOffset {
+        let passed = scope
+            .params
+            .iter()
+            .position(|p| p == id)
+            .map(|idx| self.get_param_locations(&scope.params).swap_remove(idx));
+        match passed {
+            Some(StoredLocation::Stack(offset)) => offset,
+            _ => {
+                let size = usize::from(self.get_size(id));
+                self.alloc_stack_slot(size, size)
+            }
+        }
+    }
+
+    /// The offset of the `idx`th param passed on the stack
+    fn get_stack_param_offset(idx: usize) -> StackOffset {
+        let offset = STACK_ARGS_OFFSET + idx * STACK_ARG_SIZE;
+        StackOffset(offset as isize)
+    }
+
+    /// Reserves `size` bytes in the current stack frame.
+    /// Arrays grow towards `%rbp`, so the offset is the address of the first element.
+    fn alloc_stack_slot(&mut self, size: usize, align: usize) -> StackOffset {
+        self.frame_size += size;
+        self.frame_size += (align - (self.frame_size % align)) % align;
+        StackOffset(-(self.frame_size as isize))
+    }
+
+    /// Lines from the FUNC statement at `start` up to the next function
+    fn get_function_lines(&self, start: ICLineNumber) -> Vec<ICLineNumber> {
+        self.icode
+            .into_iter()
+            .skip_while(|(line, _)| *line < start)
+            .enumerate()
+            .take_while(|(idx, (_, stmt))| *idx == 0 || !stmt.is_func())
+            .map(|(_, (line, _))| line)
+            .collect()
+    }
+
+    /// The non-global scalar variables, params and temporaries of a function
+    fn get_candidates(&self, params: &[SymbolId], lines: &[ICLineNumber]) -> HashSet<SymbolId> {
+        let liveness = self.graph.liveness();
+        let mentioned = lines.iter().flat_map(|line| {
+            let stmt = self.icode.get_statement(*line);
+            [&stmt.operand1, &stmt.operand2, &stmt.ret_target]
+                .iter()
+                .filter_map(|op| match op {
+                    Some(IOperand::Symbol { id, .. }) => Some(*id),
+                    _ => None,
+                })
+                .chain(liveness.live_out.get(line).into_iter().flatten().copied())
+                .collect::<Vec<_>>()
+        });
+        params
+            .iter()
+            .copied()
+            .chain(mentioned)
+            .filter(|id| !self.globals.contains_key(id) && !self.homes.contains_key(id))
+            .filter(|id| {
+                let sym = self.table.get_symbol(id).unwrap();
+                matches!(
+                    sym.symbol_type,
+                    SymbolType::Variable
+                        | SymbolType::Parameter
+                        | SymbolType::ArrayParam
+                        | SymbolType::TempVar
+                ) && IOperatorSize::from(sym.return_type) != IOperatorSize::Void
+            })
+            .collect()
+    }
+
+    fn get_size(&self, id: &SymbolId) -> IOperatorSize {
+        self.table.get_symbol(id).unwrap().return_type.into()
+    }
 
     fn write(&self, contents: &impl ToString) {
         output::write(self.out.clone(), contents);
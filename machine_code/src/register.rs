@@ -21,7 +21,7 @@ pub fn reg(name: RegisterName, opsize: IOperatorSize) -> Register {
 }
 
 /// Uniquely identifies an x86 register by its 64-bit name.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum RegisterName {
     Invalid,
     R15,
@@ -40,11 +40,29 @@ pub enum RegisterName {
     Rcx,
     Rbx,
     Rax,
+    Xmm0,
+    Xmm1,
+    Xmm2,
+    Xmm3,
+    Xmm4,
+    Xmm5,
+    Xmm6,
+    Xmm7,
+    Xmm8,
+    Xmm9,
+    Xmm10,
+    Xmm11,
+    Xmm12,
+    Xmm13,
+    Xmm14,
+    Xmm15,
 }
 
 pub enum RegisterType {
     GeneralPurpose,
     SpecialPurpose,
+    /// SSE registers hold reals and have the same name for every size
+    Sse,
 }
 
 impl From<&RegisterName> for RegisterType {
@@ -58,15 +76,35 @@ impl From<&RegisterName> for RegisterType {
             | RegisterName::Rdi
             | RegisterName::Rbp
             | RegisterName::Rsp => RegisterType::SpecialPurpose,
+            RegisterName::Xmm0
+            | RegisterName::Xmm1
+            | RegisterName::Xmm2
+            | RegisterName::Xmm3
+            | RegisterName::Xmm4
+            | RegisterName::Xmm5
+            | RegisterName::Xmm6
+            | RegisterName::Xmm7
+            | RegisterName::Xmm8
+            | RegisterName::Xmm9
+            | RegisterName::Xmm10
+            | RegisterName::Xmm11
+            | RegisterName::Xmm12
+            | RegisterName::Xmm13
+            | RegisterName::Xmm14
+            | RegisterName::Xmm15 => RegisterType::Sse,
             _ => RegisterType::GeneralPurpose,
         }
     }
 }
 
 impl Register {
+    pub fn is_sse(&self) -> bool {
+        matches!(RegisterType::from(&self.name), RegisterType::Sse)
+    }
+
     fn get_start(&self) -> String {
         match RegisterType::from(&self.name) {
-            RegisterType::GeneralPurpose => "",
+            RegisterType::GeneralPurpose | RegisterType::Sse => "",
             RegisterType::SpecialPurpose => match self.optype {
                 IOperatorSize::Byte | IOperatorSize::Word => "",
                 IOperatorSize::Double => "e",
@@ -95,6 +133,22 @@ impl Register {
             RegisterName::R13 => "r13",
             RegisterName::R14 => "r14",
             RegisterName::R15 => "r15",
+            RegisterName::Xmm0 => "xmm0",
+            RegisterName::Xmm1 => "xmm1",
+            RegisterName::Xmm2 => "xmm2",
+            RegisterName::Xmm3 => "xmm3",
+            RegisterName::Xmm4 => "xmm4",
+            RegisterName::Xmm5 => "xmm5",
+            RegisterName::Xmm6 => "xmm6",
+            RegisterName::Xmm7 => "xmm7",
+            RegisterName::Xmm8 => "xmm8",
+            RegisterName::Xmm9 => "xmm9",
+            RegisterName::Xmm10 => "xmm10",
+            RegisterName::Xmm11 => "xmm11",
+            RegisterName::Xmm12 => "xmm12",
+            RegisterName::Xmm13 => "xmm13",
+            RegisterName::Xmm14 => "xmm14",
+            RegisterName::Xmm15 => "xmm15",
             RegisterName::Invalid => "?",
         }
         .to_string()
@@ -102,6 +156,7 @@ impl Register {
 
     fn get_end(&self) -> String {
         match RegisterType::from(&self.name) {
+            RegisterType::Sse => "",
             RegisterType::GeneralPurpose => match self.optype {
                 IOperatorSize::Byte => "b",
                 IOperatorSize::Word => "w",
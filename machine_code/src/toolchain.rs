@@ -0,0 +1,58 @@
+use std::io::ErrorKind;
+use std::process::Command;
+
+/// What the compiler produces
+#[derive(Debug, PartialEq)]
+pub enum Emit {
+    /// An x86 assembly file
+    Assembly,
+    /// A native executable, assembled and linked by the system toolchain
+    Executable,
+}
+
+/// Compiler driver used to assemble and link when the `CC` environment variable is not set.
+/// A driver rather than `as` and `ld` directly, since the builtins call into libc.
+const DEFAULT_CC: &str = "cc";
+
+/// Assembles and links the files at `input_paths` into an executable at `exe_path`.
+/// Besides assembly files, these can be any files the compiler driver accepts, such as C sources.
+/// A `freestanding` executable is linked statically without libc and its startup files.
+pub fn link_executable(
+    input_paths: &[&str],
+    exe_path: &str,
+    freestanding: bool,
+) -> Result<(), String> {
+    let cc = std::env::var("CC").unwrap_or_else(|_| DEFAULT_CC.to_string());
+    let inputs = input_paths.join(" ");
+    log::info!("Linking {} into {} with `{}`", inputs, exe_path, cc);
+    // The builtins call into libc without going through the PLT
+    let mut command = Command::new(&cc);
+    command.arg("-no-pie");
+    if freestanding {
+        command.args(["-nostdlib", "-static"]);
+    }
+    let output = command
+        .args(input_paths)
+        .arg("-o")
+        .arg(exe_path)
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!(
+                "Could not find `{}` to assemble and link {}. \
+                 Install a C compiler such as gcc, or point the CC environment variable to one.",
+                cc, inputs
+            ),
+            _ => format!("Could not run `{}`: {}", cc, e),
+        })?;
+    let diagnostics = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!(
+            "`{}` failed to assemble and link {} ({}):\n{}",
+            cc, inputs, output.status, diagnostics
+        ));
+    }
+    if !diagnostics.is_empty() {
+        log::debug!("`{}` reported:\n{}", cc, diagnostics);
+    }
+    Ok(())
+}
@@ -1,13 +1,15 @@
-use std::borrow::Borrow;
+use itertools::Itertools;
 
 use crate::{
+    builtin::Builtin,
     error::SyntaxBuilderError,
     id::{SymbolId, SymbolName},
     node::SyntaxNode,
-    node::SyntaxNodeBox,
+    node::{ConstantNodeValue, SyntaxNodeBox},
     scope::ScopeManager,
-    symbol::Symbol,
-    symbol_table::{SymbolScope, SymbolTable},
+    span::Span,
+    symbol::{Symbol, SymbolType},
+    symbol_table::{Linkage, SymbolScope, SymbolTable},
     syntax_tree::{FunctionRoot, SyntaxTree},
 };
 
@@ -45,6 +47,32 @@ impl SyntaxBuilder {
         Ok(id)
     }
 
+    /// Declares a builtin with its parameters. A builtin has no syntax tree.
+    pub fn add_builtin(&mut self, builtin: Builtin) -> Result<SymbolId, SyntaxBuilderError> {
+        let id = self.add_function(Symbol {
+            name: builtin.name.clone(),
+            return_type: builtin.return_type,
+            symbol_type: SymbolType::Function,
+            span: Span::none(),
+            dimensions: vec![],
+        })?;
+        self.current_function = Some(id);
+        self.enter_new_scope();
+        for (name, return_type) in builtin.params.iter() {
+            self.add_symbol(Symbol {
+                name: name.clone(),
+                return_type: *return_type,
+                symbol_type: SymbolType::Parameter,
+                span: Span::none(),
+                dimensions: vec![],
+            })?;
+        }
+        self.leave_scope();
+        self.leave_function();
+        self.table.add_builtin(id, builtin);
+        Ok(id)
+    }
+
     /// Create and enter a new function.
     pub fn enter_function(&mut self, symbol: Symbol) -> Result<SymbolId, SyntaxBuilderError> {
         let name = symbol.name.clone();
@@ -60,6 +88,80 @@ impl SyntaxBuilder {
         Ok(id)
     }
 
+    /// Returns the [SymbolId] of function `name` if it was declared by a prototype,
+    /// but has not been defined yet.
+    pub fn get_prototype(&self, name: &SymbolName) -> Option<SymbolId> {
+        let id = self.get_symbol_id(name)?;
+        let is_function = self.get_symbol_by_id(&id)?.symbol_type == SymbolType::Function;
+        if is_function && self.tree.functions.get(&id)?.tree.is_none() {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the [SymbolId] of function `name` if it has been defined already.
+    /// Builtins have no syntax tree, so they are not included.
+    pub fn get_defined_function(&self, name: &SymbolName) -> Option<SymbolId> {
+        let id = self.get_symbol_id(name)?;
+        self.is_defined(&id).then_some(id)
+    }
+
+    /// Whether function `id` has been defined with a body
+    pub fn is_defined(&self, id: &SymbolId) -> bool {
+        self.tree
+            .functions
+            .get(id)
+            .is_some_and(|f| f.tree.is_some())
+    }
+
+    /// Enter a function that was declared by a prototype.
+    /// The parameters of the prototype are replaced by those of the definition.
+    pub fn enter_prototype(&mut self, id: SymbolId) {
+        self.table.remove_func_params(&id);
+        self.current_function = Some(id);
+    }
+
+    /// Enter a function that is already defined, for a prototype that repeats its declaration.
+    /// The function keeps the parameters of its definition.
+    pub fn enter_defined_function(&mut self, id: SymbolId) {
+        self.current_function = Some(id);
+    }
+
+    /// Functions that were declared by a prototype but never defined, sorted by [SymbolId].
+    /// `extern` functions are defined in another file, so they are not included.
+    pub fn get_undefined_functions(&self) -> Vec<(SymbolId, SymbolName)> {
+        self.tree
+            .functions
+            .iter()
+            .filter(|(id, f)| f.tree.is_none() && self.get_linkage(id) != Linkage::Imported)
+            .map(|(id, f)| (*id, f.name.clone()))
+            .sorted_by_key(|(id, _)| id.0)
+            .collect()
+    }
+
+    pub fn get_main_id(&self) -> Option<SymbolId> {
+        self.table.get_main_id()
+    }
+
+    pub fn get_linkage(&self, id: &SymbolId) -> Linkage {
+        self.table
+            .get_linkage(id)
+            .expect("Invariant violated: Symbol id not found")
+    }
+
+    pub fn set_linkage(&mut self, id: &SymbolId, linkage: Linkage) {
+        self.table.set_linkage(id, linkage)
+    }
+
+    pub fn set_initializer(&mut self, id: &SymbolId, values: Vec<ConstantNodeValue>) {
+        self.table.set_initializer(id, values)
+    }
+
+    pub fn add_string(&mut self, string: String) -> usize {
+        self.table.add_string(string)
+    }
+
     pub fn leave_function(&mut self) {
         self.current_function = None;
     }
@@ -125,7 +227,7 @@ impl SyntaxBuilder {
         } else {
             SymbolScope::Global
         };
-        let name = symbol.borrow().name.clone();
+        let name = symbol.name.clone();
         let id = self.table.add_symbol(symbol, scope);
         // We checked whether the symbol is defined already at the beginning of the function
         self.scope_manager.add_symbol(id, name)?;
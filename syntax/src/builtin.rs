@@ -0,0 +1,574 @@
+use crate::{id::SymbolName, symbol::ReturnType};
+
+/// Input and output of the builtins of an interpreted program
+pub trait ProgramIO {
+    /// Reads the next whitespace-separated integer, or 0 if the input does not start with one
+    fn read_integer(&mut self) -> Result<i64, String>;
+    /// Reads the next whitespace-separated real, or 0 if the input does not start with one
+    fn read_real(&mut self) -> Result<f64, String>;
+    /// Reads a single byte, or [None] at the end of the input
+    fn read_char(&mut self) -> Result<Option<u8>, String>;
+    /// Writes a single line of output
+    fn write_line(&mut self, line: &str) -> Result<(), String>;
+    /// Writes bytes of output without ending the line
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String>;
+}
+
+/// A call to a builtin in the interpreter
+pub struct BuiltinCall<'a> {
+    /// The arguments, wrapped to the types of the params.
+    /// Reals are the bits of their `f64`, strings are their index in the symbol table.
+    pub args: &'a [i64],
+    pub strings: &'a [String],
+    pub io: &'a mut dyn ProgramIO,
+}
+
+/// Runs a builtin in the interpreter, and returns its return value if it has one
+pub type Interpret = fn(&mut BuiltinCall) -> Result<Option<i64>, String>;
+
+/// A function that is provided to every program rather than defined in it
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: SymbolName,
+    pub return_type: ReturnType,
+    pub params: Vec<(SymbolName, ReturnType)>,
+    /// How the interpreter runs the builtin. Without it, programs that call it cannot be interpreted.
+    pub interpret: Option<Interpret>,
+    /// x86-64 assembly that defines the builtin in the executable.
+    /// Without it, the builtin must be linked in from another file.
+    pub assembly: Option<String>,
+    /// x86-64 assembly that defines the builtin without libc, using Linux system calls
+    /// and the routines in [FREESTANDING_RUNTIME].
+    /// Without it, freestanding programs cannot call the builtin.
+    pub freestanding_assembly: Option<String>,
+}
+
+impl Builtin {
+    /// A builtin without any runtime implementation
+    pub fn new(name: &str, return_type: ReturnType, params: &[(&str, ReturnType)]) -> Self {
+        Self {
+            name: SymbolName::from(name),
+            return_type,
+            params: params
+                .iter()
+                .map(|(name, ret)| (SymbolName::from(*name), *ret))
+                .collect(),
+            interpret: None,
+            assembly: None,
+            freestanding_assembly: None,
+        }
+    }
+}
+
+/// The builtins that are available to a program, in the order they are declared.
+/// [BuiltinRegistry::default] contains the standard builtins, such as `writeinteger`.
+#[derive(Clone)]
+pub struct BuiltinRegistry {
+    builtins: Vec<Builtin>,
+}
+
+impl BuiltinRegistry {
+    /// A registry without any builtins
+    pub fn new() -> Self {
+        Self { builtins: vec![] }
+    }
+
+    /// Adds `builtin`, replacing the builtin with the same name if there is one
+    pub fn register(&mut self, builtin: Builtin) -> &mut Self {
+        match self.builtins.iter_mut().find(|b| b.name == builtin.name) {
+            Some(existing) => *existing = builtin,
+            None => self.builtins.push(builtin),
+        }
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Builtin> {
+        self.builtins.iter()
+    }
+}
+
+impl Default for BuiltinRegistry {
+    fn default() -> Self {
+        use ReturnType::*;
+        let mut registry = Self::new();
+        registry
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, call.args[0].to_string())),
+                assembly: Some(printf(
+                    "writeinteger",
+                    ".LC1",
+                    "%d\\n",
+                    "\tmovl\t%edi, %esi\n",
+                )),
+                freestanding_assembly: Some(jump(
+                    "writeinteger",
+                    "__cm_write_signed",
+                    "\tmovslq\t%edi, %rdi\n",
+                )),
+                ..Builtin::new("writeinteger", Void, &[("i", Int)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, call.args[0].to_string())),
+                assembly: Some(printf(
+                    "writeunsigned",
+                    ".LC3",
+                    "%u\\n",
+                    "\tmovl\t%edi, %esi\n",
+                )),
+                freestanding_assembly: Some(jump(
+                    "writeunsigned",
+                    "__cm_write_unsigned",
+                    "\tmovl\t%edi, %edi\n",
+                )),
+                ..Builtin::new("writeunsigned", Void, &[("i", Uint)])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readinteger",
+                    ".LC0",
+                    "%d",
+                    -12,
+                    "\tmovl\t-12(%rbp), %eax\n",
+                )),
+                freestanding_assembly: Some(jump("readinteger", "__cm_read_integer", "")),
+                ..Builtin::new("readinteger", Int, &[])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readunsigned",
+                    ".LC2",
+                    "%u",
+                    -12,
+                    "\tmovl\t-12(%rbp), %eax\n",
+                )),
+                freestanding_assembly: Some(jump("readunsigned", "__cm_read_integer", "")),
+                ..Builtin::new("readunsigned", Int, &[])
+            })
+            .register(Builtin {
+                interpret: Some(|call| {
+                    write_line(call, format_real(f64::from_bits(call.args[0] as u64)))
+                }),
+                assembly: Some(printf("writereal", ".LC5", "%g\\n", "\tmovl\t$1, %eax\n")),
+                ..Builtin::new("writereal", Void, &[("r", Real)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| Ok(Some(call.io.read_real()?.to_bits() as i64))),
+                assembly: Some(scanf(
+                    "readreal",
+                    ".LC4",
+                    "%lf",
+                    -8,
+                    "\tmovsd\t-8(%rbp), %xmm0\n",
+                )),
+                ..Builtin::new("readreal", Real, &[])
+            })
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, call.args[0].to_string())),
+                assembly: Some(printf(
+                    "writeinteger64",
+                    ".LC7",
+                    "%ld\\n",
+                    "\tmovq\t%rdi, %rsi\n",
+                )),
+                freestanding_assembly: Some(jump("writeinteger64", "__cm_write_signed", "")),
+                ..Builtin::new("writeinteger64", Void, &[("i", Int64)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, (call.args[0] as u64).to_string())),
+                assembly: Some(printf(
+                    "writeunsigned64",
+                    ".LC9",
+                    "%lu\\n",
+                    "\tmovq\t%rdi, %rsi\n",
+                )),
+                freestanding_assembly: Some(jump("writeunsigned64", "__cm_write_unsigned", "")),
+                ..Builtin::new("writeunsigned64", Void, &[("i", Uint64)])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readinteger64",
+                    ".LC6",
+                    "%ld",
+                    -8,
+                    "\tmovq\t-8(%rbp), %rax\n",
+                )),
+                freestanding_assembly: Some(jump("readinteger64", "__cm_read_integer", "")),
+                ..Builtin::new("readinteger64", Int64, &[])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readunsigned64",
+                    ".LC8",
+                    "%lu",
+                    -8,
+                    "\tmovq\t-8(%rbp), %rax\n",
+                )),
+                freestanding_assembly: Some(jump("readunsigned64", "__cm_read_integer", "")),
+                ..Builtin::new("readunsigned64", Uint64, &[])
+            })
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, call.args[0].to_string())),
+                assembly: Some(printf(
+                    "writeinteger16",
+                    ".LC12",
+                    "%d\\n",
+                    "\tmovswl\t%di, %esi\n",
+                )),
+                freestanding_assembly: Some(jump(
+                    "writeinteger16",
+                    "__cm_write_signed",
+                    "\tmovswq\t%di, %rdi\n",
+                )),
+                ..Builtin::new("writeinteger16", Void, &[("i", Int16)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| write_line(call, call.args[0].to_string())),
+                assembly: Some(printf(
+                    "writeunsigned16",
+                    ".LC14",
+                    "%u\\n",
+                    "\tmovzwl\t%di, %esi\n",
+                )),
+                freestanding_assembly: Some(jump(
+                    "writeunsigned16",
+                    "__cm_write_unsigned",
+                    "\tmovzwl\t%di, %edi\n",
+                )),
+                ..Builtin::new("writeunsigned16", Void, &[("i", Uint16)])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readinteger16",
+                    ".LC11",
+                    "%hd",
+                    -12,
+                    "\tmovswl\t-12(%rbp), %eax\n",
+                )),
+                freestanding_assembly: Some(jump("readinteger16", "__cm_read_integer", "")),
+                ..Builtin::new("readinteger16", Int16, &[])
+            })
+            .register(Builtin {
+                interpret: Some(read_integer),
+                assembly: Some(scanf(
+                    "readunsigned16",
+                    ".LC13",
+                    "%hu",
+                    -12,
+                    "\tmovzwl\t-12(%rbp), %eax\n",
+                )),
+                freestanding_assembly: Some(jump("readunsigned16", "__cm_read_integer", "")),
+                ..Builtin::new("readunsigned16", Uint16, &[])
+            })
+            .register(Builtin {
+                interpret: Some(|call| {
+                    // Like in C, a string ends at its first null character
+                    let string = call.strings[call.args[0] as usize].split('\0').next();
+                    call.io.write(string.unwrap().as_bytes())?;
+                    Ok(None)
+                }),
+                assembly: Some(printf(
+                    "writestring",
+                    ".LC10",
+                    "%s",
+                    "\tmovq\t%rdi, %rsi\n\tmovl\t$0, %eax\n",
+                )),
+                freestanding_assembly: Some(FREESTANDING_WRITESTRING.to_string()),
+                ..Builtin::new("writestring", Void, &[("s", String)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| {
+                    call.io.write(&[call.args[0] as u8])?;
+                    Ok(None)
+                }),
+                assembly: Some(wrap_libc("writechar", "putchar", "\tmovzbl\t%dil, %edi\n")),
+                freestanding_assembly: Some(FREESTANDING_WRITECHAR.to_string()),
+                ..Builtin::new("writechar", Void, &[("c", Uint8)])
+            })
+            .register(Builtin {
+                interpret: Some(|call| Ok(Some(call.io.read_char()?.map_or(-1, i64::from)))),
+                assembly: Some(wrap_libc("readchar", "getchar", "")),
+                freestanding_assembly: Some(jump("readchar", "__cm_getc", "")),
+                ..Builtin::new("readchar", Int, &[])
+            });
+        registry
+    }
+}
+
+fn write_line(call: &mut BuiltinCall, line: String) -> Result<Option<i64>, String> {
+    call.io.write_line(&line)?;
+    Ok(None)
+}
+
+/// The interpreter wraps the value to the return type of the builtin
+fn read_integer(call: &mut BuiltinCall) -> Result<Option<i64>, String> {
+    Ok(Some(call.io.read_integer()?))
+}
+
+/// Assembly of a builtin that prints its argument with `printf` and `format` at `label`.
+/// `setup` moves the argument into place.
+fn printf(name: &str, label: &str, format: &str, setup: &str) -> String {
+    format!(
+        concat!(
+            "{label}:\n",
+            "\t.string \"{format}\"\n",
+            "\t.globl {name}\n",
+            "{name}:\n",
+            "\tpushq\t%rbp\n",
+            "{setup}",
+            "\tleaq\t{label}(%rip), %rdi\n",
+            "\tcall\tprintf\n",
+            "\tpopq\t%rbp\n",
+            "\tret\n",
+        ),
+        name = name,
+        label = label,
+        format = format,
+        setup = setup
+    )
+}
+
+/// Assembly of a builtin that reads its return value with `scanf` and `format` at `label`
+/// into the stack slot at `offset`. `load` moves the value into the return register.
+/// The slot is cleared first, so a failed read gives 0 like in the interpreter.
+fn scanf(name: &str, label: &str, format: &str, offset: i32, load: &str) -> String {
+    format!(
+        concat!(
+            "{label}:\n",
+            "\t.string \"{format}\"\n",
+            "\t.globl {name}\n",
+            "{name}:\n",
+            "\tpushq\t%rbp\n",
+            "\tmovq\t%rsp, %rbp\n",
+            "\tsubq\t$16, %rsp\n",
+            "\tmovq\t$0, {offset}(%rbp)\n",
+            "\tleaq\t{offset}(%rbp), %rsi\n",
+            "\tleaq\t{label}(%rip), %rdi\n",
+            "\tcall\tscanf\n",
+            "{load}",
+            "\tmovq\t%rbp, %rsp\n",
+            "\tpopq\t%rbp\n",
+            "\tret\n",
+        ),
+        name = name,
+        label = label,
+        format = format,
+        offset = offset,
+        load = load
+    )
+}
+
+/// Assembly of a builtin that calls the libc function `function` with the same argument
+fn wrap_libc(name: &str, function: &str, setup: &str) -> String {
+    format!(
+        concat!(
+            "\t.globl {name}\n",
+            "{name}:\n",
+            "\tpushq\t%rbp\n",
+            "{setup}",
+            "\tcall\t{function}\n",
+            "\tpopq\t%rbp\n",
+            "\tret\n",
+        ),
+        name = name,
+        function = function,
+        setup = setup
+    )
+}
+
+/// Assembly of a freestanding builtin that jumps to the runtime routine `target`
+/// after `setup` has moved its argument into place
+fn jump(name: &str, target: &str, setup: &str) -> String {
+    format!(
+        "\t.globl {name}\n{name}:\n{setup}\tjmp\t{target}\n",
+        name = name,
+        setup = setup,
+        target = target
+    )
+}
+
+const FREESTANDING_WRITECHAR: &str = "\
+\t.globl writechar
+writechar:
+\tpushq\t%rdi
+\tmovl\t$1, %eax
+\tmovl\t$1, %edi
+\tmovq\t%rsp, %rsi
+\tmovl\t$1, %edx
+\tsyscall
+\tpopq\t%rdi
+\tret
+";
+
+const FREESTANDING_WRITESTRING: &str = "\
+\t.globl writestring
+writestring:
+\tmovq\t%rdi, %rsi
+\tmovq\t%rdi, %rdx
+1:
+\tcmpb\t$0, (%rdx)
+\tje\t2f
+\tincq\t%rdx
+\tjmp\t1b
+2:
+\tsubq\t%rsi, %rdx
+\tmovl\t$1, %eax
+\tmovl\t$1, %edi
+\tsyscall
+\tret
+";
+
+/// Routines shared by the freestanding builtins, which stand in for the parts of libc they need.
+/// Output is written straight away, and input is read a byte at a time.
+/// - `__cm_write_signed` and `__cm_write_unsigned` write the 64-bit integer in `%rdi` and a newline.
+/// - `__cm_getc` returns the next byte of input, or -1 at the end of the input.
+/// - `__cm_read_integer` skips whitespace and reads a decimal integer with an optional sign,
+///   like `scanf("%ld")`. The byte after the integer is left for the next read.
+pub const FREESTANDING_RUNTIME: &str = "\
+\t.data
+__cm_peek:
+\t.long -1
+\t.text
+__cm_write_signed:
+\tmovq\t%rdi, %rsi
+\tshrq\t$63, %rsi
+\tjz\t__cm_write_digits
+\tnegq\t%rdi
+\tjmp\t__cm_write_digits
+__cm_write_unsigned:
+\txorl\t%esi, %esi
+__cm_write_digits:
+\tpushq\t%rbp
+\tmovq\t%rsp, %rbp
+\tsubq\t$32, %rsp
+\tleaq\t-1(%rbp), %rcx
+\tmovb\t$10, (%rcx)
+\tmovq\t%rdi, %rax
+\tmovl\t$10, %r8d
+1:
+\txorl\t%edx, %edx
+\tdivq\t%r8
+\taddb\t$48, %dl
+\tdecq\t%rcx
+\tmovb\t%dl, (%rcx)
+\ttestq\t%rax, %rax
+\tjnz\t1b
+\ttestq\t%rsi, %rsi
+\tjz\t2f
+\tdecq\t%rcx
+\tmovb\t$45, (%rcx)
+2:
+\tmovl\t$1, %eax
+\tmovl\t$1, %edi
+\tmovq\t%rcx, %rsi
+\tmovq\t%rbp, %rdx
+\tsubq\t%rcx, %rdx
+\tsyscall
+\tmovq\t%rbp, %rsp
+\tpopq\t%rbp
+\tret
+__cm_getc:
+\tmovl\t__cm_peek(%rip), %eax
+\tmovl\t$-1, __cm_peek(%rip)
+\tcmpl\t$-1, %eax
+\tjne\t1f
+\tpushq\t$0
+\txorl\t%eax, %eax
+\txorl\t%edi, %edi
+\tmovq\t%rsp, %rsi
+\tmovl\t$1, %edx
+\tsyscall
+\tmovq\t%rax, %rcx
+\tpopq\t%rax
+\tcmpq\t$1, %rcx
+\tje\t1f
+\tmovl\t$-1, %eax
+1:
+\tret
+__cm_read_integer:
+\tpushq\t%rbx
+\tpushq\t%r12
+1:
+\tcall\t__cm_getc
+\tcmpl\t$32, %eax
+\tje\t1b
+\tleal\t-9(%rax), %ecx
+\tcmpl\t$4, %ecx
+\tjbe\t1b
+\txorl\t%r12d, %r12d
+\tcmpl\t$43, %eax
+\tje\t2f
+\tcmpl\t$45, %eax
+\tjne\t3f
+\tmovl\t$1, %r12d
+2:
+\tcall\t__cm_getc
+3:
+\txorl\t%ebx, %ebx
+4:
+\tleal\t-48(%rax), %ecx
+\tcmpl\t$9, %ecx
+\tja\t5f
+\timulq\t$10, %rbx
+\taddq\t%rcx, %rbx
+\tcall\t__cm_getc
+\tjmp\t4b
+5:
+\tmovl\t%eax, __cm_peek(%rip)
+\tmovq\t%rbx, %rax
+\ttestl\t%r12d, %r12d
+\tjz\t6f
+\tnegq\t%rax
+6:
+\tpopq\t%r12
+\tpopq\t%rbx
+\tret
+";
+
+/// Formats a real like `printf("%g")` does in C, so interpreted and compiled programs print the same
+fn format_real(value: f64) -> String {
+    const PRECISION: i32 = 6;
+    if value.is_nan() {
+        return if value.is_sign_negative() {
+            "-nan"
+        } else {
+            "nan"
+        }
+        .to_string();
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+    let strip_zeros = |digits: &str| {
+        if digits.contains('.') {
+            digits
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        } else {
+            digits.to_string()
+        }
+    };
+    // The exponent after rounding to the precision decides between the fixed and scientific style
+    let scientific = format!("{:.*e}", PRECISION as usize - 1, value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if !(-4..PRECISION).contains(&exponent) {
+        format!(
+            "{}e{}{:02}",
+            strip_zeros(mantissa),
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else {
+        strip_zeros(&format!(
+            "{:.*}",
+            (PRECISION - 1 - exponent) as usize,
+            value
+        ))
+    }
+}
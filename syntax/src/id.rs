@@ -1,5 +1,3 @@
-pub const BUILTIN_IDS: [usize; 4] = [1, 3, 5, 6];
-
 #[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
 pub struct SymbolId(pub usize);
 
@@ -9,12 +7,6 @@ impl std::fmt::Display for SymbolId {
     }
 }
 
-impl SymbolId {
-    pub fn is_builtin(&self) -> bool {
-        BUILTIN_IDS.contains(&self.0)
-    }
-}
-
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct SymbolName(pub String);
 
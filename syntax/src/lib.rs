@@ -1,8 +1,10 @@
 pub mod builder;
+pub mod builtin;
 pub mod error;
 pub mod id;
 pub mod node;
 pub mod scope;
+pub mod span;
 pub mod symbol;
 pub mod symbol_table;
 pub mod syntax_tree;
@@ -11,34 +13,57 @@ pub mod visitor;
 
 use std::error::Error;
 use tree_walker::TreeWalker;
-use visitor::Visitor;
 
+pub use builtin::*;
 pub use error::*;
 pub use id::*;
 pub use node::*;
+pub use span::*;
 pub use symbol::*;
 pub use symbol_table::*;
 pub use syntax_tree::*;
 pub use visitor::*;
 
 /// Take an input string and generate a [SyntaxResult] for it containing the syntax tree + symbol table.
-pub fn generate(input: &str) -> Result<SyntaxAnalysisResult, Box<dyn Error>> {
-    let parse_tree = lexical::parse(input)?;
+/// The input is either the text of the program or a [Source], which also names its file.
+/// The program can call every builtin in `builtins`, which is usually [BuiltinRegistry::default].
+pub fn generate<'a>(
+    input: impl Into<Source<'a>>,
+    builtins: &BuiltinRegistry,
+) -> Result<SyntaxAnalysisResult, Box<dyn Error>> {
+    analyze(input.into(), builtins, true)
+}
+
+/// Like [generate], but for a file that is linked into a program with its `main` in another file,
+/// so it does not have to define `main` itself
+pub fn generate_linked<'a>(
+    input: impl Into<Source<'a>>,
+    builtins: &BuiltinRegistry,
+) -> Result<SyntaxAnalysisResult, Box<dyn Error>> {
+    analyze(input.into(), builtins, false)
+}
+
+fn analyze(
+    source: Source,
+    builtins: &BuiltinRegistry,
+    requires_main: bool,
+) -> Result<SyntaxAnalysisResult, Box<dyn Error>> {
+    let parse_tree = lexical::parse(source.text)?;
     let mut tree_walker = TreeWalker::new();
-    let mut visitor = Visitor::new();
+    let mut visitor = Visitor::new(&source, builtins.clone(), requires_main);
     tree_walker.construct_syntax_tree(parse_tree, &mut visitor)?;
     let syntax_res = visitor.result();
     Ok(syntax_res)
 }
 
-pub fn display_errors(errors: &[(SyntaxBuilderError, usize)]) {
-    for (err, line) in errors {
-        log::error!("Line {}: {}", line, err);
+pub fn display_errors(errors: &[(SyntaxBuilderError, Span)]) {
+    for (err, span) in errors {
+        log::error!("{}: {}", span, err);
     }
 }
 
-pub fn display_warnings(warnings: &[(SyntaxBuilderWarning, usize)]) {
-    for (warning, line) in warnings {
-        log::warn!("Line {}: {}", line, warning);
+pub fn display_warnings(warnings: &[(SyntaxBuilderWarning, Span)]) {
+    for (warning, span) in warnings {
+        log::warn!("{}: {}", span, warning);
     }
 }
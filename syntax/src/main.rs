@@ -1,8 +1,10 @@
 pub mod builder;
+pub mod builtin;
 pub mod error;
 pub mod id;
 pub mod node;
 pub mod scope;
+pub mod span;
 pub mod symbol;
 pub mod symbol_table;
 pub mod syntax_tree;
@@ -11,26 +13,39 @@ pub mod visitor;
 
 use clap::clap_app;
 use general::logging;
-use syntax::SyntaxAnalysisResult;
+use log::LevelFilter;
+use syntax::{BuiltinRegistry, Source, SyntaxAnalysisResult};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let matches = clap_app!(myapp =>
         (version: "1.0")
         (about: "Produce an abstract syntax tree for the given input C-minus file")
         (@arg show_partial: -s --show_partial "Shows the partial syntax tree built up until this point, even in case of an error")
+        (@arg warn_narrowing: -W --warn_narrowing "Warn about explicit casts that narrow their operand, such as `(uint8_t) i`")
         (@arg INPUT: +required "Sets the input") 
     )
     .get_matches();
     let show_partial = matches.is_present("show_partial");
-    logging::init_logger_from_env();
-    let input = matches.value_of("INPUT").unwrap();
-    let input = std::fs::read_to_string(input)?;
+    let warn_narrowing = matches.is_present("warn_narrowing");
+    // The narrowing casts are logged as warnings, which are hidden by default
+    if warn_narrowing {
+        logging::init_logger_from_env_or(LevelFilter::Warn);
+    } else {
+        logging::init_logger_from_env();
+    }
+    let input_path = matches.value_of("INPUT").unwrap();
+    let input = std::fs::read_to_string(input_path)?;
+    let source = Source {
+        name: input_path,
+        text: &input,
+    };
     let SyntaxAnalysisResult {
         errors,
         warnings,
+        narrowing_casts,
         symbol_table,
         tree,
-    } = syntax::generate(&input)?;
+    } = syntax::generate(source, &BuiltinRegistry::default())?;
     let has_errors = !errors.is_empty();
     if !has_errors || show_partial {
         log::info!("\n{}", symbol_table);
@@ -40,5 +55,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         syntax::display_errors(&errors);
     }
     syntax::display_warnings(&warnings);
+    if warn_narrowing {
+        syntax::display_warnings(&narrowing_casts);
+    }
     Ok(())
 }
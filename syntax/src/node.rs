@@ -1,11 +1,13 @@
-use crate::{error::SyntaxBuilderError, id::SymbolId, symbol::ReturnType, visitor::SyntaxResult};
+use crate::{
+    error::SyntaxBuilderError, id::SymbolId, span::Span, symbol::ReturnType, visitor::SyntaxResult,
+};
 use core::fmt;
 use std::{borrow::Cow, cell::RefCell, rc::Rc};
 
 use lazy_static::lazy_static;
 
 lazy_static! {
-    pub static ref TESTING: bool = std::env::var("TESTING").map_or(false, |s| s != "0");
+    pub static ref TESTING: bool = std::env::var("TESTING").is_ok_and(|s| s != "0");
 }
 
 #[derive(PartialEq, Clone, Debug, Hash, Eq, PartialOrd, Ord, Copy)]
@@ -14,10 +16,22 @@ pub enum NodeType {
     Error,
     StatementList,
     Assignment,
+    // Compound assignments
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    ModAssign,
     If,
     IfTargets,
     While,
+    DoWhile,
+    For,
+    ForTargets,
+    Break,
+    Continue,
     ArrayAccess,
+    ArrayInitializer,
     Return,
     FunctionCall,
     ExpressionList,
@@ -40,15 +54,28 @@ pub enum NodeType {
     Mod,
     And,
 
+    // Bitwise operators
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+
     // Leafs
     Num,
     Id,
+    StringLiteral,
     Empty,
 
     // Unary
     Not,
     SignPlus,
     SignMinus,
+    BitNot,
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
     Coercion, // Int to Real coercion
 }
 
@@ -62,10 +89,21 @@ impl fmt::Display for NodeType {
                 NodeType::Error => "error",
                 NodeType::StatementList => "statement_list",
                 NodeType::Assignment => "assignment",
+                NodeType::AddAssign => "add_assign",
+                NodeType::SubAssign => "sub_assign",
+                NodeType::MulAssign => "mul_assign",
+                NodeType::DivAssign => "div_assign",
+                NodeType::ModAssign => "mod_assign",
                 NodeType::If => "if",
                 NodeType::IfTargets => "if_targets",
                 NodeType::While => "while",
+                NodeType::DoWhile => "do_while",
+                NodeType::For => "for",
+                NodeType::ForTargets => "for_targets",
+                NodeType::Break => "break",
+                NodeType::Continue => "continue",
                 NodeType::ArrayAccess => "array_access",
+                NodeType::ArrayInitializer => "array_initializer",
                 NodeType::Return => "return",
                 NodeType::FunctionCall => "function_call",
                 NodeType::ExpressionList => "expression_list",
@@ -83,12 +121,23 @@ impl fmt::Display for NodeType {
                 NodeType::IDiv => "idiv",
                 NodeType::Mod => "mod",
                 NodeType::And => "and",
+                NodeType::BitAnd => "bit_and",
+                NodeType::BitOr => "bit_or",
+                NodeType::BitXor => "bit_xor",
+                NodeType::ShiftLeft => "shift_left",
+                NodeType::ShiftRight => "shift_right",
                 NodeType::Num => "num",
                 NodeType::Id => "sym_id",
+                NodeType::StringLiteral => "string_literal",
                 NodeType::Empty => "empty",
                 NodeType::Not => "not",
                 NodeType::SignPlus => "sign_plus",
                 NodeType::SignMinus => "sign_minus",
+                NodeType::BitNot => "bit_not",
+                NodeType::PreIncrement => "pre_increment",
+                NodeType::PreDecrement => "pre_decrement",
+                NodeType::PostIncrement => "post_increment",
+                NodeType::PostDecrement => "post_decrement",
                 NodeType::Coercion => "coercion",
             }
         )
@@ -107,7 +156,22 @@ impl NodeType {
                 | Self::IDiv
                 | Self::Mod
                 | Self::And
+                | Self::BitAnd
+                | Self::BitOr
+                | Self::BitXor
+                | Self::ShiftLeft
+                | Self::ShiftRight
+                | Self::BitNot
                 | Self::Assignment
+                | Self::AddAssign
+                | Self::SubAssign
+                | Self::MulAssign
+                | Self::DivAssign
+                | Self::ModAssign
+                | Self::PreIncrement
+                | Self::PreDecrement
+                | Self::PostIncrement
+                | Self::PostDecrement
                 | Self::RelEqual
                 | Self::RelLT
                 | Self::RelGT
@@ -115,6 +179,7 @@ impl NodeType {
                 | Self::RelGTE
                 | Self::RelNotEqual
                 | Self::Num
+                | Self::StringLiteral
                 | Self::Not
                 | Self::SignPlus
                 | Self::SignMinus
@@ -125,6 +190,18 @@ impl NodeType {
         )
     }
 
+    /// The arithmetic operator applied by a compound assignment, increment or decrement
+    pub fn update_operator(&self) -> Option<NodeType> {
+        match self {
+            Self::AddAssign | Self::PreIncrement | Self::PostIncrement => Some(Self::Add),
+            Self::SubAssign | Self::PreDecrement | Self::PostDecrement => Some(Self::Sub),
+            Self::MulAssign => Some(Self::Mul),
+            Self::DivAssign => Some(Self::Div),
+            Self::ModAssign => Some(Self::Mod),
+            _ => None,
+        }
+    }
+
     pub fn is_rel_expression(&self) -> bool {
         matches!(
             self,
@@ -146,6 +223,11 @@ pub enum ConstantNodeValue {
     Int8(i8),
     Int(i32),
     Uint(u32),
+    Int16(i16),
+    Uint16(u16),
+    Int64(i64),
+    Uint64(u64),
+    Real(f64),
 }
 
 impl std::ops::Add for ConstantNodeValue {
@@ -167,6 +249,21 @@ impl std::ops::Add for ConstantNodeValue {
             (ConstantNodeValue::Uint8(x), ConstantNodeValue::Uint8(y)) => {
                 ConstantNodeValue::Uint8(u8::wrapping_add(x, y))
             }
+            (ConstantNodeValue::Int16(x), ConstantNodeValue::Int16(y)) => {
+                ConstantNodeValue::Int16(i16::wrapping_add(x, y))
+            }
+            (ConstantNodeValue::Uint16(x), ConstantNodeValue::Uint16(y)) => {
+                ConstantNodeValue::Uint16(u16::wrapping_add(x, y))
+            }
+            (ConstantNodeValue::Int64(x), ConstantNodeValue::Int64(y)) => {
+                ConstantNodeValue::Int64(i64::wrapping_add(x, y))
+            }
+            (ConstantNodeValue::Uint64(x), ConstantNodeValue::Uint64(y)) => {
+                ConstantNodeValue::Uint64(u64::wrapping_add(x, y))
+            }
+            (ConstantNodeValue::Real(x), ConstantNodeValue::Real(y)) => {
+                ConstantNodeValue::Real(x + y)
+            }
             _ => unreachable!(
                 "Cannot add two different ConstantNodeValue types: {:?} and {:?}",
                 self, rhs
@@ -194,6 +291,21 @@ impl std::ops::Sub for ConstantNodeValue {
             (ConstantNodeValue::Uint8(x), ConstantNodeValue::Uint8(y)) => {
                 ConstantNodeValue::Uint8(u8::wrapping_sub(x, y))
             }
+            (ConstantNodeValue::Int16(x), ConstantNodeValue::Int16(y)) => {
+                ConstantNodeValue::Int16(i16::wrapping_sub(x, y))
+            }
+            (ConstantNodeValue::Uint16(x), ConstantNodeValue::Uint16(y)) => {
+                ConstantNodeValue::Uint16(u16::wrapping_sub(x, y))
+            }
+            (ConstantNodeValue::Int64(x), ConstantNodeValue::Int64(y)) => {
+                ConstantNodeValue::Int64(i64::wrapping_sub(x, y))
+            }
+            (ConstantNodeValue::Uint64(x), ConstantNodeValue::Uint64(y)) => {
+                ConstantNodeValue::Uint64(u64::wrapping_sub(x, y))
+            }
+            (ConstantNodeValue::Real(x), ConstantNodeValue::Real(y)) => {
+                ConstantNodeValue::Real(x - y)
+            }
             _ => unreachable!(
                 "Cannot subtract two different ConstantNodeValue types: {:?} and {:?}",
                 self, rhs
@@ -221,6 +333,21 @@ impl std::ops::Mul for ConstantNodeValue {
             (ConstantNodeValue::Uint8(x), ConstantNodeValue::Uint8(y)) => {
                 ConstantNodeValue::Uint8(u8::wrapping_mul(x, y))
             }
+            (ConstantNodeValue::Int16(x), ConstantNodeValue::Int16(y)) => {
+                ConstantNodeValue::Int16(i16::wrapping_mul(x, y))
+            }
+            (ConstantNodeValue::Uint16(x), ConstantNodeValue::Uint16(y)) => {
+                ConstantNodeValue::Uint16(u16::wrapping_mul(x, y))
+            }
+            (ConstantNodeValue::Int64(x), ConstantNodeValue::Int64(y)) => {
+                ConstantNodeValue::Int64(i64::wrapping_mul(x, y))
+            }
+            (ConstantNodeValue::Uint64(x), ConstantNodeValue::Uint64(y)) => {
+                ConstantNodeValue::Uint64(u64::wrapping_mul(x, y))
+            }
+            (ConstantNodeValue::Real(x), ConstantNodeValue::Real(y)) => {
+                ConstantNodeValue::Real(x * y)
+            }
             _ => unreachable!(
                 "Cannot multiply two different ConstantNodeValue types: {:?} and {:?}",
                 self, rhs
@@ -248,6 +375,21 @@ impl std::ops::Div for ConstantNodeValue {
             (ConstantNodeValue::Uint8(x), ConstantNodeValue::Uint8(y)) => {
                 ConstantNodeValue::Uint8(u8::wrapping_div(x, y))
             }
+            (ConstantNodeValue::Int16(x), ConstantNodeValue::Int16(y)) => {
+                ConstantNodeValue::Int16(i16::wrapping_div(x, y))
+            }
+            (ConstantNodeValue::Uint16(x), ConstantNodeValue::Uint16(y)) => {
+                ConstantNodeValue::Uint16(u16::wrapping_div(x, y))
+            }
+            (ConstantNodeValue::Int64(x), ConstantNodeValue::Int64(y)) => {
+                ConstantNodeValue::Int64(i64::wrapping_div(x, y))
+            }
+            (ConstantNodeValue::Uint64(x), ConstantNodeValue::Uint64(y)) => {
+                ConstantNodeValue::Uint64(u64::wrapping_div(x, y))
+            }
+            (ConstantNodeValue::Real(x), ConstantNodeValue::Real(y)) => {
+                ConstantNodeValue::Real(x / y)
+            }
             _ => unreachable!(
                 "Cannot multiply two different ConstantNodeValue types: {:?} and {:?}",
                 self, rhs
@@ -258,27 +400,28 @@ impl std::ops::Div for ConstantNodeValue {
 
 impl PartialOrd for ConstantNodeValue {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let l = i64::from(*self);
-        let r = i64::from(*other);
-        i64::partial_cmp(&l, &r)
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for ConstantNodeValue {
     fn eq(&self, other: &Self) -> bool {
-        let l = i64::from(*self);
-        let r = i64::from(*other);
-        i64::eq(&l, &r)
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
 impl Eq for ConstantNodeValue {}
 
 impl Ord for ConstantNodeValue {
+    /// Reals are compared as reals. NaN is equal to everything here, so comparisons with NaN
+    /// have to be checked with [ConstantNodeValue::is_nan] before using this ordering.
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let l = i64::from(*self);
-        let r = i64::from(*other);
-        i64::cmp(&l, &r)
+        if self.is_real() || other.is_real() {
+            let l = f64::from(*self);
+            let r = f64::from(*other);
+            return l.partial_cmp(&r).unwrap_or(std::cmp::Ordering::Equal);
+        }
+        i128::cmp(&self.to_i128(), &other.to_i128())
     }
 }
 
@@ -292,6 +435,12 @@ impl fmt::Display for ConstantNodeValue {
                 Self::Int8(v) => v.to_string(),
                 Self::Int(v) => v.to_string(),
                 Self::Uint(v) => v.to_string(),
+                Self::Int16(v) => v.to_string(),
+                Self::Uint16(v) => v.to_string(),
+                Self::Int64(v) => v.to_string(),
+                Self::Uint64(v) => v.to_string(),
+                // Debug formatting keeps the fraction of whole numbers, e.g. `1.0`
+                Self::Real(v) => format!("{:?}", v),
             }
         )
     }
@@ -304,38 +453,90 @@ impl From<ConstantNodeValue> for i64 {
             ConstantNodeValue::Int8(v) => v.into(),
             ConstantNodeValue::Uint(v) => v.into(),
             ConstantNodeValue::Uint8(v) => v.into(),
+            ConstantNodeValue::Int16(v) => v.into(),
+            ConstantNodeValue::Uint16(v) => v.into(),
+            ConstantNodeValue::Int64(v) => v,
+            // Keeps the bits, so values above `i64::MAX` become negative
+            ConstantNodeValue::Uint64(v) => v as i64,
+            ConstantNodeValue::Real(v) => v as i64,
+        }
+    }
+}
+
+impl From<ConstantNodeValue> for f64 {
+    fn from(val: ConstantNodeValue) -> Self {
+        match val {
+            ConstantNodeValue::Real(v) => v,
+            ConstantNodeValue::Uint64(v) => v as f64,
+            _ => i64::from(val) as f64,
         }
     }
 }
 
 impl From<i64> for ConstantNodeValue {
-    fn from(number: i64) -> Self {
-        let number = number.to_string();
+    fn from(value: i64) -> Self {
+        let number = value.to_string();
         if let Ok(num) = number.parse::<i8>() {
             ConstantNodeValue::Int8(num)
         } else if let Ok(num) = number.parse::<u8>() {
             ConstantNodeValue::Uint8(num)
+        } else if let Ok(num) = number.parse::<i16>() {
+            ConstantNodeValue::Int16(num)
+        } else if let Ok(num) = number.parse::<u16>() {
+            ConstantNodeValue::Uint16(num)
         } else if let Ok(num) = number.parse::<i32>() {
             ConstantNodeValue::Int(num)
         } else if let Ok(num) = number.parse::<u32>() {
             ConstantNodeValue::Uint(num)
         } else {
-            panic!("Invalid number: {}", number);
+            ConstantNodeValue::Int64(value)
         }
     }
 }
 
 impl ConstantNodeValue {
+    /// Widens the value without losing the sign of large unsigned 64-bit values
+    fn to_i128(self) -> i128 {
+        match self {
+            ConstantNodeValue::Uint64(v) => v.into(),
+            _ => i64::from(self).into(),
+        }
+    }
+
     pub fn new_with_ret(value: i64, ret: ReturnType) -> Self {
         match ret {
             ReturnType::Uint8 => Self::Uint8(value as u8),
             ReturnType::Uint => Self::Uint(value as u32),
             ReturnType::Int8 => Self::Int8(value as i8),
             ReturnType::Int => Self::Int(value as i32),
+            ReturnType::Int16 => Self::Int16(value as i16),
+            ReturnType::Uint16 => Self::Uint16(value as u16),
+            ReturnType::Int64 => Self::Int64(value),
+            ReturnType::Uint64 => Self::Uint64(value as u64),
             ReturnType::Bool => Self::Uint8((value != 0) as u8),
+            ReturnType::Real => Self::Real(value as f64),
             _ => unreachable!(),
         }
     }
+
+    /// Converts the value to `ret` like a coercion or cast does.
+    /// Reals are truncated towards zero when converted to an integer type.
+    pub fn cast(self, ret: ReturnType) -> Self {
+        match (self, ret) {
+            (Self::Real(v), ReturnType::Bool) => Self::Uint8((v != 0.0) as u8),
+            (_, ReturnType::Real) => Self::Real(f64::from(self)),
+            (Self::Real(v), ReturnType::Uint64) => Self::Uint64(v as u64),
+            _ => Self::new_with_ret(i64::from(self), ret),
+        }
+    }
+
+    pub fn is_real(&self) -> bool {
+        matches!(self, Self::Real(_))
+    }
+
+    pub fn is_nan(&self) -> bool {
+        matches!(self, Self::Real(v) if v.is_nan())
+    }
 }
 
 pub struct PreorderIter {
@@ -417,13 +618,10 @@ impl Iterator for PostorderIter {
                     _ => None,
                 };
                 match (&self.last_visited, right) {
-                    (Some(last_visited), Some(right)) => {
-                        if last_visited.as_ptr() != right.as_ptr() {
-                            self.root = Some(right);
-                        } else {
-                            self.last_visited = self.nodes.pop();
-                            break self.last_visited.clone();
-                        }
+                    (Some(last_visited), Some(right))
+                        if last_visited.as_ptr() != right.as_ptr() =>
+                    {
+                        self.root = Some(right);
                     }
                     _ => {
                         self.last_visited = self.nodes.pop();
@@ -444,28 +642,39 @@ pub type SyntaxNodeChild = Option<SyntaxNodeBox>;
 #[derive(PartialEq, Eq)]
 pub struct SyntaxNodeId(usize);
 
+/// A node of the syntax tree. Every node but [SyntaxNode::Error] and [SyntaxNode::Empty]
+/// has the [Span] of the source code it was made from.
 #[derive(PartialEq, Clone, Debug)]
 pub enum SyntaxNode {
     Unary {
         node_type: NodeType,
         return_type: ReturnType,
         child: SyntaxNodeChild,
+        span: Span,
     },
     Binary {
         node_type: NodeType,
         return_type: ReturnType,
         left: SyntaxNodeChild,
         right: SyntaxNodeChild,
+        span: Span,
     },
     Constant {
         node_type: NodeType,
         return_type: ReturnType,
         value: ConstantNodeValue,
+        span: Span,
     },
     Symbol {
         node_type: NodeType,
         return_type: ReturnType,
         symbol_id: SymbolId,
+        span: Span,
+    },
+    /// A string literal, stored in the symbol table at `index`
+    StringLiteral {
+        index: usize,
+        span: Span,
     },
     Error,
     Empty,
@@ -495,10 +704,20 @@ impl SyntaxNode {
         if from_ret_t == to {
             Ok(from)
         }
+        // Strings are only passed as they are
+        else if (from_ret_t == ReturnType::String && to != ReturnType::Error)
+            || (to == ReturnType::String && from_ret_t != ReturnType::Error)
+        {
+            Err(SyntaxBuilderError(format!(
+                "Cannot coerce {} to {}",
+                from_ret_t, to
+            )))
+        }
         // ReturnTypes have a defined partial ordering for coercion.
         // Any type can be coerced to bool or error.
         else if matches!(to, ReturnType::Bool | ReturnType::Error) || from_ret_t < to {
             Ok(SyntaxNode::Unary {
+                span: from.span(),
                 child: SyntaxNode::create_child(from),
                 return_type: to,
                 node_type: NodeType::Coercion,
@@ -511,12 +730,38 @@ impl SyntaxNode {
         }
     }
 
+    /// Explicitly cast [SyntaxNode] `from` to [ReturnType] `to`.
+    /// Unlike [SyntaxNode::coerce], this allows narrowing and sign changes between any scalar types.
+    pub fn cast(from: SyntaxNode, to: ReturnType) -> SyntaxResult {
+        let from_ret_t = from.return_type();
+        if from_ret_t == ReturnType::Void {
+            return Err("Expression must have a return value".into());
+        }
+        if from_ret_t.is_array() || from_ret_t == ReturnType::String || to == ReturnType::String {
+            return Err(SyntaxBuilderError(format!(
+                "Cannot cast {} to {}",
+                from_ret_t, to
+            )));
+        }
+        if from_ret_t == to || from_ret_t == ReturnType::Error {
+            Ok(from)
+        } else {
+            Ok(SyntaxNode::Unary {
+                span: from.span(),
+                child: SyntaxNode::create_child(from),
+                return_type: to,
+                node_type: NodeType::Coercion,
+            })
+        }
+    }
+
     pub fn return_type(&self) -> ReturnType {
         match self {
             SyntaxNode::Unary { return_type, .. }
             | SyntaxNode::Binary { return_type, .. }
             | SyntaxNode::Constant { return_type, .. }
             | SyntaxNode::Symbol { return_type, .. } => *return_type,
+            SyntaxNode::StringLiteral { .. } => ReturnType::String,
             SyntaxNode::Empty => ReturnType::Void,
             SyntaxNode::Error => ReturnType::Error,
         }
@@ -528,11 +773,24 @@ impl SyntaxNode {
             | SyntaxNode::Binary { node_type, .. }
             | SyntaxNode::Constant { node_type, .. }
             | SyntaxNode::Symbol { node_type, .. } => *node_type,
+            SyntaxNode::StringLiteral { .. } => NodeType::StringLiteral,
             SyntaxNode::Empty => NodeType::Empty,
             SyntaxNode::Error => NodeType::Error,
         }
     }
 
+    /// Where the node is in the source, or [Span::none] for [SyntaxNode::Error] and [SyntaxNode::Empty]
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxNode::Unary { span, .. }
+            | SyntaxNode::Binary { span, .. }
+            | SyntaxNode::Constant { span, .. }
+            | SyntaxNode::Symbol { span, .. }
+            | SyntaxNode::StringLiteral { span, .. } => span.clone(),
+            SyntaxNode::Empty | SyntaxNode::Error => Span::none(),
+        }
+    }
+
     pub fn symbol_id(&self) -> SymbolId {
         if let SyntaxNode::Symbol { symbol_id, .. } = self {
             *symbol_id
@@ -552,11 +810,15 @@ impl SyntaxNode {
     /*
     assign => 0,
     or | and => 1,
-    gt | gte | lt | lte => 2,
-    eq | neq => 3,
-    add | sub => 4,
-    mul | div | modulo => 5,
-    _ => 6, */
+    bit_or => 2,
+    bit_xor => 3,
+    bit_and => 4,
+    gt | gte | lt | lte => 5,
+    eq | neq => 6,
+    shl | shr => 7,
+    add | sub => 8,
+    mul | div | modulo => 9,
+    _ => 10, */
     pub fn precedence(&self) -> Result<u8, SyntaxBuilderError> {
         use NodeType::*;
 
@@ -564,10 +826,14 @@ impl SyntaxNode {
             match node_type {
                 Assignment => Ok(0),
                 Or | And => Ok(1),
-                RelGT | RelGTE | RelLT | RelLTE => Ok(2),
-                RelEqual | RelNotEqual => Ok(3),
-                Add | Sub => Ok(4),
-                Mul | Div | Mod => Ok(5),
+                BitOr => Ok(2),
+                BitXor => Ok(3),
+                BitAnd => Ok(4),
+                RelGT | RelGTE | RelLT | RelLTE => Ok(5),
+                RelEqual | RelNotEqual => Ok(6),
+                ShiftLeft | ShiftRight => Ok(7),
+                Add | Sub => Ok(8),
+                Mul | Div | Mod => Ok(9),
                 _ => Err(SyntaxBuilderError(format!(
                     "Node {} is not an infix operator",
                     self
@@ -635,12 +901,21 @@ impl SyntaxNode {
                 | NodeType::RelGTE
                 | NodeType::RelLT
                 | NodeType::RelLTE
+                | NodeType::BitAnd
+                | NodeType::BitOr
+                | NodeType::BitXor
+                | NodeType::ShiftLeft
+                | NodeType::ShiftRight
         )
     }
     pub fn is_unop(&self) -> bool {
         matches!(
             self.node_type(),
-            NodeType::SignMinus | NodeType::Not | NodeType::SignPlus | NodeType::Coercion
+            NodeType::SignMinus
+                | NodeType::Not
+                | NodeType::SignPlus
+                | NodeType::BitNot
+                | NodeType::Coercion
         )
     }
 }
@@ -672,6 +947,7 @@ impl fmt::Display for SyntaxNode {
                     write!(f, "symbol - Sym:{}", symbol_id)
                 }
             }
+            Self::StringLiteral { index, .. } => write!(f, "string_literal - #{}", index),
             Self::Empty => write!(f, "[EMPTY]"),
             Self::Error => write!(f, "ERROR"),
         }
@@ -683,7 +959,7 @@ impl ptree::TreeItem for SyntaxNode {
     fn write_self<W: std::io::Write>(&self, f: &mut W, _: &ptree::Style) -> std::io::Result<()> {
         write!(f, "{}", self)
     }
-    fn children(&self) -> Cow<[Self::Child]> {
+    fn children(&self) -> Cow<'_, [Self::Child]> {
         match self {
             Self::Unary { child, .. } => {
                 if let Some(node) = child {
@@ -707,6 +983,7 @@ impl ptree::TreeItem for SyntaxNode {
             }
             Self::Constant { .. } => Cow::from(vec![]),
             Self::Symbol { .. } => Cow::from(vec![]),
+            Self::StringLiteral { .. } => Cow::from(vec![]),
             Self::Empty | Self::Error => Cow::from(vec![]),
         }
     }
@@ -76,7 +76,6 @@ impl ScopeManager {
             .last()
             .expect("Invariant violated: Scope stack was empty")
             .symbols
-            .get(name)
-            .is_some()
+            .contains_key(name)
     }
 }
@@ -0,0 +1,116 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use crate::id::Linenumber;
+
+/// The source code of a program, and the name of the file it was read from
+pub struct Source<'a> {
+    /// Empty if the source was not read from a file
+    pub name: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> From<&'a str> for Source<'a> {
+    fn from(text: &'a str) -> Self {
+        Self { name: "", text }
+    }
+}
+
+impl<'a> From<&'a String> for Source<'a> {
+    fn from(text: &'a String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+/// Where a part of a program is in its source file
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Span {
+    /// The name of the source file, empty if the source was not read from a file
+    pub file: Rc<str>,
+    /// The line the span starts on, starting at 1
+    pub line: Linenumber,
+    /// The character in its line that the span starts at, starting at 1
+    pub column: usize,
+    /// The bytes of the source that the span covers
+    pub bytes: Range<usize>,
+}
+
+impl Span {
+    /// The span of something that is not in the source, such as a builtin or a temporary.
+    /// Its line is 0.
+    pub fn none() -> Self {
+        Self {
+            file: Rc::from(""),
+            line: 0,
+            column: 0,
+            bytes: 0..0,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.line == 0
+    }
+
+    /// The span from the start of `self` to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+        if self.is_none() {
+            return other.clone();
+        }
+        if other.is_none() {
+            return self.clone();
+        }
+        Span {
+            bytes: self.bytes.start..other.bytes.end.max(self.bytes.end),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.file.is_empty() {
+            write!(f, "{}:", self.file)?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Turns byte ranges of a source into [Span]s
+pub struct SourceMap {
+    file: Rc<str>,
+    text: String,
+    /// The byte at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(source: &Source) -> Self {
+        let bytes = source.text.as_bytes();
+        let mut line_starts = vec![0];
+        for (i, byte) in bytes.iter().enumerate() {
+            // `\r\n` is a single line break
+            let is_break = *byte == b'\n' || (*byte == b'\r' && bytes.get(i + 1) != Some(&b'\n'));
+            if is_break {
+                line_starts.push(i + 1);
+            }
+        }
+        Self {
+            file: Rc::from(source.name),
+            text: source.text.to_string(),
+            line_starts,
+        }
+    }
+
+    pub fn span(&self, bytes: Range<usize>) -> Span {
+        let line = self
+            .line_starts
+            .partition_point(|start| *start <= bytes.start);
+        let line_start = self.line_starts[line - 1];
+        let column = self.text[line_start..bytes.start].chars().count() + 1;
+        Span {
+            file: self.file.clone(),
+            line,
+            column,
+            bytes,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::id::SymbolName;
+use crate::{id::SymbolName, span::Span};
 use core::fmt;
 use std::convert::From;
 
@@ -15,9 +15,20 @@ pub enum ReturnType {
     UintArray,
     Uint8,
     Uint8Array,
+    Int16,
+    Int16Array,
+    Uint16,
+    Uint16Array,
+    Int64,
+    Int64Array,
+    Uint64,
+    Uint64Array,
     Real,
+    RealArray,
     Bool,
     Label,
+    /// A string literal, which can only be passed to a builtin
+    String,
 }
 
 impl From<&ReturnType> for usize {
@@ -27,22 +38,32 @@ impl From<&ReturnType> for usize {
             ReturnType::Bool => 1,
             ReturnType::Int8 => 2,
             ReturnType::Uint8 => 3,
-            ReturnType::Int => 4,
-            ReturnType::Uint => 5,
-            _ => 6, // Other types cannot be coerced
+            ReturnType::Int16 => 4,
+            ReturnType::Uint16 => 5,
+            ReturnType::Int => 6,
+            ReturnType::Uint => 7,
+            ReturnType::Int64 => 8,
+            ReturnType::Uint64 => 9,
+            ReturnType::Real => 10,
+            _ => 11, // Other types cannot be coerced
         }
     }
 }
 
 impl PartialOrd for ReturnType {
     /// Defines a partial ordering for the purpose of type coercion as follows:
-    /// ```
+    /// ```text
     /// From      To
-    /// UINT      BOOL
-    /// INT       UINT, BOOL
-    /// UINT8     INT, UINT, BOOL
-    /// INT8      UINT8, INT, UINT, BOOL
-    /// BOOL      INT8, UINT8, INT, UINT
+    /// REAL      BOOL
+    /// UINT64    REAL, BOOL
+    /// INT64     UINT64, REAL, BOOL
+    /// UINT      INT64, UINT64, REAL, BOOL
+    /// INT       UINT, INT64, UINT64, REAL, BOOL
+    /// UINT16    INT, UINT, INT64, UINT64, REAL, BOOL
+    /// INT16     UINT16, INT, UINT, INT64, UINT64, REAL, BOOL
+    /// UINT8     INT16, UINT16, INT, UINT, INT64, UINT64, REAL, BOOL
+    /// INT8      UINT8, INT16, UINT16, INT, UINT, INT64, UINT64, REAL, BOOL
+    /// BOOL      INT8, UINT8, INT16, UINT16, INT, UINT, INT64, UINT64, REAL
     /// ```
     /// If `a < b` then `a` can be coerced to `b`
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -69,9 +90,19 @@ impl fmt::Display for ReturnType {
                 ReturnType::UintArray => "uint_array",
                 ReturnType::Uint8 => "uint8",
                 ReturnType::Uint8Array => "uint8_array",
+                ReturnType::Int16 => "int16",
+                ReturnType::Int16Array => "int16_array",
+                ReturnType::Uint16 => "uint16",
+                ReturnType::Uint16Array => "uint16_array",
+                ReturnType::Int64 => "int64",
+                ReturnType::Int64Array => "int64_array",
+                ReturnType::Uint64 => "uint64",
+                ReturnType::Uint64Array => "uint64_array",
                 ReturnType::Real => "real",
+                ReturnType::RealArray => "real_array",
                 ReturnType::Bool => "bool",
                 ReturnType::Label => "label",
+                ReturnType::String => "string",
             }
         )
     }
@@ -85,6 +116,11 @@ impl From<&str> for ReturnType {
             "void" => Self::Void,
             "unsigned int" | "unsigned" => Self::Uint,
             "uint8_t" => Self::Uint8,
+            "int16_t" => Self::Int16,
+            "uint16_t" => Self::Uint16,
+            "int64_t" => Self::Int64,
+            "uint64_t" => Self::Uint64,
+            "double" => Self::Real,
             _ => Self::Error,
         }
     }
@@ -97,6 +133,11 @@ impl ReturnType {
             Self::Int8 => Self::Int8Array,
             Self::Uint => Self::UintArray,
             Self::Uint8 => Self::Uint8Array,
+            Self::Int16 => Self::Int16Array,
+            Self::Uint16 => Self::Uint16Array,
+            Self::Int64 => Self::Int64Array,
+            Self::Uint64 => Self::Uint64Array,
+            Self::Real => Self::RealArray,
             _ => Self::Error,
         }
     }
@@ -107,12 +148,32 @@ impl ReturnType {
             ReturnType::Uint8Array => ReturnType::Uint8,
             ReturnType::IntArray => ReturnType::Int,
             ReturnType::UintArray => ReturnType::Uint,
+            ReturnType::Int16Array => ReturnType::Int16,
+            ReturnType::Uint16Array => ReturnType::Uint16,
+            ReturnType::Int64Array => ReturnType::Int64,
+            ReturnType::Uint64Array => ReturnType::Uint64,
+            ReturnType::RealArray => ReturnType::Real,
             _ => self,
         }
     }
 
     pub fn is_unsigned(&self) -> bool {
-        matches!(self, Self::Uint | Self::Uint8)
+        matches!(self, Self::Uint | Self::Uint8 | Self::Uint16 | Self::Uint64)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(
+            self,
+            Self::Int8Array
+                | Self::IntArray
+                | Self::Uint8Array
+                | Self::UintArray
+                | Self::Int16Array
+                | Self::Uint16Array
+                | Self::Int64Array
+                | Self::Uint64Array
+                | Self::RealArray
+        )
     }
 }
 
@@ -155,18 +216,16 @@ pub struct Symbol {
     pub name: SymbolName,
     pub return_type: ReturnType,
     pub symbol_type: SymbolType,
-    pub line: usize,
+    /// Where the symbol is declared
+    pub span: Span,
+    /// The sizes of all but the first dimension of a multi-dimensional array, in row-major order.
+    /// Empty for one-dimensional arrays and other symbols.
+    pub dimensions: Vec<usize>,
 }
 
 impl Symbol {
     pub fn is_array(&self) -> bool {
-        matches!(
-            self.return_type,
-            ReturnType::Int8Array
-                | ReturnType::IntArray
-                | ReturnType::Uint8Array
-                | ReturnType::UintArray
-        )
+        self.return_type.is_array()
     }
     pub fn is_param(&self) -> bool {
         matches!(
@@ -174,6 +233,14 @@ impl Symbol {
             SymbolType::ArrayParam | SymbolType::Parameter
         )
     }
+    /// The type of the symbol including its inner dimensions, e.g. `int_array[8]`
+    pub fn type_name(&self) -> String {
+        self.dimensions
+            .iter()
+            .fold(self.return_type.to_string(), |name, dim| {
+                format!("{}[{}]", name, dim)
+            })
+    }
 }
 
 impl fmt::Display for Symbol {
@@ -189,14 +256,16 @@ impl fmt::Display for Symbol {
 impl PartialEq for Symbol {
     fn eq(&self, other: &Self) -> bool {
         let Symbol {
-            line,
+            span,
             name,
             return_type,
             symbol_type,
+            dimensions,
         } = other;
-        self.line == *line
+        self.span == *span
             && self.name == *name
             && self.return_type == *return_type
             && self.symbol_type == *symbol_type
+            && self.dimensions == *dimensions
     }
 }
@@ -2,7 +2,10 @@ use itertools::Itertools;
 use regex::Regex;
 
 use crate::{
+    builtin::Builtin,
     id::*,
+    node::ConstantNodeValue,
+    span::Span,
     symbol::{ReturnType, Symbol, SymbolType},
 };
 use std::borrow::Borrow;
@@ -14,11 +17,23 @@ pub enum SymbolScope {
     Global,
 }
 
+/// Whether a global symbol is shared with other files when linking
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Linkage {
+    /// Defined in this file and visible to other files. The default for globals and functions.
+    External,
+    /// Defined in this file and only visible to it, i.e. `static`
+    Internal,
+    /// Declared with `extern` and defined in another file
+    Imported,
+}
+
 #[derive(Clone)]
 pub struct SymbolInfo {
     pub id: SymbolId,
     pub symbol_scope: SymbolScope,
     pub symbol: Symbol,
+    pub linkage: Linkage,
 }
 
 #[derive(Clone)]
@@ -35,6 +50,11 @@ pub struct SymbolTable {
     functions: HashMap<SymbolId, FunctionInfo>,
     id_count: usize,
     main: Option<SymbolId>,
+    /// Initial elements of global arrays, the rest of their elements are zero
+    initializers: HashMap<SymbolId, Vec<ConstantNodeValue>>,
+    /// Contents of all string literals, referred to by their index
+    strings: Vec<String>,
+    builtins: HashMap<SymbolId, Builtin>,
 }
 
 impl fmt::Display for SymbolTable {
@@ -74,12 +94,12 @@ impl fmt::Display for SymbolTable {
             .sorted_by(|(a, _), (b, _)| a.0.cmp(&b.0))
         {
             let symbol = &info.symbol;
-            if !id.is_builtin() {
+            if !self.is_builtin(id) {
                 writeln!(
                     f,
                     "{:<13} {:<13} {:<13} {:<13}",
                     format!("{}.", id.0),
-                    format!("{}", symbol.line),
+                    format!("{}", symbol.span.line),
                     format!("{}", symbol.return_type),
                     format!("{}", symbol.name)
                 )?;
@@ -96,6 +116,9 @@ impl SymbolTable {
             functions: HashMap::new(),
             id_count: SYMBOL_ID_ERROR + 1,
             main: None,
+            initializers: HashMap::new(),
+            strings: vec![],
+            builtins: HashMap::new(),
         }
     }
 
@@ -120,6 +143,7 @@ impl SymbolTable {
                 id,
                 symbol,
                 symbol_scope: scope,
+                linkage: Linkage::External,
             },
         );
 
@@ -137,7 +161,7 @@ impl SymbolTable {
                 SymbolType::Parameter | SymbolType::ArrayParam => {
                     func_info.parameters.push(id);
                 }
-                SymbolType::Variable => {
+                SymbolType::Variable | SymbolType::ArrayVariable { .. } => {
                     func_info.variables.push(id);
                 }
                 _ => {}
@@ -164,6 +188,70 @@ impl SymbolTable {
         id
     }
 
+    /// Removes the parameters of a function, so they can be declared again
+    pub fn remove_func_params(&mut self, func_id: &SymbolId) {
+        let func_info = self
+            .functions
+            .get_mut(func_id)
+            .expect("Invariant violated: Function id not found");
+        for id in func_info.parameters.drain(..) {
+            self.symbols.remove(&id);
+        }
+    }
+
+    pub fn get_linkage(&self, id: &SymbolId) -> Option<Linkage> {
+        Some(self.symbols.get(id)?.linkage)
+    }
+
+    pub fn set_linkage(&mut self, id: &SymbolId, linkage: Linkage) {
+        self.symbols
+            .get_mut(id)
+            .expect("Invariant violated: Symbol id not found")
+            .linkage = linkage;
+    }
+
+    pub fn get_initializer(&self, id: &SymbolId) -> Option<&Vec<ConstantNodeValue>> {
+        self.initializers.get(id)
+    }
+
+    pub fn set_initializer(&mut self, id: &SymbolId, values: Vec<ConstantNodeValue>) {
+        self.initializers.insert(*id, values);
+    }
+
+    pub fn add_builtin(&mut self, id: SymbolId, builtin: Builtin) {
+        self.builtins.insert(id, builtin);
+    }
+
+    pub fn get_builtin(&self, id: &SymbolId) -> Option<&Builtin> {
+        self.builtins.get(id)
+    }
+
+    pub fn is_builtin(&self, id: &SymbolId) -> bool {
+        self.builtins.contains_key(id)
+    }
+
+    /// All builtins, in the order they were declared
+    pub fn get_builtins(&self) -> Vec<&Builtin> {
+        self.builtins
+            .iter()
+            .sorted_by_key(|(id, _)| id.0)
+            .map(|(_, builtin)| builtin)
+            .collect()
+    }
+
+    /// Adds a string literal and returns its index. Identical literals share an index.
+    pub fn add_string(&mut self, string: String) -> usize {
+        if let Some(index) = self.strings.iter().position(|s| *s == string) {
+            return index;
+        }
+        self.strings.push(string);
+        self.strings.len() - 1
+    }
+
+    pub fn get_strings(&self) -> &Vec<String> {
+        &self.strings
+    }
+
     pub fn get_func_param_ids(&self, func_id: &SymbolId) -> Option<&Vec<SymbolId>> {
         Some(&self.functions.get(func_id)?.parameters)
     }
@@ -198,14 +286,16 @@ impl SymbolTable {
             SymbolInfo {
                 id,
                 symbol: Symbol {
-                    line: 0,
+                    span: Span::none(),
                     name: SymbolName(name),
                     return_type: ReturnType::Label,
                     symbol_type: SymbolType::Label,
+                    dimensions: vec![],
                 },
                 symbol_scope: SymbolScope::Local {
                     owning_function: func_id,
                 },
+                linkage: Linkage::Internal,
             },
         );
         self.id_count += 1;
@@ -225,14 +315,16 @@ impl SymbolTable {
             SymbolInfo {
                 id,
                 symbol: Symbol {
-                    line: 0,
+                    span: Span::none(),
                     name: SymbolName(name),
                     return_type,
                     symbol_type: SymbolType::TempVar,
+                    dimensions: vec![],
                 },
                 symbol_scope: SymbolScope::Local {
                     owning_function: func_id,
                 },
+                linkage: Linkage::Internal,
             },
         );
         self.id_count += 1;
@@ -252,15 +344,16 @@ impl SymbolTable {
         hm
     }
 
-    pub fn get_main_id(&self) -> SymbolId {
-        self.main.expect("Error: `main` function is undefined")
+    /// The [SymbolId] of `main`, which a file that is linked with others might not have
+    pub fn get_main_id(&self) -> Option<SymbolId> {
+        self.main
     }
 
     pub fn annotate_icode(&self, icode: String) -> String {
         let mut annotated = String::new();
+        let re = Regex::new(r"\[sym:(\d+)\]").unwrap();
         for line in icode.split('\n') {
             for part in line.split(' ') {
-                let re = Regex::new(r"\[sym:(\d+)\]").unwrap();
                 if let Some(c) = re.captures(part) {
                     let num: usize = c.get(1).unwrap().as_str().parse().unwrap();
                     let symbol = self.get_symbol(&SymbolId(num)).unwrap();
@@ -25,13 +25,6 @@ pub struct SyntaxTree {
     pub functions: HashMap<SymbolId, FunctionRoot>,
 }
 
-pub const BUILT_INS: [&str; 4] = [
-    "writeinteger",
-    "writeunsigned",
-    "readinteger",
-    "readunsigned",
-];
-
 impl SyntaxTree {
     pub fn new() -> Self {
         Self {
@@ -57,9 +50,11 @@ impl SyntaxTree {
 
     pub fn postorder_traverse(&mut self) -> Vec<PostorderIter> {
         let mut iters = vec![];
-        for (_, f) in self.functions.iter().filter(|(id, _)| !id.is_builtin()) {
-            let iter = SyntaxNode::postorder(f.tree.as_ref().unwrap());
-            iters.push(iter)
+        // `extern` functions are defined in another file and have no tree
+        for f in self.functions.values() {
+            if let Some(tree) = &f.tree {
+                iters.push(SyntaxNode::postorder(tree))
+            }
         }
         iters
     }
@@ -68,9 +63,6 @@ impl SyntaxTree {
 impl fmt::Display for SyntaxTree {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for func in self.functions.values() {
-            if BUILT_INS.contains(&func.name.0.as_str()) {
-                continue;
-            }
             if !*TESTING {
                 writeln!(f, "function `{}`", func.name)?;
             }
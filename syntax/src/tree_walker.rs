@@ -7,6 +7,7 @@ use crate::{
     id::{SymbolId, SymbolName},
     node::{NodeType, SyntaxNode},
     symbol::{ReturnType, Symbol, SymbolType},
+    symbol_table::Linkage,
     visitor::Visitor,
 };
 use lexical::{ParseNode, ParseTree, Rule};
@@ -35,6 +36,8 @@ impl From<SyntaxBuilderError> for ParserValue {
 #[derive(Default)]
 pub struct TreeWalker {
     current_decl_type: Option<ReturnType>,
+    /// Set by `extern` or `static` for the top-level declaration that follows it
+    storage_class: Option<Linkage>,
     is_func_body: bool,
     func_has_return: bool,
 }
@@ -43,6 +46,7 @@ impl TreeWalker {
     pub fn new() -> Self {
         Self {
             current_decl_type: None,
+            storage_class: None,
             is_func_body: false,
             func_has_return: false,
         }
@@ -61,11 +65,20 @@ impl TreeWalker {
         Ok(())
     }
 
+    /// Walks `parse_node`, while the visitor knows the span of its source
     fn walk_tree(&mut self, parse_node: Option<ParseNode>, visitor: &mut Visitor) -> ParserValue {
-        if parse_node.is_none() {
-            return ParserValue::End;
-        }
-        let parse_node = parse_node.unwrap();
+        let parse_node = match parse_node {
+            Some(parse_node) => parse_node,
+            None => return ParserValue::End,
+        };
+        let span = parse_node.as_span();
+        let outer = visitor.enter_span(span.start()..span.end());
+        let value = self.walk_rule(parse_node, visitor);
+        visitor.leave_span(outer);
+        value
+    }
+
+    fn walk_rule(&mut self, parse_node: ParseNode, visitor: &mut Visitor) -> ParserValue {
         log::trace!("{:?}", parse_node.as_rule());
         match parse_node.as_rule() {
             Rule::program => {
@@ -73,9 +86,12 @@ impl TreeWalker {
                 for node in parse_node.into_inner() {
                     self.walk_tree(Some(node), visitor);
                 }
+                visitor.program_end();
                 ParserValue::End
             }
-            Rule::fn_declaration => {
+            Rule::fn_declaration | Rule::fn_prototype => {
+                let is_prototype = parse_node.as_rule() == Rule::fn_prototype;
+                let storage_class = self.storage_class.take();
                 let mut nodes = parse_node.into_inner();
                 self.func_has_return = false;
                 let return_type = loop {
@@ -92,26 +108,27 @@ impl TreeWalker {
                         _ => panic!("Expected function name"),
                     };
                 };
-                let id =
-                    match visitor.visit_func_start(SymbolType::Function, return_type, name.clone())
-                    {
-                        Ok(id) => id,
-                        Err(e) => {
-                            return ParserValue::Node(e);
-                        }
-                    };
-                // Param declaration is handled in [Rule::parameter]
-                // We do need to check if that worked correctly, but don't need the return value
-                loop {
+                let params = loop {
                     match self.walk_tree(nodes.next(), visitor) {
-                        ParserValue::Ids(ids) => {
-                            log::trace!("Func parameters added: {:?}", ids);
-                            break;
-                        }
-                        ParserValue::None => break,
+                        ParserValue::Symbols(params) => break params,
                         ParserValue::Skip => continue,
                         _ => panic!("Expected function parameters"),
                     };
+                };
+                let id = match visitor.visit_func_start(
+                    SymbolType::Function,
+                    return_type,
+                    name.clone(),
+                    params,
+                    is_prototype,
+                ) {
+                    Ok(id) => id,
+                    Err(e) => return ParserValue::Node(e),
+                };
+                visitor.visit_func_linkage(&id, storage_class, is_prototype);
+                if is_prototype {
+                    visitor.visit_prototype_end();
+                    return ParserValue::Skip;
                 }
                 let func_body = loop {
                     match self.walk_tree(nodes.next(), visitor) {
@@ -166,6 +183,7 @@ impl TreeWalker {
                     }
                 }
                 self.current_decl_type = None;
+                self.storage_class = None;
                 ParserValue::Nodes(assignments)
             }
             Rule::var_decl_maybe_init => {
@@ -178,8 +196,9 @@ impl TreeWalker {
                 loop {
                     match self.walk_tree(nodes.next(), visitor) {
                         ParserValue::Name(name) => {
-                            let id = visitor.visit_var_decl(name, decl_type);
+                            let id = visitor.visit_var_decl(name, decl_type, self.storage_class);
                             current_id = Some(SyntaxNode::Symbol {
+                                span: visitor.span(),
                                 node_type: NodeType::Id,
                                 return_type: decl_type,
                                 symbol_id: id,
@@ -187,7 +206,11 @@ impl TreeWalker {
                         }
                         ParserValue::Node(node) => {
                             if let Some(var) = current_id.clone() {
-                                assignment = Some(visitor.visit_assignment(var, node));
+                                assignment = if self.storage_class == Some(Linkage::Imported) {
+                                    Some(visitor.visit_extern_initializer(&var))
+                                } else {
+                                    visitor.visit_var_initializer(var, node)
+                                };
                             } else {
                                 panic!("Invariant violated: Assignment expression without id to assign to")
                             }
@@ -206,31 +229,51 @@ impl TreeWalker {
             Rule::array_decl => {
                 let mut nodes = parse_node.into_inner();
                 let mut ident: Option<SymbolName> = None;
-                let mut size: Option<SyntaxNode> = None;
+                let mut sizes: Vec<SyntaxNode> = vec![];
+                let mut initializer: Option<Vec<SyntaxNode>> = None;
                 loop {
                     match self.walk_tree(nodes.next(), visitor) {
                         ParserValue::ReturnType(rt) => self.current_decl_type = Some(rt),
                         ParserValue::Name(name) => ident = Some(name),
-                        ParserValue::Node(n) => size = Some(n),
+                        ParserValue::Node(n) => sizes.push(n),
+                        ParserValue::Nodes(elements) => initializer = Some(elements),
                         ParserValue::Skip => continue,
                         ParserValue::End => break,
-                        _ => unreachable!("Expected array type, ident or size"),
+                        _ => unreachable!("Expected array type, ident, size or initializer"),
                     }
                 }
                 if let Some(ident) = ident {
-                    if let Some(size) = size {
-                        return match visitor.visit_array_decl(
-                            ident,
-                            size,
-                            self.current_decl_type.expect("No declaration type set"),
-                        ) {
-                            Ok(id) => ParserValue::Id(id),
-                            Err(e) => ParserValue::Node(e),
-                        };
-                    }
+                    let id = match visitor.visit_array_decl(
+                        ident,
+                        sizes,
+                        self.current_decl_type.expect("No declaration type set"),
+                        self.storage_class,
+                    ) {
+                        Ok(id) => id,
+                        Err(e) => return ParserValue::Node(e),
+                    };
+                    return match initializer
+                        .and_then(|elements| visitor.visit_array_initializer(id, elements))
+                    {
+                        Some(init) => ParserValue::Node(init),
+                        None => ParserValue::Id(id),
+                    };
                 }
                 ParserValue::Node(SyntaxNode::create_error())
             }
+            Rule::array_initializer => {
+                let mut nodes = parse_node.into_inner();
+                let mut elements = vec![];
+                loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => elements.push(n),
+                        ParserValue::Skip => continue,
+                        ParserValue::End => break,
+                        _ => unreachable!("Expected array initializer element"),
+                    }
+                }
+                ParserValue::Nodes(elements)
+            }
             Rule::formal_parameters => {
                 let mut nodes = parse_node.into_inner();
                 let mut params = vec![];
@@ -243,7 +286,7 @@ impl TreeWalker {
                                 panic!("Expected returntype for param",);
                             }
                         }
-                        ParserValue::Id(param_id) => params.push(param_id),
+                        ParserValue::Symbol(param) => params.push(param),
                         ParserValue::Skip => continue,
                         ParserValue::End | ParserValue::None => break,
                         _ => {
@@ -251,12 +294,9 @@ impl TreeWalker {
                         }
                     };
                 }
-                if params.is_empty() {
-                    ParserValue::None
-                } else {
-                    ParserValue::Ids(params)
-                }
+                ParserValue::Symbols(params)
             }
+            // Params are declared by [Visitor::visit_func_start], once the function is known
             Rule::parameter | Rule::array_parameter => {
                 let is_array = parse_node.as_str().contains('[');
                 let mut nodes = parse_node.into_inner();
@@ -274,13 +314,36 @@ impl TreeWalker {
                         _ => unreachable!("Expected identifier as name for param"),
                     };
                 };
-
-                let id = if is_array {
-                    visitor.visit_array_param_decl(ident, type_spec)
+                let mut sizes = vec![];
+                loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(size) => sizes.push(size),
+                        ParserValue::Skip => continue,
+                        ParserValue::End => break,
+                        _ => unreachable!("Expected dimension of array param"),
+                    };
+                }
+                let dimensions = visitor.visit_param_dimensions(&ident, sizes);
+                let (return_type, symbol_type) = if is_array {
+                    (type_spec.to_array_type(), SymbolType::ArrayParam)
                 } else {
-                    visitor.visit_param_decl(ident, type_spec)
+                    (type_spec, SymbolType::Parameter)
                 };
-                ParserValue::Id(id)
+                ParserValue::Symbol(Symbol {
+                    name: ident,
+                    return_type,
+                    symbol_type,
+                    span: visitor.span(),
+                    dimensions,
+                })
+            }
+            Rule::storage_class => {
+                self.storage_class = Some(match parse_node.as_str() {
+                    "extern" => Linkage::Imported,
+                    "static" => Linkage::Internal,
+                    _ => unreachable!("Unknown storage class"),
+                });
+                ParserValue::Skip
             }
             Rule::void => ParserValue::ReturnType(ReturnType::Void),
             Rule::fn_body => {
@@ -298,6 +361,8 @@ impl TreeWalker {
                 let mut statements = vec![];
                 loop {
                     match self.walk_tree(nodes.next(), visitor) {
+                        // An empty statement does nothing in a list of statements
+                        ParserValue::Node(SyntaxNode::Empty) => continue,
                         ParserValue::Node(node) => statements.push(node),
                         ParserValue::Nodes(mut nodes) => statements.append(&mut nodes),
                         ParserValue::None => continue,
@@ -354,6 +419,9 @@ impl TreeWalker {
                 let res = visitor.visit_number(parse_node);
                 ParserValue::Node(res)
             }
+            Rule::real => ParserValue::Node(visitor.visit_real(parse_node.as_str())),
+            Rule::char_literal => ParserValue::Node(visitor.visit_char(parse_node.as_str())),
+            Rule::string_literal => ParserValue::Node(visitor.visit_string(parse_node.as_str())),
             Rule::ident => {
                 let ident = SymbolName::from(parse_node.as_str());
                 ParserValue::Name(ident)
@@ -363,6 +431,7 @@ impl TreeWalker {
                 use Rule::*;
                 let op = parse_node.into_inner().next().unwrap();
                 let node = SyntaxNode::Binary {
+                    span: visitor.span(),
                     node_type: match op.as_rule() {
                         add => Add,
                         sub => Sub,
@@ -371,6 +440,11 @@ impl TreeWalker {
                         modulo => Mod,
                         and => And,
                         or => Or,
+                        bit_and => BitAnd,
+                        bit_or => BitOr,
+                        bit_xor => BitXor,
+                        shl => ShiftLeft,
+                        shr => ShiftRight,
                         eq => RelEqual,
                         neq => RelNotEqual,
                         lt => RelLT,
@@ -491,28 +565,92 @@ impl TreeWalker {
                 let assignment = visitor.visit_assignment(lvar, expr);
                 ParserValue::Node(assignment)
             }
+            Rule::compound_assignment => {
+                let mut nodes = parse_node.into_inner();
+                let lvar = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => break n,
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected lvariable"),
+                    }
+                };
+                let op = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => break n,
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected compound assignment operator"),
+                    }
+                };
+                let expr = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => break n,
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected assignment expression"),
+                    }
+                };
+                let assignment = visitor.visit_compound_assignment(lvar, op.node_type(), expr);
+                ParserValue::Node(assignment)
+            }
+            Rule::compound_op => {
+                let node = SyntaxNode::Binary {
+                    span: visitor.span(),
+                    node_type: match parse_node.as_str() {
+                        "+=" => NodeType::AddAssign,
+                        "-=" => NodeType::SubAssign,
+                        "*=" => NodeType::MulAssign,
+                        "/=" => NodeType::DivAssign,
+                        "%=" => NodeType::ModAssign,
+                        _ => unreachable!(),
+                    },
+                    return_type: ReturnType::Unknown,
+                    left: None,
+                    right: None,
+                };
+                ParserValue::Node(node)
+            }
+            Rule::pre_increment
+            | Rule::pre_decrement
+            | Rule::post_increment
+            | Rule::post_decrement => {
+                let node_type = match parse_node.as_rule() {
+                    Rule::pre_increment => NodeType::PreIncrement,
+                    Rule::pre_decrement => NodeType::PreDecrement,
+                    Rule::post_increment => NodeType::PostIncrement,
+                    _ => NodeType::PostDecrement,
+                };
+                let mut nodes = parse_node.into_inner();
+                let lvar = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => break n,
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected lvariable"),
+                    }
+                };
+                let node = visitor.visit_update(node_type, lvar);
+                ParserValue::Node(node)
+            }
             Rule::lvar | Rule::rvar => {
                 let mut nodes = parse_node.into_inner();
-                let mut access_exp: Option<SyntaxNode> = None;
+                let mut indexes: Vec<SyntaxNode> = vec![];
                 let mut name: Option<SymbolName> = None;
                 loop {
                     match self.walk_tree(nodes.next(), visitor) {
                         ParserValue::Name(n) => name = Some(n),
-                        ParserValue::Node(exp) => access_exp = Some(exp),
+                        ParserValue::Node(exp) => indexes.push(exp),
                         ParserValue::End => break,
                         ParserValue::Skip => continue,
                         _ => unreachable!("Expected identifier"),
                     }
                 }
                 let name = name.unwrap();
-                let id_node = if let Some(array_access) = access_exp {
-                    visitor.visit_array_access(&name, array_access)
-                } else {
+                let id_node = if indexes.is_empty() {
                     visitor.visit_variable(&name)
+                } else {
+                    visitor.visit_array_access(&name, indexes)
                 };
                 ParserValue::Node(id_node)
             }
-            Rule::iteration_stmt => {
+            Rule::while_stmt => {
                 let mut nodes = parse_node.into_inner();
                 let condition = loop {
                     match self.walk_tree(nodes.next(), visitor) {
@@ -523,6 +661,7 @@ impl TreeWalker {
                         }
                     }
                 };
+                visitor.enter_loop();
                 let statement = loop {
                     match self.walk_tree(nodes.next(), visitor) {
                         ParserValue::Node(stmt) => break stmt,
@@ -532,9 +671,91 @@ impl TreeWalker {
                         }
                     }
                 };
+                visitor.leave_loop();
                 let while_node = visitor.visit_while(condition, statement);
                 ParserValue::Node(while_node)
             }
+            Rule::do_while_stmt => {
+                let mut nodes = parse_node.into_inner();
+                visitor.enter_loop();
+                let statement = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(stmt) => break stmt,
+                        ParserValue::Skip => continue,
+                        _ => {
+                            unreachable!("Expected statement for do-while loop")
+                        }
+                    }
+                };
+                visitor.leave_loop();
+                let condition = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(exp) => break exp,
+                        ParserValue::Skip => continue,
+                        _ => {
+                            unreachable!("Expected condition expression for do-while loop")
+                        }
+                    }
+                };
+                let do_while_node = visitor.visit_do_while(statement, condition);
+                ParserValue::Node(do_while_node)
+            }
+            Rule::for_stmt => {
+                let mut nodes = parse_node.into_inner();
+                // Variables declared in the init statement are only visible in the loop
+                visitor.add_local_scope();
+                let mut parts = vec![];
+                while parts.len() < 3 {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => parts.push(vec![n]),
+                        ParserValue::Nodes(n) => parts.push(n),
+                        ParserValue::None => parts.push(vec![]),
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected init, condition and step of for loop"),
+                    }
+                }
+                visitor.enter_loop();
+                let statement = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(stmt) => break stmt,
+                        ParserValue::Skip => continue,
+                        _ => {
+                            unreachable!("Expected statement for for loop")
+                        }
+                    }
+                };
+                visitor.leave_loop();
+                visitor.leave_local_scope();
+                let mut parts = parts.into_iter();
+                let init = parts.next().unwrap();
+                let condition = parts.next().unwrap().pop();
+                let step = parts.next().unwrap().pop();
+                let for_node = visitor.visit_for(init, condition, step, statement);
+                ParserValue::Node(for_node)
+            }
+            Rule::for_init | Rule::for_cond | Rule::for_step => {
+                let mut part = ParserValue::None;
+                for node in parse_node.into_inner() {
+                    match self.walk_tree(Some(node), visitor) {
+                        value @ (ParserValue::Node(_) | ParserValue::Nodes(_)) => part = value,
+                        ParserValue::None | ParserValue::Skip => continue,
+                        _ => unreachable!("Expected declaration or expression in for loop"),
+                    }
+                }
+                part
+            }
+            Rule::empty_stmt => ParserValue::Node(SyntaxNode::Empty),
+            Rule::break_stmt | Rule::continue_stmt => {
+                for node in parse_node.clone().into_inner() {
+                    self.walk_tree(Some(node), visitor);
+                }
+                let node_type = if parse_node.as_rule() == Rule::break_stmt {
+                    NodeType::Break
+                } else {
+                    NodeType::Continue
+                };
+                ParserValue::Node(visitor.visit_loop_jump(node_type))
+            }
             Rule::unary => {
                 let mut nodes = parse_node.into_inner();
                 let unary_op = loop {
@@ -554,37 +775,42 @@ impl TreeWalker {
                 let node = visitor.visit_unary(unary_op, unary_child);
                 ParserValue::Node(node)
             }
+            Rule::cast => {
+                let mut nodes = parse_node.into_inner();
+                let to = match self.walk_tree(nodes.next(), visitor) {
+                    ParserValue::ReturnType(rt) => rt,
+                    _ => unreachable!("Expected type"),
+                };
+                let child = loop {
+                    match self.walk_tree(nodes.next(), visitor) {
+                        ParserValue::Node(n) => break n,
+                        ParserValue::Skip => continue,
+                        _ => unreachable!("Expected node"),
+                    }
+                };
+                ParserValue::Node(visitor.visit_cast(to, child))
+            }
             Rule::unary_op => {
                 let return_val = SyntaxNode::Unary {
+                    span: visitor.span(),
                     child: None,
                     return_type: ReturnType::Unknown,
                     node_type: match parse_node.as_str() {
                         "-" => NodeType::SignMinus,
                         "+" => NodeType::SignPlus,
                         "!" => NodeType::Not,
+                        "~" => NodeType::BitNot,
                         _ => unreachable!(),
                     },
                 };
                 ParserValue::Node(return_val)
             }
-            Rule::COMMENT | Rule::WHITESPACE => {
-                // We can never enter the newline rule, so we manually count newlines in whitespace/comments
-                let newlines = parse_node.as_str().chars().fold(0, |n, c| {
-                    if c == '\r' || c == '\n' {
-                        n + 1
-                    } else {
-                        n
-                    }
-                });
-                visitor.increase_line_nr(newlines);
-                ParserValue::Skip
-            }
-            Rule::EOI => ParserValue::Skip,
+            Rule::COMMENT | Rule::WHITESPACE | Rule::EOI => ParserValue::Skip,
             _ => {
                 unreachable!(
                     "Unimplemented rule `{:?}`:\n{}. {}",
                     parse_node.as_rule(),
-                    visitor.current_line(),
+                    visitor.span(),
                     parse_node.as_str()
                 );
             }
@@ -1,44 +1,62 @@
 use itertools::{EitherOrBoth, Itertools};
 use std::cmp::Ordering;
+use std::ops::Range;
 
+use crate::builtin::BuiltinRegistry;
 use crate::error::SyntaxBuilderWarning;
 use crate::node::{ConstantNodeValue, NodeType};
 use crate::syntax_tree::SyntaxTree;
 use crate::{
     builder::SyntaxBuilder,
     error::SyntaxBuilderError,
-    id::{Linenumber, SymbolId, SymbolName, BUILTIN_IDS},
+    id::{SymbolId, SymbolName},
     node::SyntaxNode,
+    span::{Source, SourceMap, Span},
     symbol::{ReturnType, Symbol, SymbolType},
-    symbol_table::{SymbolTable, SYMBOL_ID_ERROR},
+    symbol_table::{Linkage, SymbolTable, MAIN_FN, SYMBOL_ID_ERROR},
 };
 
 pub struct SyntaxAnalysisResult {
     pub tree: SyntaxTree,
     pub symbol_table: SymbolTable,
-    pub errors: Vec<ErrorWithLinenumber>,
-    pub warnings: Vec<WarningWithLinenumber>,
+    pub errors: Vec<ErrorWithSpan>,
+    pub warnings: Vec<WarningWithSpan>,
+    /// Explicit casts that narrow their operand, which are only shown when asked for
+    pub narrowing_casts: Vec<WarningWithSpan>,
 }
 
-type ErrorWithLinenumber = (SyntaxBuilderError, Linenumber);
-type WarningWithLinenumber = (SyntaxBuilderWarning, Linenumber);
+type ErrorWithSpan = (SyntaxBuilderError, Span);
+type WarningWithSpan = (SyntaxBuilderWarning, Span);
 
 pub struct Visitor {
     builder: SyntaxBuilder,
-    errors: Vec<ErrorWithLinenumber>,
-    warnings: Vec<WarningWithLinenumber>,
-    pub current_line: Linenumber,
+    errors: Vec<ErrorWithSpan>,
+    warnings: Vec<WarningWithSpan>,
+    narrowing_casts: Vec<WarningWithSpan>,
+    builtins: BuiltinRegistry,
+    /// Whether the program has to define `main`, which a file that is linked into it does not
+    requires_main: bool,
+    source_map: SourceMap,
+    /// The span of the part of the program that is being visited
+    span: Span,
+    /// Number of loops around the statement being visited
+    loop_depth: usize,
 }
 
 pub type SyntaxResult = Result<SyntaxNode, SyntaxBuilderError>;
 
 impl Visitor {
-    pub fn new() -> Self {
+    pub fn new(source: &Source, builtins: BuiltinRegistry, requires_main: bool) -> Self {
         Self {
             builder: SyntaxBuilder::new(),
             errors: vec![],
             warnings: vec![],
-            current_line: 1,
+            narrowing_casts: vec![],
+            builtins,
+            requires_main,
+            source_map: SourceMap::new(source),
+            span: Span::none(),
+            loop_depth: 0,
         }
     }
 
@@ -49,135 +67,324 @@ impl Visitor {
             tree,
             errors: self.errors,
             warnings: self.warnings,
+            narrowing_casts: self.narrowing_casts,
         }
     }
 
+    /// Declares every registered builtin. They come before the program, so they are always in scope.
     fn add_builtins(&mut self) {
-        let old_line = self.current_line;
-        self.current_line = 0;
-
-        // writeinteger
-        let id = self
-            .visit_func_start(
-                SymbolType::Function,
-                ReturnType::Void,
-                SymbolName::from("writeinteger"),
-            )
-            .expect("Error adding builtins: Function `writeinteger` start");
-        assert_ne!(
-            self.visit_param_decl(SymbolName::from("i"), ReturnType::Int)
-                .0,
-            SYMBOL_ID_ERROR,
-            "Error adding builtins: Param `i` for `writeinteger`"
-        );
-
-        self.visit_func_end(&id, SyntaxNode::Empty)
-            .expect("Error adding builtins: Function `writeinteger` end");
-        // writeunsigned
-        let id = self
-            .visit_func_start(
-                SymbolType::Function,
-                ReturnType::Void,
-                SymbolName::from("writeunsigned"),
-            )
-            .expect("Error adding builtins: Function `writeunsigned` start");
-        assert_eq!(id.0, BUILTIN_IDS[1]);
-        assert_ne!(
-            self.visit_param_decl(SymbolName::from("i"), ReturnType::Uint)
-                .0,
-            SYMBOL_ID_ERROR,
-            "Error adding builtins: Param `i` for `writeunsigned`"
-        );
-        self.visit_func_end(&id, SyntaxNode::Empty)
-            .expect("Error adding builtins: Function `writeunsigned` end");
-        // readinteger
-        let id = self
-            .visit_func_start(
-                SymbolType::Function,
-                ReturnType::Int,
-                SymbolName::from("readinteger"),
-            )
-            .expect("Error adding builtins: Function `readinteger` start");
-        assert_eq!(id.0, BUILTIN_IDS[2]);
-        self.visit_func_end(&id, SyntaxNode::Empty)
-            .expect("Error adding builtins: Function `readinteger` end");
-        let id = self
-            .visit_func_start(
-                SymbolType::Function,
-                ReturnType::Int,
-                SymbolName::from("readunsigned"),
-            )
-            .expect("Error adding builtins: Function `readunsigned` start");
-        assert_eq!(id.0, BUILTIN_IDS[3]);
-        self.visit_func_end(&id, SyntaxNode::Empty)
-            .expect("Error adding builtins: Function `readunsigned` end");
-        self.current_line = old_line;
-    }
-
-    pub fn current_line(&self) -> usize {
-        self.current_line
-    }
-
-    pub fn increase_line_nr(&mut self, increase: usize) {
-        self.current_line += increase;
+        for builtin in self.builtins.iter() {
+            self.builder
+                .add_builtin(builtin.clone())
+                .unwrap_or_else(|e| panic!("Error adding builtin `{}`: {}", builtin.name, e));
+        }
+    }
+
+    /// The span of the part of the program that is being visited
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// Starts visiting the part of the program at the `bytes` of the source,
+    /// and returns the span of the part that it is in
+    pub fn enter_span(&mut self, bytes: Range<usize>) -> Span {
+        std::mem::replace(&mut self.span, self.source_map.span(bytes))
+    }
+
+    /// Goes back to visiting the `outer` part of the program
+    pub fn leave_span(&mut self, outer: Span) {
+        self.span = outer;
     }
 
     pub fn program_start(&mut self) {
         self.add_builtins();
     }
 
-    /// Register a function and return its [SymbolId],
+    /// Reports every function that was declared by a prototype but never defined,
+    /// and a missing `main`
+    pub fn program_end(&mut self) {
+        if self.requires_main {
+            self.check_main_defined();
+        }
+        for (id, name) in self.builder.get_undefined_functions() {
+            let span = self.builder.get_symbol_by_id(&id).unwrap().span.clone();
+            let err =
+                SyntaxBuilderError(format!("Function `{}` is declared but never defined", name));
+            self.handle_error_at(err, span);
+        }
+    }
+
+    /// Register a function with its parameters and return its [SymbolId],
     /// or an error if it is already defined.
+    /// A function that was declared by a prototype keeps its [SymbolId],
+    /// but its return type and parameter types must match the prototype exactly.
+    /// Parameter names may differ.
+    /// A prototype may also repeat a function that is already defined, if it matches the definition.
     pub fn visit_func_start(
         &mut self,
         symbol_type: SymbolType,
         return_type: ReturnType,
         name: SymbolName,
+        params: Vec<Symbol>,
+        is_prototype: bool,
     ) -> Result<SymbolId, SyntaxNode> {
-        let id = self
+        if let Some(id) = self
             .builder
-            .enter_function(Symbol {
-                name,
-                return_type,
-                symbol_type,
-                line: self.current_line,
-            })
-            .map_err(|e| self.handle_error(e));
+            .get_defined_function(&name)
+            .filter(|_| is_prototype)
+        {
+            if let Err(e) = self.check_prototype(&id, return_type, &params) {
+                self.handle_error(e);
+            }
+            self.builder.enter_defined_function(id);
+            self.add_local_scope();
+            return Ok(id);
+        }
+        let id = match self.builder.get_prototype(&name) {
+            Some(id) => {
+                // The definition is still visited, so errors in its body are found as well
+                if let Err(e) = self.check_prototype(&id, return_type, &params) {
+                    self.handle_error(e);
+                }
+                self.builder.enter_prototype(id);
+                id
+            }
+            None => {
+                if name.0 == MAIN_FN {
+                    if let Err(e) = Self::check_main(return_type, &params) {
+                        self.handle_error(e);
+                    }
+                }
+                self.builder.enter_function(Symbol {
+                    name,
+                    return_type,
+                    symbol_type,
+                    span: self.span(),
+                    dimensions: vec![],
+                })
+            }
+            .map_err(|e| self.handle_error(e))?,
+        };
         self.add_local_scope();
-        id
+        for param in params {
+            self.visit_param_decl(param);
+        }
+        Ok(id)
     }
 
-    pub fn visit_param_decl(&mut self, name: SymbolName, return_type: ReturnType) -> SymbolId {
-        self.builder
-            .add_symbol(Symbol {
-                name,
-                return_type,
-                symbol_type: SymbolType::Parameter,
-                line: self.current_line,
-            })
-            .unwrap_or_else(|err| {
-                self.handle_error(err);
-                SymbolId(SYMBOL_ID_ERROR)
-            })
+    /// Ends a function prototype, which has no body
+    pub fn visit_prototype_end(&mut self) {
+        self.builder.leave_function();
+        self.leave_local_scope();
+    }
+
+    /// Applies the storage class of a function declaration.
+    /// Only an `extern` prototype may be left undefined, its definition is visible to other files.
+    /// A function stays `static` once one of its declarations is,
+    /// and it is not imported once this file defines it.
+    pub fn visit_func_linkage(
+        &mut self,
+        id: &SymbolId,
+        storage_class: Option<Linkage>,
+        is_prototype: bool,
+    ) {
+        let imported = is_prototype && !self.builder.is_defined(id);
+        let linkage = match (storage_class, self.builder.get_linkage(id)) {
+            (Some(Linkage::Internal), _) | (_, Linkage::Internal) => Linkage::Internal,
+            (Some(Linkage::Imported), _) | (None, Linkage::Imported) if imported => {
+                Linkage::Imported
+            }
+            _ => Linkage::External,
+        };
+        self.builder.set_linkage(id, linkage);
+    }
+
+    /// `main` has to be defined in the program with external linkage, so the runtime can call it
+    fn check_main_defined(&mut self) {
+        let id = match self.builder.get_main_id() {
+            Some(id) => id,
+            None => {
+                self.handle_error(SyntaxBuilderError::from("Program has no `main` function"));
+                return;
+            }
+        };
+        let msg = match self.builder.get_linkage(&id) {
+            Linkage::Internal => "`main` cannot be static",
+            Linkage::Imported => "`main` is declared extern but never defined",
+            Linkage::External => return,
+        };
+        let span = self.builder.get_symbol_by_id(&id).unwrap().span.clone();
+        self.handle_error_at(SyntaxBuilderError::from(msg), span);
+    }
+
+    /// `main` is called without arguments, and what it returns is the exit status of the program
+    fn check_main(return_type: ReturnType, params: &[Symbol]) -> Result<(), SyntaxBuilderError> {
+        if !matches!(return_type, ReturnType::Int | ReturnType::Void) {
+            return Err(SyntaxBuilderError(format!(
+                "`main` must return int or void, not {}",
+                return_type
+            )));
+        }
+        if !params.is_empty() {
+            return Err(SyntaxBuilderError::from("`main` cannot have parameters"));
+        }
+        Ok(())
+    }
+
+    fn check_prototype(
+        &self,
+        id: &SymbolId,
+        return_type: ReturnType,
+        params: &[Symbol],
+    ) -> Result<(), SyntaxBuilderError> {
+        let prototype = self.builder.get_symbol_by_id(id).unwrap();
+        if prototype.return_type != return_type {
+            return Err(SyntaxBuilderError(format!(
+                "Function `{}` returns {}, but its declaration on line {} returns {}",
+                prototype.name, return_type, prototype.span.line, prototype.return_type
+            )));
+        }
+        let param_types = |params: &[Symbol]| params.iter().map(Symbol::type_name).join(", ");
+        let expected = param_types(&self.builder.get_parameters(id)?);
+        let actual = param_types(params);
+        if expected != actual {
+            return Err(SyntaxBuilderError(format!(
+                "Function `{}` has parameters ({}), but its declaration on line {} has ({})",
+                prototype.name, actual, prototype.span.line, expected
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn visit_param_decl(&mut self, param: Symbol) -> SymbolId {
+        self.builder.add_symbol(param).unwrap_or_else(|err| {
+            self.handle_error(err);
+            SymbolId(SYMBOL_ID_ERROR)
+        })
     }
 
-    /// Declare a new variable and return its [SymbolId].
+    /// Declare a new variable with the storage class of its declaration and return its [SymbolId].
     /// Returns an error if the variable has already been declared in this scope.
     /// TODO: Mentions parameter shadowing in error if applicable
-    pub fn visit_var_decl(&mut self, name: SymbolName, return_type: ReturnType) -> SymbolId {
-        self.builder
-            .add_symbol(Symbol {
-                name,
-                return_type,
-                symbol_type: SymbolType::Variable,
-                line: self.current_line,
-            })
+    pub fn visit_var_decl(
+        &mut self,
+        name: SymbolName,
+        return_type: ReturnType,
+        storage_class: Option<Linkage>,
+    ) -> SymbolId {
+        let symbol = Symbol {
+            name,
+            return_type,
+            symbol_type: SymbolType::Variable,
+            span: self.span(),
+            dimensions: vec![],
+        };
+        self.declare_var(symbol, storage_class)
             .unwrap_or_else(|err| {
                 self.handle_error(err);
                 SymbolId(SYMBOL_ID_ERROR)
             })
     }
 
+    /// Adds variable `symbol` with the linkage of `storage_class`,
+    /// unless it declares a global that was declared before
+    fn declare_var(
+        &mut self,
+        symbol: Symbol,
+        storage_class: Option<Linkage>,
+    ) -> Result<SymbolId, SyntaxBuilderError> {
+        if self.builder.get_current_function().is_none() {
+            if let Some(id) = self.redeclare_global(&symbol, storage_class)? {
+                return Ok(id);
+            }
+        }
+        let id = self.builder.add_symbol(symbol)?;
+        if let Some(linkage) = storage_class {
+            self.builder.set_linkage(&id, linkage);
+        }
+        Ok(id)
+    }
+
+    /// A global may be declared again if either declaration is `extern`, like C allows.
+    /// `extern int x;` followed by `int x;` defines `x` in this file, the other way around it stays
+    /// defined. Both declarations must have the same type.
+    /// Returns [None] if `symbol` is not such a redeclaration.
+    fn redeclare_global(
+        &mut self,
+        symbol: &Symbol,
+        storage_class: Option<Linkage>,
+    ) -> Result<Option<SymbolId>, SyntaxBuilderError> {
+        let (earlier, id) = match self.builder.get_symbol_by_name(&symbol.name) {
+            Some((earlier, id)) if earlier.symbol_type != SymbolType::Function => (earlier, id),
+            _ => return Ok(None),
+        };
+        let linkage = match (self.builder.get_linkage(&id), storage_class) {
+            (linkage, Some(Linkage::Imported)) => linkage,
+            (Linkage::Imported, None) => Linkage::External,
+            // Reported as a redefinition
+            _ => return Ok(None),
+        };
+        if earlier.symbol_type != symbol.symbol_type || earlier.type_name() != symbol.type_name() {
+            return Err(SyntaxBuilderError(format!(
+                "`{}` has type {}, but its declaration on line {} has type {}",
+                symbol.name,
+                symbol.type_name(),
+                earlier.span.line,
+                earlier.type_name()
+            )));
+        }
+        self.builder.set_linkage(&id, linkage);
+        Ok(Some(id))
+    }
+
+    /// Initializes a variable in its declaration. A global is initialized in the data section
+    /// rather than by an assignment, so its initializer has to be a constant expression.
+    pub fn visit_var_initializer(
+        &mut self,
+        var: SyntaxNode,
+        exp: SyntaxNode,
+    ) -> Option<SyntaxNode> {
+        if self.builder.get_current_function().is_some() {
+            return Some(self.visit_assignment(var, exp));
+        }
+        let (id, ret_type) = (var.symbol_id(), var.return_type());
+        let value = match SyntaxNode::coerce(exp, ret_type) {
+            Ok(value) => value,
+            Err(e) => return Some(self.handle_error(e)),
+        };
+        match eval_constant(&value) {
+            Ok(constant) => {
+                self.builder
+                    .set_initializer(&id, vec![constant.cast(ret_type)]);
+                None
+            }
+            Err(_) if value.node_type() == NodeType::Error => None,
+            Err(_) => {
+                let name = self.builder.get_symbol_by_id(&id).unwrap().name.clone();
+                Some(self.handle_error(SyntaxBuilderError(format!(
+                    "Global variable `{}` must be initialized with a constant expression",
+                    name
+                ))))
+            }
+        }
+    }
+
+    /// An `extern` variable is stored in another file, so it cannot be initialized here
+    pub fn visit_extern_initializer(&mut self, var: &SyntaxNode) -> SyntaxNode {
+        let name = match var {
+            SyntaxNode::Symbol { symbol_id, .. } => self
+                .builder
+                .get_symbol_by_id(symbol_id)
+                .map(|s| s.name.to_string())
+                .unwrap_or_default(),
+            _ => unreachable!("Expected a variable to initialize"),
+        };
+        self.handle_error(SyntaxBuilderError(format!(
+            "Cannot initialize `{}`, it is declared `extern`",
+            name
+        )))
+    }
+
     pub fn visit_func_end(
         &mut self,
         id: &SymbolId,
@@ -247,8 +454,10 @@ impl Visitor {
             for pair in actual_args.zip_longest(formal_args).rev() {
                 if let EitherOrBoth::Both(mut actual_arg, formal_arg) = pair {
                     actual_arg = SyntaxNode::coerce(actual_arg, formal_arg.return_type)
+                        .and_then(|arg| self.check_array_arg(arg, &formal_arg))
                         .unwrap_or_else(|err| self.handle_error(err));
                     current_node = Some(SyntaxNode::Binary {
+                        span: actual_arg.span(),
                         node_type: NodeType::ExpressionList,
                         return_type: ReturnType::Void,
                         left: SyntaxNode::create_child(actual_arg),
@@ -261,7 +470,9 @@ impl Visitor {
             return self.handle_error(err);
         };
         SyntaxNode::Binary {
+            span: self.span(),
             left: SyntaxNode::create_child(SyntaxNode::Symbol {
+                span: self.span(),
                 node_type: NodeType::Id,
                 return_type: ReturnType::Void,
                 symbol_id: id,
@@ -272,31 +483,81 @@ impl Visitor {
         }
     }
 
+    /// An array argument must have the same inner dimensions as the array param it is passed to
+    fn check_array_arg(&self, arg: SyntaxNode, param: &Symbol) -> SyntaxResult {
+        if !param.is_array() || arg.node_type() != NodeType::Id {
+            return Ok(arg);
+        }
+        let array = self.builder.get_symbol_by_id(&arg.symbol_id()).unwrap();
+        if array.dimensions != param.dimensions {
+            return Err(SyntaxBuilderError(format!(
+                "Cannot pass `{}` of type {} as parameter `{}` of type {}",
+                array.name,
+                array.type_name(),
+                param.name,
+                param.type_name()
+            )));
+        }
+        Ok(arg)
+    }
+
     pub fn visit_number(&mut self, number: String) -> SyntaxNode {
         let node = if let Ok(num) = number.parse::<i8>() {
             SyntaxNode::Constant {
+                span: self.span(),
                 node_type: NodeType::Num,
                 value: ConstantNodeValue::Int8(num),
                 return_type: ReturnType::Int8,
             }
         } else if let Ok(num) = number.parse::<u8>() {
             SyntaxNode::Constant {
+                span: self.span(),
                 node_type: NodeType::Num,
                 value: ConstantNodeValue::Uint8(num),
                 return_type: ReturnType::Uint8,
             }
+        } else if let Ok(num) = number.parse::<i16>() {
+            SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Int16(num),
+                return_type: ReturnType::Int16,
+            }
+        } else if let Ok(num) = number.parse::<u16>() {
+            SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Uint16(num),
+                return_type: ReturnType::Uint16,
+            }
         } else if let Ok(num) = number.parse::<i32>() {
             SyntaxNode::Constant {
+                span: self.span(),
                 node_type: NodeType::Num,
                 value: ConstantNodeValue::Int(num),
                 return_type: ReturnType::Int,
             }
         } else if let Ok(num) = number.parse::<u32>() {
             SyntaxNode::Constant {
+                span: self.span(),
                 node_type: NodeType::Num,
                 value: ConstantNodeValue::Uint(num),
                 return_type: ReturnType::Uint,
             }
+        } else if let Ok(num) = number.parse::<i64>() {
+            SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Int64(num),
+                return_type: ReturnType::Int64,
+            }
+        } else if let Ok(num) = number.parse::<u64>() {
+            SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Uint64(num),
+                return_type: ReturnType::Uint64,
+            }
         } else {
             let err =
                 SyntaxBuilderError(format!("Could not convert {} to any number type", number));
@@ -305,12 +566,59 @@ impl Visitor {
         node
     }
 
+    pub fn visit_real(&mut self, number: &str) -> SyntaxNode {
+        match number.parse::<f64>() {
+            Ok(num) => SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Real(num),
+                return_type: ReturnType::Real,
+            },
+            Err(_) => {
+                let err = SyntaxBuilderError(format!("Could not convert {} to a real", number));
+                self.handle_error(err)
+            }
+        }
+    }
+
+    /// A character literal such as `'a'` is a `uint8_t` constant
+    pub fn visit_char(&mut self, literal: &str) -> SyntaxNode {
+        let c = unescape(&literal[1..literal.len() - 1])
+            .chars()
+            .next()
+            .unwrap();
+        if c.is_ascii() {
+            SyntaxNode::Constant {
+                span: self.span(),
+                node_type: NodeType::Num,
+                value: ConstantNodeValue::Uint8(c as u8),
+                return_type: ReturnType::Uint8,
+            }
+        } else {
+            let err = SyntaxBuilderError(format!(
+                "Character literal {} is not a single byte",
+                literal
+            ));
+            self.handle_error(err)
+        }
+    }
+
+    /// A string literal is stored in the symbol table, and the node refers to it
+    pub fn visit_string(&mut self, literal: &str) -> SyntaxNode {
+        let string = unescape(&literal[1..literal.len() - 1]);
+        SyntaxNode::StringLiteral {
+            span: self.span(),
+            index: self.builder.add_string(string),
+        }
+    }
+
     /// Take a `list` of [SyntaxNode]s and weave them together by making them the left child of a StatementList and linking the StatementLists.
     pub fn visit_statement_list(&mut self, list: Vec<SyntaxNode>) -> SyntaxNode {
         let mut stmt_list: Option<SyntaxNode> = None;
 
         for node in list.into_iter().rev() {
             stmt_list = Some(SyntaxNode::Binary {
+                span: node.span(),
                 left: SyntaxNode::create_child(node),
                 right: stmt_list.map(SyntaxNode::create_boxed),
                 node_type: NodeType::StatementList,
@@ -338,12 +646,14 @@ impl Visitor {
                 .unwrap_or_else(|err| self.handle_error(err));
 
             SyntaxNode::Unary {
+                span: self.span(),
                 node_type: NodeType::Return,
                 return_type: ret_node.return_type(),
                 child: SyntaxNode::create_child(ret_node),
             }
         } else {
             SyntaxNode::Unary {
+                span: self.span(),
                 node_type: NodeType::Return,
                 return_type: ReturnType::Void,
                 child: None,
@@ -357,6 +667,7 @@ impl Visitor {
             Err(err) => self.handle_error(err),
         };
         SyntaxNode::Binary {
+            span: self.span(),
             node_type: NodeType::While,
             return_type: ReturnType::Void,
             left: SyntaxNode::create_child(expression),
@@ -364,6 +675,79 @@ impl Visitor {
         }
     }
 
+    /// Lowers a `for` loop to its `init` statements followed by a [NodeType::For] node.
+    /// A missing condition always holds.
+    pub fn visit_for(
+        &mut self,
+        init: Vec<SyntaxNode>,
+        condition: Option<SyntaxNode>,
+        step: Option<SyntaxNode>,
+        body: SyntaxNode,
+    ) -> SyntaxNode {
+        let condition = condition.unwrap_or_else(|| self.visit_number("1".to_string()));
+        let condition = match SyntaxNode::coerce(condition, ReturnType::Bool) {
+            Ok(n) => n,
+            Err(err) => self.handle_error(err),
+        };
+        let for_node = SyntaxNode::Binary {
+            span: self.span(),
+            node_type: NodeType::For,
+            return_type: ReturnType::Void,
+            left: SyntaxNode::create_child(condition),
+            right: SyntaxNode::create_child(SyntaxNode::Binary {
+                span: self.span(),
+                node_type: NodeType::ForTargets,
+                return_type: ReturnType::Void,
+                left: SyntaxNode::create_child(body),
+                right: SyntaxNode::create_child(step.unwrap_or(SyntaxNode::Empty)),
+            }),
+        };
+        if init.is_empty() {
+            for_node
+        } else {
+            let mut statements = init;
+            statements.push(for_node);
+            self.visit_statement_list(statements)
+        }
+    }
+
+    pub fn visit_do_while(&mut self, body: SyntaxNode, mut expression: SyntaxNode) -> SyntaxNode {
+        expression = match SyntaxNode::coerce(expression, ReturnType::Bool) {
+            Ok(n) => n,
+            Err(err) => self.handle_error(err),
+        };
+        SyntaxNode::Binary {
+            span: self.span(),
+            node_type: NodeType::DoWhile,
+            return_type: ReturnType::Void,
+            left: SyntaxNode::create_child(body),
+            right: SyntaxNode::create_child(expression),
+        }
+    }
+
+    /// Marks the start of a loop body, in which `break` and `continue` are allowed
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn leave_loop(&mut self) {
+        self.loop_depth -= 1;
+    }
+
+    /// Visits a `break` or `continue`, which is an error outside of a loop
+    pub fn visit_loop_jump(&mut self, node_type: NodeType) -> SyntaxNode {
+        if self.loop_depth == 0 {
+            let err = SyntaxBuilderError(format!("`{}` outside of a loop", node_type));
+            return self.handle_error(err);
+        }
+        SyntaxNode::Unary {
+            span: self.span(),
+            node_type,
+            return_type: ReturnType::Void,
+            child: None,
+        }
+    }
+
     pub fn visit_if(
         &mut self,
         mut condition: SyntaxNode,
@@ -377,6 +761,7 @@ impl Visitor {
 
         let rchild = if let Some(else_body) = else_body {
             SyntaxNode::Binary {
+                span: self.span(),
                 node_type: NodeType::IfTargets,
                 return_type: ReturnType::Void,
                 left: SyntaxNode::create_child(if_body),
@@ -386,6 +771,7 @@ impl Visitor {
             if_body
         };
         SyntaxNode::Binary {
+            span: self.span(),
             node_type: NodeType::If,
             return_type: ReturnType::Void,
             left: SyntaxNode::create_child(condition),
@@ -393,7 +779,26 @@ impl Visitor {
         }
     }
 
-    pub fn visit_assignment(&mut self, lvar: SyntaxNode, mut exp: SyntaxNode) -> SyntaxNode {
+    pub fn visit_assignment(&mut self, lvar: SyntaxNode, exp: SyntaxNode) -> SyntaxNode {
+        self.visit_compound_assignment(lvar, NodeType::Assignment, exp)
+    }
+
+    /// Visits `lvar <op>= exp`, or a plain assignment if `node_type` is [NodeType::Assignment].
+    /// `exp` is coerced to the type of `lvar` either way.
+    pub fn visit_compound_assignment(
+        &mut self,
+        lvar: SyntaxNode,
+        node_type: NodeType,
+        mut exp: SyntaxNode,
+    ) -> SyntaxNode {
+        if node_type != NodeType::Assignment && lvar.return_type().is_array() {
+            let err = SyntaxBuilderError(format!("Cannot apply `{}` to an array", node_type));
+            return self.handle_error(err);
+        }
+        if node_type == NodeType::ModAssign && lvar.return_type() == ReturnType::Real {
+            let err = SyntaxBuilderError(format!("Cannot apply `{}` to a real", node_type));
+            return self.handle_error(err);
+        }
         let ret_type = lvar.return_type().to_base_type();
         exp = match SyntaxNode::coerce(exp, ret_type) {
             Ok(n) => n,
@@ -401,26 +806,59 @@ impl Visitor {
         };
         log::trace!("{}", exp);
         SyntaxNode::Binary {
-            node_type: NodeType::Assignment,
+            span: self.span(),
+            node_type,
             return_type: ret_type.to_base_type(),
             left: SyntaxNode::create_child(lvar),
             right: SyntaxNode::create_child(exp),
         }
     }
 
+    /// Visits a prefix or postfix `++` or `--`, which has the type of `lvar`
+    pub fn visit_update(&mut self, node_type: NodeType, lvar: SyntaxNode) -> SyntaxNode {
+        if lvar.return_type().is_array() {
+            let err = SyntaxBuilderError(format!("Cannot apply `{}` to an array", node_type));
+            return self.handle_error(err);
+        }
+        SyntaxNode::Unary {
+            span: self.span(),
+            node_type,
+            return_type: lvar.return_type(),
+            child: SyntaxNode::create_child(lvar),
+        }
+    }
+
     pub fn visit_unary(&mut self, mut op: SyntaxNode, unary_child: SyntaxNode) -> SyntaxNode {
         let op_type = op.node_type();
         if let SyntaxNode::Unary {
             ref mut child,
             ref mut return_type,
+            ref mut span,
             ..
         } = op
         {
-            if op_type == NodeType::Not {
+            *span = self.span.clone();
+            if unary_child.return_type() == ReturnType::String {
+                let err = SyntaxBuilderError(format!("Cannot apply `{}` to a string", op_type));
+                *return_type = ReturnType::Error;
+                *child = SyntaxNode::create_child(self.handle_error(err));
+            } else if op_type == NodeType::Not {
                 *return_type = ReturnType::Bool;
                 let unary_child = SyntaxNode::coerce(unary_child, ReturnType::Bool)
                     .unwrap_or_else(|e| self.handle_error(e));
                 *child = SyntaxNode::create_child(unary_child)
+            } else if op_type == NodeType::BitNot && unary_child.return_type() == ReturnType::Real {
+                let err = SyntaxBuilderError(format!("Cannot apply `{}` to a real", op_type));
+                *return_type = ReturnType::Error;
+                *child = SyntaxNode::create_child(self.handle_error(err));
+            } else if matches!(op_type, NodeType::BitNot | NodeType::SignMinus)
+                && unary_child.return_type() == ReturnType::Bool
+            {
+                // Complementing or negating a bool should give more than 0 or 1
+                *return_type = ReturnType::Int;
+                let unary_child = SyntaxNode::coerce(unary_child, ReturnType::Int)
+                    .unwrap_or_else(|e| self.handle_error(e));
+                *child = SyntaxNode::create_child(unary_child)
             } else {
                 *return_type = unary_child.return_type();
                 *child = SyntaxNode::create_child(unary_child);
@@ -429,6 +867,24 @@ impl Visitor {
         op
     }
 
+    /// Casts `child` to `to` with an explicit `(type) expr`.
+    /// A cast that could not have been a coercion narrows its operand, and is recorded.
+    pub fn visit_cast(&mut self, to: ReturnType, child: SyntaxNode) -> SyntaxNode {
+        let from = child.return_type();
+        match SyntaxNode::cast(child, to) {
+            Ok(node) => {
+                if from > to && to != ReturnType::Bool && from != ReturnType::Error {
+                    self.narrowing_casts.push((
+                        SyntaxBuilderWarning(format!("Narrowing cast from {} to {}", from, to)),
+                        self.span(),
+                    ));
+                }
+                node
+            }
+            Err(e) => self.handle_error(e),
+        }
+    }
+
     pub fn visit_binary(
         &mut self,
         mut left_child: SyntaxNode,
@@ -436,14 +892,16 @@ impl Visitor {
         mut right_child: SyntaxNode,
     ) {
         use NodeType::*;
-        let common_ret_type;
+        let mut common_ret_type;
         if let SyntaxNode::Binary {
             ref mut return_type,
             ref mut left,
             ref mut right,
+            ref mut span,
             node_type,
         } = op
         {
+            *span = left_child.span().to(&right_child.span());
             if left_child.return_type() == right_child.return_type() {
                 common_ret_type = left_child.return_type();
             } else if left_child.return_type() < right_child.return_type() {
@@ -456,11 +914,42 @@ impl Visitor {
                     .unwrap_or_else(|e| self.handle_error(e));
             }
 
-            *return_type = match node_type {
-                RelGT | RelGTE | RelLT | RelLTE | RelNotEqual | RelEqual | And | Or => {
-                    ReturnType::Bool
+            let is_arithmetic = !matches!(
+                node_type,
+                RelGT | RelGTE | RelLT | RelLTE | RelNotEqual | RelEqual | And | Or
+            );
+            if common_ret_type == ReturnType::Bool && is_arithmetic {
+                // Like in C, bools are promoted to int, so e.g. `!0 + !0` is 2
+                common_ret_type = ReturnType::Int;
+                left_child = SyntaxNode::coerce(left_child, common_ret_type)
+                    .unwrap_or_else(|e| self.handle_error(e));
+                right_child = SyntaxNode::coerce(right_child, common_ret_type)
+                    .unwrap_or_else(|e| self.handle_error(e));
+            }
+            if common_ret_type == ReturnType::String {
+                let err = SyntaxBuilderError(format!("Cannot apply `{}` to a string", node_type));
+                left_child = self.handle_error(err);
+            } else if common_ret_type == ReturnType::Real {
+                match node_type {
+                    Mod | BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight => {
+                        let err =
+                            SyntaxBuilderError(format!("Cannot apply `{}` to a real", node_type));
+                        left_child = self.handle_error(err);
+                    }
+                    // Only the logical operators on integers test for non-zero themselves
+                    And | Or => {
+                        left_child = SyntaxNode::coerce(left_child, ReturnType::Bool)
+                            .unwrap_or_else(|e| self.handle_error(e));
+                        right_child = SyntaxNode::coerce(right_child, ReturnType::Bool)
+                            .unwrap_or_else(|e| self.handle_error(e));
+                    }
+                    _ => {}
                 }
-                _ => common_ret_type,
+            }
+            *return_type = if is_arithmetic {
+                common_ret_type
+            } else {
+                ReturnType::Bool
             };
             *left = SyntaxNode::create_child(left_child);
             *right = SyntaxNode::create_child(right_child);
@@ -481,15 +970,22 @@ impl Visitor {
             }
         };
         SyntaxNode::Symbol {
+            span: self.span(),
             node_type: NodeType::Id,
             return_type: symbol.return_type,
             symbol_id: id,
         }
     }
 
-    pub fn visit_array_access(&mut self, name: &SymbolName, expr: SyntaxNode) -> SyntaxNode {
+    /// Accesses an element of array `name`, which needs one index for each of its dimensions.
+    /// The indexes of a multi-dimensional array become an [NodeType::ExpressionList] of `uint`s.
+    pub fn visit_array_access(
+        &mut self,
+        name: &SymbolName,
+        mut indexes: Vec<SyntaxNode>,
+    ) -> SyntaxNode {
         let (symbol, id) = match self.builder.get_symbol_by_name(name) {
-            Some((s, i)) => (s, i),
+            Some((s, i)) => (s.clone(), i),
             None => {
                 let err = SyntaxBuilderError(format!("Symbol `{}` is not defined", name));
                 return self.handle_error(err);
@@ -499,77 +995,287 @@ impl Visitor {
         if !symbol.is_array() {
             return SyntaxBuilderError(format!("Symbol {} is not an array", name)).into();
         }
+        let n_dimensions = symbol.dimensions.len() + 1;
+        if indexes.len() != n_dimensions {
+            return self.handle_error(SyntaxBuilderError(format!(
+                "Array `{}` has {} dimension(s), but is accessed with {} index(es)",
+                name,
+                n_dimensions,
+                indexes.len()
+            )));
+        }
         let id_node = SyntaxNode::Symbol {
+            span: self.span(),
             node_type: NodeType::Id,
             return_type: symbol.return_type,
             symbol_id: id,
         };
+        let access = if n_dimensions == 1 {
+            indexes.pop().unwrap()
+        } else {
+            let mut list = None;
+            while let Some(index) = indexes.pop() {
+                let index = SyntaxNode::coerce(index, ReturnType::Uint)
+                    .unwrap_or_else(|e| self.handle_error(e));
+                list = Some(SyntaxNode::Binary {
+                    span: index.span(),
+                    node_type: NodeType::ExpressionList,
+                    return_type: ReturnType::Void,
+                    left: SyntaxNode::create_child(index),
+                    right: list.map(SyntaxNode::create_boxed),
+                });
+            }
+            list.unwrap()
+        };
         SyntaxNode::Binary {
+            span: self.span(),
             node_type: NodeType::ArrayAccess,
             return_type: symbol.return_type.to_base_type(),
             left: SyntaxNode::create_child(id_node),
-            right: SyntaxNode::create_child(expr),
+            right: SyntaxNode::create_child(access),
         }
     }
 
+    /// Declare an array of `base_type` with the given dimensions and return its [SymbolId].
+    /// Each size must be a constant expression greater than 0.
     pub fn visit_array_decl(
         &mut self,
         name: SymbolName,
-        arr_size: SyntaxNode,
+        sizes: Vec<SyntaxNode>,
         base_type: ReturnType,
+        storage_class: Option<Linkage>,
     ) -> Result<SymbolId, SyntaxNode> {
-        let size = match arr_size {
-            SyntaxNode::Constant { value, .. } => {
-                let value = i64::from(value);
-                if value < 1 {
-                    Err(self.handle_error(SyntaxBuilderError::from(
-                        "Array size must be greater than 0",
-                    )))
-                } else {
-                    Ok(value as usize)
-                }
-            }
-            _ => Err(self.handle_error(SyntaxBuilderError::from(
-                "`size` was not a Constant number SyntaxNode",
-            ))),
-        }?;
+        let mut dimensions = vec![];
+        for size in sizes {
+            dimensions.push(self.eval_array_size(&name, size)?);
+        }
         let arr_symbol = Symbol {
-            line: self.current_line,
+            span: self.span(),
             name,
             return_type: base_type.to_array_type(),
-            symbol_type: SymbolType::ArrayVariable { size },
+            symbol_type: SymbolType::ArrayVariable {
+                size: dimensions.iter().product(),
+            },
+            dimensions: dimensions.split_off(1),
         };
-        self.builder
-            .add_symbol(arr_symbol)
+        self.declare_var(arr_symbol, storage_class)
             .map_err(|err| self.handle_error(err))
     }
 
-    pub fn visit_array_param_decl(&mut self, name: SymbolName, base_type: ReturnType) -> SymbolId {
-        let arr_symbol = Symbol {
-            line: self.current_line,
-            name,
-            return_type: base_type.to_array_type(),
-            symbol_type: SymbolType::ArrayParam,
+    /// Evaluates the sizes of the dimensions after the first of array param `name`
+    pub fn visit_param_dimensions(
+        &mut self,
+        name: &SymbolName,
+        sizes: Vec<SyntaxNode>,
+    ) -> Vec<usize> {
+        sizes
+            .into_iter()
+            .filter_map(|size| self.eval_array_size(name, size).ok())
+            .collect()
+    }
+
+    /// Evaluates the size of a dimension of array `name`
+    fn eval_array_size(
+        &mut self,
+        name: &SymbolName,
+        size: SyntaxNode,
+    ) -> Result<usize, SyntaxNode> {
+        if size.node_type() == NodeType::Error {
+            return Err(SyntaxNode::create_error());
+        }
+        match eval_constant(&size) {
+            Ok(value) if value.is_real() => Err(self.handle_error(SyntaxBuilderError(format!(
+                "Array size must be an integer, but `{}` has size {}",
+                name, value
+            )))),
+            Ok(value) if i64::from(value) < 1 => {
+                Err(self.handle_error(SyntaxBuilderError(format!(
+                    "Array size must be greater than 0, but `{}` has size {}",
+                    name, value
+                ))))
+            }
+            Ok(value) => Ok(i64::from(value) as usize),
+            Err(e) => Err(self.handle_error(SyntaxBuilderError(format!(
+                "Invalid size for array `{}`: {}",
+                name, e.0
+            )))),
+        }
+    }
+
+    /// Checks the `elements` that initialize array `id` against its element type.
+    /// A local array gets an [NodeType::ArrayInitializer] that stores the elements,
+    /// and zeroes the rest of the array.
+    /// The elements of a global array must be constant, they are kept in the symbol table instead
+    /// and [None] is returned.
+    pub fn visit_array_initializer(
+        &mut self,
+        id: SymbolId,
+        elements: Vec<SyntaxNode>,
+    ) -> Option<SyntaxNode> {
+        let array = self.builder.get_symbol_by_id(&id).unwrap().clone();
+        let array_node = SyntaxNode::Symbol {
+            span: self.span(),
+            node_type: NodeType::Id,
+            return_type: array.return_type,
+            symbol_id: id,
         };
-        self.builder.add_symbol(arr_symbol).unwrap_or_else(|err| {
-            self.handle_error(err);
-            SymbolId(SYMBOL_ID_ERROR)
+        if self.builder.get_linkage(&id) == Linkage::Imported {
+            return Some(self.visit_extern_initializer(&array_node));
+        }
+        let size = match array.symbol_type {
+            SymbolType::ArrayVariable { size } => size,
+            _ => unreachable!("Initializer for non-array `{}`", array.name),
+        };
+        if elements.len() > size {
+            return Some(self.handle_error(SyntaxBuilderError(format!(
+                "Too many elements to initialize `{}`. Expected at most {}, got {}",
+                array.name,
+                size,
+                elements.len()
+            ))));
+        }
+        let base_type = array.return_type.to_base_type();
+        let mut elements = elements
+            .into_iter()
+            .map(|elem| {
+                SyntaxNode::coerce(elem, base_type).unwrap_or_else(|e| self.handle_error(e))
+            })
+            .collect_vec();
+        if self.builder.get_current_function().is_none() {
+            let mut values = vec![];
+            for elem in elements.iter().filter(|e| e.node_type() != NodeType::Error) {
+                match eval_constant(elem) {
+                    Ok(value) => values.push(value.cast(base_type)),
+                    Err(_) => {
+                        self.handle_error(SyntaxBuilderError(format!(
+                            "Global array `{}` must be initialized with constant expressions",
+                            array.name
+                        )));
+                        return None;
+                    }
+                }
+            }
+            self.builder.set_initializer(&id, values);
+            return None;
+        }
+        let mut list = None;
+        while let Some(elem) = elements.pop() {
+            list = Some(SyntaxNode::Binary {
+                span: elem.span(),
+                node_type: NodeType::ExpressionList,
+                return_type: ReturnType::Void,
+                left: SyntaxNode::create_child(elem),
+                right: list.map(SyntaxNode::create_boxed),
+            });
+        }
+        Some(SyntaxNode::Binary {
+            span: self.span(),
+            node_type: NodeType::ArrayInitializer,
+            return_type: ReturnType::Void,
+            left: SyntaxNode::create_child(array_node),
+            right: list.map(SyntaxNode::create_boxed),
         })
     }
 
     /// Returns the given `err` as a [SyntaxNode]
     pub fn handle_error(&mut self, err: SyntaxBuilderError) -> SyntaxNode {
-        self.errors.push((err.clone(), self.current_line));
+        self.handle_error_at(err, self.span())
+    }
+
+    /// Like [Visitor::handle_error], for an error in another part of the program than the one being visited
+    fn handle_error_at(&mut self, err: SyntaxBuilderError, span: Span) -> SyntaxNode {
+        self.errors.push((err.clone(), span));
         err.into()
     }
 
     pub fn add_warning(&mut self, warning: &SyntaxBuilderWarning) {
-        self.warnings.push((warning.clone(), self.current_line))
+        self.warnings.push((warning.clone(), self.span()))
     }
 }
 
 impl Default for Visitor {
     fn default() -> Self {
-        Self::new()
+        Self::new(&Source::from(""), BuiltinRegistry::default(), true)
+    }
+}
+
+/// Evaluates a constant expression with `int64_t` arithmetic, so e.g. `16 * 16` does not wrap
+/// around in the `int8_t` type of its literals. Reals are evaluated with `double` arithmetic
+fn eval_constant(node: &SyntaxNode) -> Result<ConstantNodeValue, SyntaxBuilderError> {
+    let not_constant = || SyntaxBuilderError::from("Expected a constant expression");
+    match node {
+        SyntaxNode::Constant {
+            value: ConstantNodeValue::Real(value),
+            ..
+        } => Ok(ConstantNodeValue::Real(*value)),
+        SyntaxNode::Constant { value, .. } => Ok(ConstantNodeValue::Int64(i64::from(*value))),
+        SyntaxNode::Unary {
+            node_type,
+            child: Some(child),
+            return_type,
+            ..
+        } => {
+            let value = eval_constant(&child.borrow())?;
+            match (node_type, value) {
+                (NodeType::Coercion, _) if *return_type == ReturnType::Real => {
+                    Ok(value.cast(ReturnType::Real))
+                }
+                (NodeType::Coercion, _) | (NodeType::SignPlus, _) => Ok(value),
+                (NodeType::SignMinus, ConstantNodeValue::Real(v)) => {
+                    Ok(ConstantNodeValue::Real(-v))
+                }
+                (NodeType::SignMinus, _) => Ok(ConstantNodeValue::Int64(0) - value),
+                (NodeType::BitNot, _) => Ok(ConstantNodeValue::Int64(!i64::from(value))),
+                _ => Err(not_constant()),
+            }
+        }
+        SyntaxNode::Binary {
+            node_type,
+            left: Some(left),
+            right: Some(right),
+            ..
+        } => {
+            let l = eval_constant(&left.borrow())?;
+            let r = eval_constant(&right.borrow())?;
+            let (l_int, r_int) = (i64::from(l), i64::from(r));
+            if matches!(node_type, NodeType::Div | NodeType::Mod) && !r.is_real() && r_int == 0 {
+                return Err("Division by zero in a constant expression".into());
+            }
+            Ok(match node_type {
+                NodeType::Add => l + r,
+                NodeType::Sub => l - r,
+                NodeType::Mul => l * r,
+                NodeType::Div => l / r,
+                NodeType::Mod => ConstantNodeValue::Int64(l_int.wrapping_rem(r_int)),
+                NodeType::BitAnd => ConstantNodeValue::Int64(l_int & r_int),
+                NodeType::BitOr => ConstantNodeValue::Int64(l_int | r_int),
+                NodeType::BitXor => ConstantNodeValue::Int64(l_int ^ r_int),
+                NodeType::ShiftLeft => ConstantNodeValue::Int64(l_int.wrapping_shl(r_int as u32)),
+                NodeType::ShiftRight => ConstantNodeValue::Int64(l_int.wrapping_shr(r_int as u32)),
+                _ => return Err(not_constant()),
+            })
+        }
+        _ => Err(not_constant()),
+    }
+}
+
+/// Replaces the escape sequences of a character or string literal with the characters they stand for
+fn unescape(literal: &str) -> String {
+    let mut string = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+        string.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c) => c,
+            None => unreachable!("Literal ends with a backslash"),
+        });
     }
+    string
 }
@@ -1,6 +1,11 @@
-use intermediate_code::ic_generator::OptLevel;
+use intermediate_code::ic_generator::{Intermediate, OptLevel};
+use intermediate_code::interpreter::StreamIO;
 use std::io;
-use tests::{collect_tests_in_path, run_single_test, Expectation, TestStats};
+use syntax::{BuiltinRegistry, SyntaxAnalysisResult};
+use tests::{
+    collect_tests_in_path, expected_status, program_runs, run_single_test, Expectation, ProgramRun,
+    Test, TestStats, IO_EXTENSION, LINK_EXTENSION,
+};
 
 const PROGRAM_TEST_PATH: &str = "tests/testfiles/general/programs";
 const UNIT_TEST_PATH: &str = "tests/testfiles/general/units";
@@ -12,6 +17,51 @@ pub fn test_function(input: &str) -> Result<(), &str> {
         .and(Ok(()))
 }
 
+/// Like [test_function], for a file that is linked into a program and has no `main`
+pub fn linked_test_function(input: &str) -> Result<(), &str> {
+    let SyntaxAnalysisResult {
+        mut symbol_table,
+        mut tree,
+        ..
+    } = syntax::generate_linked(input, &BuiltinRegistry::default()).or(Err("error"))?;
+    intermediate_code::generate(&mut tree, &mut symbol_table, OptLevel::None)
+        .or(Err("error"))
+        .and(Ok(()))
+}
+
+/// Interprets the program in `test` once for each of its runs and compares the output
+fn interpret_program(test: &Test, opt: OptLevel) -> Result<(), String> {
+    let file = std::fs::read_to_string(&test.path).map_err(|e| e.to_string())?;
+    let SyntaxAnalysisResult {
+        mut symbol_table,
+        mut tree,
+        ..
+    } = syntax::generate(&file, &BuiltinRegistry::default()).map_err(|e| e.to_string())?;
+    let Intermediate { icode, .. } =
+        intermediate_code::generate(&mut tree, &mut symbol_table, opt).map_err(|e| e.0)?;
+    let status = expected_status(&test.path)?;
+    for ProgramRun { input, expected } in program_runs(&test.path)? {
+        let mut output = vec![];
+        let mut io = StreamIO::new(input.as_bytes(), &mut output);
+        let returned =
+            intermediate_code::interpret(&icode, &symbol_table, &mut io).map_err(|e| e.0)?;
+        let actual = String::from_utf8_lossy(&output);
+        if actual != expected {
+            return Err(format!(
+                "Expected output:\n{}\nActual output:\n{}",
+                expected, actual
+            ));
+        }
+        if returned != i64::from(status) {
+            return Err(format!(
+                "Expected `main` to return {}, but it returned {}",
+                status, returned
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn run() -> io::Result<()> {
     let mut stats = TestStats {
         total: 0,
@@ -29,10 +79,54 @@ pub fn run() -> io::Result<()> {
         .filter(|t| t.expectation == Expectation::Success)
     {
         stats.total += 1;
-        if run_single_test(test, test_function).is_ok() {
+        let test_func = if test.linked {
+            linked_test_function
+        } else {
+            test_function
+        };
+        if run_single_test(test, test_func).is_ok() {
             stats.success += 1;
         }
     }
     log::info!("[{} / {}] TESTS PASSED", stats.success, stats.total);
+
+    let mut stats = TestStats {
+        total: 0,
+        success: 0,
+    };
+    // Unit tests with an `.io` file are run like programs
+    let unit_tests = collect_tests_in_path(UNIT_TEST_PATH)?
+        .into_iter()
+        .filter(|t| t.path.with_extension(IO_EXTENSION).exists());
+    // Interpreted programs should behave the same with and without optimizations
+    for test in collect_tests_in_path(PROGRAM_TEST_PATH)?
+        .into_iter()
+        .chain(unit_tests)
+        .filter(|t| t.expectation == Expectation::Success && !t.linked)
+        .filter(|t| !t.path.with_extension(LINK_EXTENSION).exists())
+    {
+        for opt in [
+            OptLevel::None,
+            OptLevel::Pre,
+            OptLevel::Post,
+            OptLevel::Both,
+        ] {
+            stats.total += 1;
+            println!();
+            log::info!("Interpreting test {} with {:?}", test.name, opt);
+            match interpret_program(&test, opt) {
+                Ok(()) => {
+                    log::info!("↪    PASSED");
+                    stats.success += 1;
+                }
+                Err(e) => log::error!("↪   FAILED\n{}", e),
+            }
+        }
+    }
+    log::info!(
+        "[{} / {}] INTERPRETER TESTS PASSED",
+        stats.success,
+        stats.total
+    );
     Ok(())
 }
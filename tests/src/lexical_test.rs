@@ -1,8 +1,6 @@
 use std::io;
 
-use tests::{collect_tests_in_path, run_single_test};
-
-use crate::lib::TestStats;
+use tests::{collect_tests_in_path, run_single_test, TestStats};
 
 const PROGRAM_TEST_PATH: &str = "tests/testfiles/general/programs";
 const UNIT_TEST_PATH: &str = "tests/testfiles/general/units";
@@ -1,9 +1,24 @@
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const INCORRECT_TEST_DIR: &str = "incorrect";
 const PROGRAM_DIR: &str = "programs";
+/// Sidecar file with the stdin of a program. Without one, the program gets empty input.
+pub const INPUT_EXTENSION: &str = "in";
+/// Sidecar file with the expected stdout of a program. Without one, no output is expected.
+pub const OUTPUT_EXTENSION: &str = "out";
+/// Sidecar file listing other C-minus files, relative to the program, that are linked into it.
+/// Such a program cannot be interpreted, since the interpreter only runs a single file.
+pub const LINK_EXTENSION: &str = "link";
+/// Sidecar file with the expected exit status of a program. Without one, it should exit with 0.
+pub const STATUS_EXTENSION: &str = "status";
+/// Sidecar file with more runs of a program, one per line. A run is a comma-separated list of
+/// lines of input, each prefixed with `i`, and lines of expected output, each prefixed with `o`.
+pub const IO_EXTENSION: &str = "io";
+/// Empty sidecar file marking a program that needs libc, such as one that reads or writes reals.
+/// Other programs are also compiled and run freestanding.
+pub const HOSTED_EXTENSION: &str = "hosted";
 
 #[derive(Clone, Copy)]
 pub enum TestStage {
@@ -23,6 +38,14 @@ pub struct Test {
     pub name: String,
     pub path: PathBuf,
     pub expectation: Expectation,
+    /// Listed in the `.link` sidecar of another program, so it does not need a `main` of its own
+    pub linked: bool,
+}
+
+/// A single run of a program
+pub struct ProgramRun {
+    pub input: String,
+    pub expected: String,
 }
 
 pub struct TestStats {
@@ -59,6 +82,7 @@ pub fn collect_tests_in_path(path: impl Into<PathBuf>) -> io::Result<Vec<Test>>
                 };
                 tests.push(Test {
                     expectation,
+                    linked: is_linked(&entry.path())?,
                     name: entry.path().into_os_string().into_string().unwrap(),
                     path: entry.path(),
                 });
@@ -68,6 +92,22 @@ pub fn collect_tests_in_path(path: impl Into<PathBuf>) -> io::Result<Vec<Test>>
     Ok(tests)
 }
 
+/// Whether a `.link` sidecar in the directory of the file at `path` lists that file
+fn is_linked(path: &Path) -> io::Result<bool> {
+    let dir = path.parent().unwrap();
+    for entry in fs::read_dir(dir)? {
+        let sidecar = entry?.path();
+        if sidecar.extension().is_some_and(|e| e == LINK_EXTENSION)
+            && fs::read_to_string(&sidecar)?
+                .lines()
+                .any(|file| dir.join(file) == path)
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 pub fn run_single_test(test: Test, test_func: TestFunction) -> Result<(), TestFailed> {
     println!();
     log::info!("Running test {}", test.name);
@@ -88,3 +128,59 @@ pub fn run_single_test(test: Test, test_func: TestFunction) -> Result<(), TestFa
         }
     }
 }
+
+/// The exit status that the program at `path` should exit with
+pub fn expected_status(path: &Path) -> Result<i32, String> {
+    let status = read_sidecar(path, STATUS_EXTENSION)?;
+    if status.trim().is_empty() {
+        return Ok(0);
+    }
+    status
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid exit status {:?}: {}", status.trim(), e))
+}
+
+/// The runs of the program at `path`: one with its `.in` and `.out` files, which is left out
+/// if it only has an `.io` file, and one for each line of its `.io` file
+pub fn program_runs(path: &Path) -> Result<Vec<ProgramRun>, String> {
+    let mut runs = vec![];
+    let has_io = path.with_extension(IO_EXTENSION).exists();
+    let has_in_out = [INPUT_EXTENSION, OUTPUT_EXTENSION]
+        .iter()
+        .any(|extension| path.with_extension(extension).exists());
+    if has_in_out || !has_io {
+        runs.push(ProgramRun {
+            input: read_sidecar(path, INPUT_EXTENSION)?,
+            expected: read_sidecar(path, OUTPUT_EXTENSION)?,
+        });
+    }
+    let io = read_sidecar(path, IO_EXTENSION)?;
+    for line in io.lines().filter(|line| !line.trim().is_empty()) {
+        let mut run = ProgramRun {
+            input: String::new(),
+            expected: String::new(),
+        };
+        for entry in line.trim().split(',').filter(|entry| !entry.is_empty()) {
+            let (lines, value) = match entry.split_at(1) {
+                ("i", value) => (&mut run.input, value),
+                ("o", value) => (&mut run.expected, value),
+                _ => return Err(format!("Invalid entry `{}` in the .io file", entry)),
+            };
+            lines.push_str(value);
+            lines.push('\n');
+        }
+        runs.push(run);
+    }
+    Ok(runs)
+}
+
+/// Reads the sidecar of the test at `path` with the given extension, or nothing if there is none
+pub fn read_sidecar(path: &Path, extension: &str) -> Result<String, String> {
+    let sidecar = path.with_extension(extension);
+    if !sidecar.exists() {
+        return Ok(String::new());
+    }
+    std::fs::read_to_string(&sidecar)
+        .map_err(|e| format!("Could not read {}: {}", sidecar.display(), e))
+}
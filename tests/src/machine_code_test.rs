@@ -0,0 +1,185 @@
+use intermediate_code::ic_generator::OptLevel;
+use machine_code::reg_alloc::AllocStrategy;
+use machine_code::toolchain::Emit;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use syntax::{Builtin, BuiltinRegistry, ReturnType};
+use tests::{
+    collect_tests_in_path, expected_status, program_runs, read_sidecar, Expectation, ProgramRun,
+    Test, TestStats, HOSTED_EXTENSION, IO_EXTENSION, LINK_EXTENSION,
+};
+
+const PROGRAM_TEST_PATH: &str = "tests/testfiles/general/programs";
+const UNIT_TEST_PATH: &str = "tests/testfiles/general/units";
+const BUILD_DIR: &str = "cminus-machine-tests";
+
+/// Compiles and links the program in `test` into `build_dir`,
+/// runs it once for each of its runs and compares the output
+fn run_program(
+    test: &Test,
+    build_dir: &Path,
+    strategy: AllocStrategy,
+    freestanding: bool,
+) -> Result<(), String> {
+    let mut exe_name = test.path.file_stem().unwrap().to_string_lossy().to_string();
+    if let AllocStrategy::LinearScan = strategy {
+        exe_name += "-linear";
+    }
+    if freestanding {
+        exe_name += "-freestanding";
+    }
+    let exe_path = build_dir.join(exe_name);
+    let test_dir = test.path.parent().unwrap();
+    let linked = read_sidecar(&test.path, LINK_EXTENSION)?
+        .lines()
+        .map(|file| test_dir.join(file).to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    let inputs = std::iter::once(test.path.to_str().unwrap())
+        .chain(linked.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    machine_code::compile_files(
+        &inputs,
+        &[],
+        exe_path.to_str(),
+        OptLevel::None,
+        strategy,
+        Emit::Executable,
+        false,
+        false,
+        freestanding,
+        &BuiltinRegistry::default(),
+    )?;
+    let status = expected_status(&test.path)?;
+    for run in program_runs(&test.path)? {
+        run_executable(&exe_path, &run, status)?;
+    }
+    Ok(())
+}
+
+/// Runs the executable at `exe_path` with the input of `run` and compares its output
+fn run_executable(exe_path: &Path, run: &ProgramRun, status: i32) -> Result<(), String> {
+    let mut child = Command::new(exe_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run {}: {}", exe_path.display(), e))?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(run.input.as_bytes())
+        .map_err(|e| format!("Could not write to stdin: {}", e))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Could not wait for {}: {}", exe_path.display(), e))?;
+    if output.status.code() != Some(status) {
+        return Err(format!(
+            "Expected exit status {}, but the program exited with {}",
+            status, output.status
+        ));
+    }
+    let actual = String::from_utf8_lossy(&output.stdout);
+    if actual != run.expected {
+        return Err(format!(
+            "Expected output:\n{}\nActual output:\n{}",
+            run.expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Compiles a program that calls a builtin registered by the embedder and checks its output
+fn run_registered_builtin(build_dir: &Path) -> Result<(), String> {
+    let source = build_dir.join("registered_builtin.c");
+    std::fs::write(
+        &source,
+        "int main(void) { writeinteger(triple(7)); return 0; }\n",
+    )
+    .map_err(|e| format!("Could not write {}: {}", source.display(), e))?;
+    let mut builtins = BuiltinRegistry::default();
+    builtins.register(Builtin {
+        assembly: Some("\t.globl triple\ntriple:\n\tleal\t(%rdi,%rdi,2), %eax\n\tret\n".into()),
+        ..Builtin::new("triple", ReturnType::Int, &[("i", ReturnType::Int)])
+    });
+    let exe_path = build_dir.join("registered_builtin");
+    machine_code::compile_files(
+        &[source.to_str().unwrap()],
+        &[],
+        exe_path.to_str(),
+        OptLevel::None,
+        AllocStrategy::GraphColoring,
+        Emit::Executable,
+        false,
+        false,
+        false,
+        &builtins,
+    )?;
+    let output = Command::new(&exe_path)
+        .output()
+        .map_err(|e| format!("Could not run {}: {}", exe_path.display(), e))?;
+    let actual = String::from_utf8_lossy(&output.stdout);
+    if actual != "21\n" {
+        return Err(format!(
+            "Expected output:\n21\n\nActual output:\n{}",
+            actual
+        ));
+    }
+    Ok(())
+}
+
+pub fn run() -> io::Result<()> {
+    let mut stats = TestStats {
+        total: 0,
+        success: 0,
+    };
+    let build_dir = std::env::temp_dir().join(BUILD_DIR);
+    std::fs::create_dir_all(&build_dir)?;
+    let program_tests = collect_tests_in_path(PROGRAM_TEST_PATH)?.into_iter();
+    // Unit tests with an `.io` file are run like programs
+    let unit_tests = collect_tests_in_path(UNIT_TEST_PATH)?
+        .into_iter()
+        .filter(|t| t.path.with_extension(IO_EXTENSION).exists());
+
+    // Linked files are compiled as part of the program that links them
+    for test in program_tests
+        .chain(unit_tests)
+        .filter(|t| t.expectation == Expectation::Success && !t.linked)
+    {
+        // Programs should behave the same with either allocator, and with and without libc
+        let hosted = test.path.with_extension(HOSTED_EXTENSION).exists();
+        for strategy in [AllocStrategy::GraphColoring, AllocStrategy::LinearScan] {
+            for freestanding in [false, true] {
+                if freestanding && hosted {
+                    continue;
+                }
+                stats.total += 1;
+                println!();
+                if freestanding {
+                    log::info!("Running test {} ({:?}, freestanding)", test.name, strategy);
+                } else {
+                    log::info!("Running test {} ({:?})", test.name, strategy);
+                }
+                match run_program(&test, &build_dir, strategy, freestanding) {
+                    Ok(()) => {
+                        log::info!("↪    PASSED");
+                        stats.success += 1;
+                    }
+                    Err(e) => log::error!("↪   FAILED\n{}", e),
+                }
+            }
+        }
+    }
+    stats.total += 1;
+    println!();
+    log::info!("Running test with a registered builtin");
+    match run_registered_builtin(&build_dir) {
+        Ok(()) => {
+            log::info!("↪    PASSED");
+            stats.success += 1;
+        }
+        Err(e) => log::error!("↪   FAILED\n{}", e),
+    }
+    log::info!("[{} / {}] TESTS PASSED", stats.success, stats.total);
+    Ok(())
+}
@@ -1,6 +1,6 @@
 pub mod intermediate_code_test;
 pub mod lexical_test;
-pub mod lib;
+pub mod machine_code_test;
 pub mod syntax_test;
 
 use clap::clap_app;
@@ -12,7 +12,7 @@ fn main() -> io::Result<()> {
     let matches = clap_app!(myapp =>
         (version: "1.0")
         (about: "Produce an abstract syntax tree for the given input C-minus file")
-        (@arg TESTS: +required "Sets the test(s) to run. One or more of `lexical`, `syntax`, `intermediate`")
+        (@arg TESTS: +required "Sets the test(s) to run. One or more of `lexical`, `syntax`, `intermediate`, `machine`")
         (@arg verbose: -v --verbose "Print debug information")
     )
     .get_matches();
@@ -27,6 +27,7 @@ fn main() -> io::Result<()> {
             "lexical" => lexical_test::run()?,
             "syntax" => syntax_test::run()?,
             "intermediate" => intermediate_code_test::run()?,
+            "machine" => machine_code_test::run()?,
             _ => log::error!("No such test {}", test),
         }
     }
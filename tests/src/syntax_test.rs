@@ -1,5 +1,6 @@
+use std::error::Error;
 use std::io;
-use syntax::{NodeType, SyntaxAnalysisResult, SyntaxNode};
+use syntax::{BuiltinRegistry, Linkage, NodeType, SyntaxAnalysisResult, SyntaxNode};
 use tests::{collect_tests_in_path, run_single_test, TestStats};
 
 const PROGRAM_TEST_PATH: &str = "tests/testfiles/general/programs";
@@ -8,7 +9,7 @@ const SYNTAX_TEST_PATH: &str = "tests/testfiles/syntax";
 
 mod specific_tests {
     use itertools::{self, EitherOrBoth, Itertools};
-    use syntax::{SyntaxNode, SyntaxNodeBox};
+    use syntax::{BuiltinRegistry, SyntaxNode, SyntaxNodeBox};
 
     const GLOBAL_PREFIX: &str = "tests/testfiles/general/units/";
     fn read_to_string(path: &str) -> String {
@@ -19,11 +20,7 @@ mod specific_tests {
         expectation: impl IntoIterator<Item = &'a str>,
         actual: impl IntoIterator<Item = SyntaxNodeBox>,
     ) -> bool {
-        for (idx, pair) in expectation
-            .into_iter()
-            .zip_longest(actual.into_iter())
-            .enumerate()
-        {
+        for (idx, pair) in expectation.into_iter().zip_longest(actual).enumerate() {
             match pair {
                 EitherOrBoth::Both(l, r) => {
                     if l != (*r.borrow()).to_string() {
@@ -55,7 +52,7 @@ mod specific_tests {
             let test_path = GLOBAL_PREFIX.to_owned() + PREFIX + "correct/simple_func.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let table = result.symbol_table;
@@ -67,7 +64,7 @@ mod specific_tests {
                 GLOBAL_PREFIX.to_owned() + PREFIX + "correct/simple_func_param/simple_func_param.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let table = result.symbol_table;
@@ -82,7 +79,7 @@ mod specific_tests {
                 GLOBAL_PREFIX.to_owned() + PREFIX + "correct/simple_var_assign/simple_var_assign.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let table = result.symbol_table;
@@ -101,7 +98,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "if_coercion.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -142,7 +139,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "while_coercion.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -179,6 +176,45 @@ mod specific_tests {
             assert!(syntax_similar(expectation, tree));
             true
         }
+
+        /// An explicit cast is a coercion too, and only a narrowing one is recorded
+        #[rustfmt::skip]
+        pub fn explicit_cast() -> bool {
+            let test_path = PREFIX.to_owned() + "cast.c";
+            log::info!("Running test {}", test_path);
+            let input = read_to_string(&test_path);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
+            assert!(result.is_ok());
+            let result = result.unwrap();
+            let narrowing_lines = result.narrowing_casts.iter().map(|(_, span)| span.line);
+            assert!(narrowing_lines.eq([5]));
+            let main = result.tree.get_func_by_name("main").unwrap();
+            assert!(main.tree.is_some());
+            let tree = SyntaxNode::preorder(main.tree.as_ref().unwrap());
+            let expectation = [
+                "statement_list - void",
+                    "assignment - int",
+                        "symbol - int",
+                        "coercion - int",
+                            "num - 300",
+                "statement_list - void",
+                    "assignment - uint8",
+                        "symbol - uint8",
+                        "coercion - uint8",
+                            "symbol - int",
+                "statement_list - void",
+                    "assignment - int",
+                        "symbol - int",
+                        "coercion - int",
+                            "symbol - uint8",
+                "statement_list - void",
+                    "return - int",
+                        "coercion - int",
+                            "num - 0"
+            ];
+            assert!(syntax_similar(expectation, tree));
+            true
+        }
     }
 
     pub mod node {
@@ -190,7 +226,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "statementlist_empty.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -205,7 +241,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "statementlist_funccall.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -232,7 +268,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "assignment.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -258,7 +294,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "if_targets.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -302,7 +338,7 @@ mod specific_tests {
             let test_path = PREFIX.to_owned() + "array.c";
             log::info!("Running test {}", test_path);
             let input = read_to_string(&test_path);
-            let result = syntax::generate(&input);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
             assert!(result.is_ok());
             let result = result.unwrap();
             let main = result.tree.get_func_by_name("main").unwrap();
@@ -326,28 +362,163 @@ mod specific_tests {
         }
     }
 
-    pub const ALL_TESTS: [fn() -> bool; 10] = [
+    pub mod node_loop {
+        use super::*;
+        const PREFIX: &str = "tests/testfiles/syntax/node/correct/";
+
+        #[rustfmt::skip]
+        pub fn for_loop() -> bool {
+            let test_path = PREFIX.to_owned() + "for.c";
+            log::info!("Running test {}", test_path);
+            let input = read_to_string(&test_path);
+            let result = syntax::generate(&input, &BuiltinRegistry::default());
+            assert!(result.is_ok());
+            let result = result.unwrap();
+            let main = result.tree.get_func_by_name("main").unwrap();
+            assert!(main.tree.is_some());
+            let tree = SyntaxNode::preorder(main.tree.as_ref().unwrap());
+            let expectation = [
+                "statement_list - void",
+                    // init
+                    "statement_list - void",
+                        "assignment - int",
+                            "symbol - int",
+                            "coercion - int",
+                                "num - 0",
+                    "statement_list - void",
+                        "for - void",
+                            "rel_lt - bool",
+                                "symbol - int",
+                                "coercion - int",
+                                    "num - 3",
+                            "for_targets - void",
+                                // body
+                                "statement_list - void",
+                                    "if - void",
+                                        "rel_equal - bool",
+                                            "symbol - int",
+                                            "coercion - int",
+                                                "num - 1",
+                                        "continue - void",
+                                "statement_list - void",
+                                    "break - void",
+                                // step
+                                "assignment - int",
+                                    "symbol - int",
+                                    "add - int",
+                                        "symbol - int",
+                                        "coercion - int",
+                                            "num - 1",
+                "statement_list - void",
+                    "return - int",
+                        "coercion - int",
+                            "num - 0"
+            ];
+            assert!(syntax_similar(expectation, tree));
+            true
+        }
+    }
+
+    pub mod builtins {
+        use super::*;
+        use syntax::{Builtin, ReturnType};
+
+        const INPUT: &str = "int main(void) { writeinteger(abs(-2)); return 0; }";
+
+        pub fn registered() -> bool {
+            log::info!("Running test builtins::registered");
+            let mut builtins = BuiltinRegistry::default();
+            builtins.register(Builtin::new(
+                "abs",
+                ReturnType::Int,
+                &[("i", ReturnType::Int)],
+            ));
+            let result = syntax::generate(INPUT, &builtins).unwrap();
+            assert!(result.errors.is_empty());
+            assert!(result.symbol_table.has_function("abs"));
+            assert!(result.symbol_table.has_parameter("abs", "i"));
+            assert!(result.tree.get_func_by_name("abs").is_none());
+
+            let result = syntax::generate(INPUT, &BuiltinRegistry::default()).unwrap();
+            assert_eq!(result.errors.len(), 1);
+            true
+        }
+    }
+
+    pub mod spans {
+        use super::*;
+        use syntax::Source;
+
+        const INPUT: &str = "int main(void) {\r\n    /* a\n comment */ int x;\n    x = 1 +\n        x;\n    return y;\n}\n";
+
+        pub fn located() -> bool {
+            log::info!("Running test spans::located");
+            let source = Source {
+                name: "spans.c",
+                text: INPUT,
+            };
+            let result = syntax::generate(source, &BuiltinRegistry::default()).unwrap();
+            assert_eq!(result.errors.len(), 1);
+            let (err, span) = &result.errors[0];
+            assert!(err.to_string().contains("`y`"));
+            assert_eq!((span.line, span.column), (6, 12));
+            assert_eq!(&INPUT[span.bytes.clone()], "y");
+            assert_eq!(span.to_string(), "spans.c:6:12");
+
+            let table = &result.symbol_table;
+            let main_id = table.get_main_id().unwrap();
+            let locals = table.get_func_var_symbols(&main_id).unwrap();
+            let (_, x) = locals.iter().find(|(_, s)| s.name.0 == "x").unwrap();
+            assert_eq!((x.span.line, x.span.column), (3, 17));
+
+            let main = result.tree.get_func_by_name("main").unwrap();
+            let tree = SyntaxNode::preorder(main.tree.as_ref().unwrap());
+            let sum = tree
+                .into_iter()
+                .find(|node| node.borrow().to_string().starts_with("add"))
+                .unwrap();
+            let span = sum.borrow().span();
+            assert_eq!((span.line, span.column), (4, 9));
+            assert_eq!(&INPUT[span.bytes], "1 +\n        x");
+            true
+        }
+    }
+
+    pub const ALL_TESTS: [fn() -> bool; 14] = [
         declaration::simple_func,
         declaration::simple_func_param,
         declaration::simple_var_assign,
         node_coercion::if_coerce,
         node_coercion::while_coerce,
+        node_coercion::explicit_cast,
         node::statementlist_empty,
         node::statementlist_functioncall,
         node::assignment,
         node::if_targets,
         node::array,
+        node_loop::for_loop,
+        builtins::registered,
+        spans::located,
     ];
 }
 
 pub fn test_function(input: &str) -> Result<(), &str> {
-    let result = syntax::generate(input);
+    check_result(syntax::generate(input, &BuiltinRegistry::default()))
+}
+
+/// Like [test_function], for a file that is linked into a program and has no `main`
+pub fn linked_test_function(input: &str) -> Result<(), &str> {
+    check_result(syntax::generate_linked(input, &BuiltinRegistry::default()))
+}
+
+fn check_result(result: Result<SyntaxAnalysisResult, Box<dyn Error>>) -> Result<(), &'static str> {
     if let Err(err) = result {
         log::error!("{}", err);
         return Err("Error occurred");
     }
     let SyntaxAnalysisResult {
         tree,
+        symbol_table,
         errors,
         warnings,
         ..
@@ -356,6 +527,10 @@ pub fn test_function(input: &str) -> Result<(), &str> {
         return Err("Errors present");
     }
     for (id, func) in tree.functions {
+        // `extern` functions are defined in another file
+        if func.tree.is_none() && symbol_table.get_linkage(&id) == Some(Linkage::Imported) {
+            continue;
+        }
         for node in SyntaxNode::preorder(&func.tree.ok_or_else(|| {
             log::error!("Rootless function {}", id);
             "Error occurred"
@@ -380,7 +555,12 @@ pub fn run() -> io::Result<()> {
 
     for test in unit_tests.chain(program_tests).chain(syntax_tests) {
         stats.total += 1;
-        if run_single_test(test, test_function).is_ok() {
+        let test_func = if test.linked {
+            linked_test_function
+        } else {
+            test_function
+        };
+        if run_single_test(test, test_func).is_ok() {
             stats.success += 1;
         }
     }
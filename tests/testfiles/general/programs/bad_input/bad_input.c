@@ -0,0 +1,14 @@
+/* Like `scanf`, reading a number that is not there gives 0 and leaves the input for `readchar`,
+   and reading past the end of the input gives 0 as well. */
+
+int main(void) {
+    int c;
+    writeinteger(readinteger());
+    writeinteger(readinteger());
+    c = readchar();
+    writechar((uint8_t) c);
+    writechar('\n');
+    writeinteger(readinteger());
+    writeinteger(readinteger());
+    return 0;
+}
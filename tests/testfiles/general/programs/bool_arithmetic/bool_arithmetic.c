@@ -0,0 +1,10 @@
+/* Like in C, bools are promoted to int in arithmetic */
+
+int main(void) {
+    writeinteger(!0 + !0);
+    writeinteger(-(1 < 2));
+    writeinteger((1 < 2) * 5 - !0);
+    writeinteger((2 > 1) << 3);
+    writeinteger((1 < 2) == !0);
+    return 0;
+}
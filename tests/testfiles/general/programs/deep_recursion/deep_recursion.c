@@ -0,0 +1,13 @@
+/* Recursion deeper than a few thousand calls is fine for a compiled program, so the interpreter
+   has to allow it too. */
+
+int sum(int n) {
+    if (n == 0)
+        return 0;
+    return n + sum(n - 1);
+}
+
+int main(void) {
+    writeinteger(sum(20000));
+    return 0;
+}
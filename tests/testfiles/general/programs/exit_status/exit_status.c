@@ -0,0 +1,15 @@
+/* Exits with the number of negative integers in its input, until a 0 */
+
+int main(void) {
+    int count;
+    int n;
+    count = 0;
+    n = readinteger();
+    while (n != 0) {
+        if (n < 0)
+            count++;
+        n = readinteger();
+    }
+    writeinteger(count);
+    return count;
+}
@@ -0,0 +1,15 @@
+/* Folded `!`, `&&`, `||` and comparisons are bools, whichever type their operands have */
+
+int main(void) {
+    int x;
+    x = !0 + (3 && 0);
+    writeinteger(x);
+    x = !5 * (2 < 3);
+    writeinteger(x);
+    x = (!0 == (5 && 2)) + (1.5 > 2.5);
+    writeinteger(x);
+    if (!1 || 4 >= 3) {
+        writeinteger(7);
+    }
+    return (!0 && 2 != 2) + 3;
+}
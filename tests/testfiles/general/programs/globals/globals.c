@@ -0,0 +1,21 @@
+/* Globals are initialized before `main` runs, with constant expressions */
+
+int count = 7;
+static int16_t offset = -(2 * 300);
+uint8_t wrapped = (uint8_t) 300;
+double scale = 2.5;
+int64_t big = 1 << 20, unset;
+
+void bump(void) {
+    count = count + offset;
+}
+
+int main(void) {
+    writeinteger(count);
+    bump();
+    writeinteger(count);
+    writeinteger(wrapped);
+    writeinteger((int) (scale * 4.0));
+    writeinteger64(big + unset);
+    return 0;
+}
@@ -0,0 +1,28 @@
+extern int stack_size;
+extern void push(int value);
+extern int pop(void);
+extern int pushed(void);
+
+static int total;
+
+static void add(int value) {
+    total += value;
+}
+
+int main(void) {
+    int i;
+    int n;
+    n = readinteger();
+    for (i = 0; i < n; i++) {
+        push(readinteger());
+    }
+    writeinteger(stack_size);
+    while (stack_size > 0) {
+        i = pop();
+        add(i);
+        writeinteger(i);
+    }
+    writeinteger(total);
+    writeinteger(pushed());
+    return 0;
+}
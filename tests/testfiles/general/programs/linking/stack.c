@@ -0,0 +1,21 @@
+// Linked into linking.c by its `.link` sidecar.
+// It has no `main`, so the tests check it as a linked file rather than as a program of its own.
+int stack_size;
+static int items[16];
+// Also defined in linking.c, but `static` keeps both local to their own file
+static int total;
+
+void push(int value) {
+    items[stack_size] = value;
+    stack_size++;
+    total++;
+}
+
+int pop(void) {
+    stack_size--;
+    return items[stack_size];
+}
+
+int pushed(void) {
+    return total;
+}
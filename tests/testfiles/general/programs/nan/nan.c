@@ -0,0 +1,42 @@
+/* Only `!=` holds when either operand is NaN, whether it is computed or constant */
+
+int main(void) {
+    double zero = 0.0;
+    double nan = zero / zero;
+    writeinteger(nan == 1.0);
+    writeinteger(nan != nan);
+    writeinteger(nan < 1.0);
+    writeinteger(nan <= 1.0);
+    writeinteger(nan > 1.0);
+    writeinteger(1.0 >= nan);
+    writeinteger(0.0 / 0.0 == 1.0);
+    writeinteger(0.0 / 0.0 != 0.0 / 0.0);
+    writeinteger(0.0 / 0.0 < 1.0);
+    writeinteger(1.0 <= 0.0 / 0.0);
+    if (nan == nan) {
+        writeinteger(1);
+    } else {
+        writeinteger(2);
+    }
+    if (nan != 1.0) {
+        writeinteger(3);
+    }
+    if (nan < 1.0) {
+        writeinteger(4);
+    } else {
+        writeinteger(5);
+    }
+    if (1.0 >= nan) {
+        writeinteger(6);
+    }
+    if (0.0 / 0.0 == 0.0 / 0.0) {
+        writeinteger(7);
+    }
+    if (0.0 / 0.0 != 1.0) {
+        writeinteger(8);
+    }
+    if (nan) {
+        writeinteger(9);
+    }
+    return 0;
+}
@@ -0,0 +1,24 @@
+/* Rotates 64-bit values by counts that are 64-bit variables themselves */
+
+uint64_t rotate_left(uint64_t x, uint64_t k) {
+    if (k == 0)
+        return x;
+    return (x << k) | (x >> (64 - k));
+}
+
+int main(void) {
+    uint64_t x = readunsigned64();
+    int64_t y = readinteger64();
+    uint64_t k = 0;
+    int64_t n = 1;
+    while (k < 64) {
+        writeunsigned64(rotate_left(x, k));
+        k = k + 20;
+    }
+    while (n < 64) {
+        writeinteger64(y >> n);
+        writeinteger64(y << n);
+        n = n * 4;
+    }
+    return 0;
+}
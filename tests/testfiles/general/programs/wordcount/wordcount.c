@@ -0,0 +1,44 @@
+/* Counts the lines, words and characters of its input, like `wc` */
+
+int is_space(int c) {
+    return c == ' ' || c == '\t' || c == '\n' || c == '\r';
+}
+
+void report(int count) {
+    writechar(' ');
+    writeinteger(count);
+}
+
+int main(void) {
+    int lines;
+    int words;
+    int chars;
+    int in_word;
+    int c;
+    lines = 0;
+    words = 0;
+    chars = 0;
+    in_word = 0;
+
+    c = readchar();
+    while (c != -1) {
+        chars++;
+        if (c == '\n')
+            lines++;
+        if (is_space(c))
+            in_word = 0;
+        else if (!in_word) {
+            in_word = 1;
+            words++;
+        }
+        c = readchar();
+    }
+
+    writestring("lines:");
+    report(lines);
+    writestring("words:");
+    report(words);
+    writestring("chars:");
+    report(chars);
+    return 0;
+}
@@ -0,0 +1,18 @@
+uint8_t flags[3];
+int squares[10];
+int8_t deltas[2];
+
+int main(void) {
+    int i;
+    i = 0;
+    while (i < 10) {
+        squares[i] = i * i;
+        i = i + 1;
+    }
+    flags[2] = 200;
+    deltas[1] = -5;
+    writeinteger(squares[9]);
+    writeunsigned(flags[2]);
+    writeinteger(deltas[1]);
+    return 0;
+}
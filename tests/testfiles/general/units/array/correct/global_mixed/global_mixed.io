@@ -0,0 +1 @@
+o81,o200,o-5,
\ No newline at end of file
@@ -0,0 +1,33 @@
+int primes[2 * 4] = {2, 3, 5, 7, 11};
+uint8_t bytes[4] = {250, -1};
+int empty[3];
+
+int sum(int values[], int n) {
+    int i;
+    int total;
+    total = 0;
+    for (i = 0; i < n; i++) {
+        total += values[i];
+    }
+    return total;
+}
+
+int main(void) {
+    int i;
+    int local[16 * 16] = {readinteger(), primes[4] * 2};
+    uint8_t flags[3] = {1};
+    writeinteger(sum(primes, 8));
+    writeinteger(primes[7]);
+    writeinteger(bytes[0] + bytes[1]);
+    writeinteger(bytes[3]);
+    writeinteger(sum(local, 256));
+    writeinteger(flags[0] + flags[2]);
+    for (i = 0; i < 3; i++) {
+        // Initialized on every iteration
+        int counts[2] = {i};
+        counts[1] += i;
+        writeinteger(counts[0] + counts[1]);
+    }
+    writeinteger(sum(empty, 3));
+    return 0;
+}
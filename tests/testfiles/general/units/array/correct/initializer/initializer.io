@@ -0,0 +1 @@
+i5,o28,o0,o249,o0,o27,o1,o0,o2,o4,o0,
\ No newline at end of file
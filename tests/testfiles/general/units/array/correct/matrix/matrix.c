@@ -0,0 +1,53 @@
+int g[3][4] = {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12};
+
+int trace(int m[][4], int n) {
+    int i;
+    int sum;
+    sum = 0;
+    for (i = 0; i < n; i++) {
+        sum += m[i][i];
+    }
+    return sum;
+}
+
+void multiply(int a[][3], int b[][3], int c[][3]) {
+    int i;
+    int j;
+    int k;
+    for (i = 0; i < 3; i++) {
+        for (j = 0; j < 3; j++) {
+            c[i][j] = 0;
+            for (k = 0; k < 3; k++) {
+                c[i][j] += a[i][k] * b[k][j];
+            }
+        }
+    }
+}
+
+int main(void) {
+    int a[3][3];
+    int b[3][3] = {2, 0, 0, 0, 2, 0, 0, 0, 2};
+    int c[3][3];
+    uint8_t cube[2][3][4];
+    int i;
+    unsigned int j2;
+    int j;
+    int n;
+    n = readinteger();
+    for (i = 0; i < 3; i++) {
+        for (j = 0; j < 3; j++) {
+            a[i][j] = n * j + i;
+        }
+    }
+    multiply(a, b, c);
+    writeinteger(c[2][1]);
+    writeinteger(trace(g, 3));
+    j2 = 3;
+    cube[1][2][j2] = 200;
+    cube[0][0][0] = 1;
+    writeinteger(cube[1][2][3] + cube[0][0][0]);
+    writeinteger(g[2][3]);
+    g[1][0]--;
+    writeinteger(g[1][0]);
+    return 0;
+}
@@ -0,0 +1 @@
+i5,o14,o18,o201,o12,o4,
\ No newline at end of file
@@ -0,0 +1,32 @@
+int calls;
+
+int next(void) {
+    calls += 1;
+    return calls;
+}
+
+int main(void) {
+    int a[4];
+    int x;
+
+    a[0] = 0;
+    a[1] = 10;
+    a[2] = 20;
+    a[3] = 30;
+    calls = 0;
+
+    /* Each index is evaluated exactly once */
+    a[next()] += 5; /* a[1] = 15 */
+    writeinteger(a[1]);
+    x = a[next()]++; /* a[2] = 21 */
+    writeinteger(x);
+    writeinteger(a[2]);
+    x = --a[next()]; /* a[3] = 29 */
+    writeinteger(x);
+    writeinteger(calls);
+
+    a[0] = 7;
+    a[a[0] - 7] *= a[0]; /* a[0] = 49 */
+    writeinteger(a[0]);
+    return 0;
+}
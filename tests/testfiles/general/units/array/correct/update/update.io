@@ -0,0 +1 @@
+o15,o20,o21,o29,o3,o49,
\ No newline at end of file
@@ -0,0 +1,35 @@
+int is_even(unsigned n);
+int is_odd(unsigned n);
+void fill(int a[], int n);
+
+int main(void) {
+    int a[4];
+    writeinteger(is_even(10));
+    writeinteger(is_odd(7));
+    writeinteger(is_even(7));
+    fill(a, 4);
+    writeinteger(a[3]);
+    return 0;
+}
+
+/* Parameter names may differ from the prototype */
+int is_even(unsigned x) {
+    if (x == 0) {
+        return 1;
+    }
+    return is_odd(x - 1);
+}
+
+int is_odd(unsigned x) {
+    if (x == 0) {
+        return 0;
+    }
+    return is_even(x - 1);
+}
+
+void fill(int a[], int n) {
+    int i;
+    for (i = 0; i < n; i++) {
+        a[i] = i * i;
+    }
+}
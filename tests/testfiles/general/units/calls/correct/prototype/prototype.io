@@ -0,0 +1 @@
+o1,o1,o0,o9,
\ No newline at end of file
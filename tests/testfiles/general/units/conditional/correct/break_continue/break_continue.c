@@ -0,0 +1,28 @@
+int main(void) {
+    int i;
+    int j;
+
+    for (i = 0; i < 10; i = i + 1) {
+        if (i % 2 == 0) continue;
+        if (i > 6) break;
+        writeinteger(i); /* 1, 3 and 5 */
+    }
+
+    i = 0;
+    while (1) {
+        i = i + 1;
+        for (j = 0;; j = j + 1) {
+            if (j == i) break; /* only leaves the inner loop */
+        }
+        if (j >= 3) break;
+        writeinteger(j); /* 1 and 2 */
+    }
+
+    i = 0;
+    do {
+        i = i + 1;
+        if (i == 2) continue; /* still checks the condition */
+        writeinteger(i); /* 1, 3 and 4 */
+    } while (i < 4);
+    return 0;
+}
@@ -0,0 +1 @@
+o1,o3,o5,o1,o2,o1,o3,o4,
\ No newline at end of file
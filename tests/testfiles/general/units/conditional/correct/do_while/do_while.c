@@ -0,0 +1,13 @@
+int main(void) {
+    int a;
+    a = 0;
+
+    do {
+        writeinteger(a); /* should run for 3 times */
+        a = a + 1;
+    } while (a < 3);
+
+    do writeinteger(a); /* should run for 1 time */
+    while (a < 0);
+    return 0;
+}
@@ -0,0 +1 @@
+o0,o1,o2,o3,
\ No newline at end of file
@@ -0,0 +1,11 @@
+int main(void) {
+    int a;
+    a = 0;
+
+    do ; while (0);
+    writeinteger(a);
+
+    do ; while (a++ < 3); /* the condition does the work */
+    writeinteger(a);
+    return 0;
+}
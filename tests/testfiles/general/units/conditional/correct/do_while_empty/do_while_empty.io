@@ -0,0 +1 @@
+o0,o4,
\ No newline at end of file
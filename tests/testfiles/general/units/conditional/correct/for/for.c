@@ -0,0 +1,23 @@
+int main(void) {
+    int a;
+    int total;
+
+    for (a = 0; a < 3; a = a + 1) {
+        writeinteger(a); /* should run for 3 times */
+    }
+
+    total = 0;
+    for (int i = 10; i > 0; i = i - 2) total = total + i;
+    writeinteger(total);
+
+    a = 5;
+    for (; a < 7;) {
+        writeinteger(a); /* should run for 2 times */
+        a = a + 1;
+    }
+
+    for (a = 0; a > 0; a = a + 1) {
+        writeinteger(0); /* should run for 0 times */
+    }
+    return 0;
+}
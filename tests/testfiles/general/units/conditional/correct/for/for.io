@@ -0,0 +1 @@
+o0,o1,o2,o30,o5,o6,
\ No newline at end of file
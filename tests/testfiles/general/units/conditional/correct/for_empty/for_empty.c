@@ -0,0 +1,13 @@
+int main(void) {
+    int i;
+    int n;
+    n = 5;
+
+    for (i = 0; i < n; i++) ; /* only counts */
+    writeinteger(i);
+
+    for (int j = 1; j < 100; j = j * 2)
+        ;
+    writeinteger(i * 2);
+    return 0;
+}
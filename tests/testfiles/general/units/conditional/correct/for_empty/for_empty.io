@@ -0,0 +1 @@
+o5,o10,
\ No newline at end of file
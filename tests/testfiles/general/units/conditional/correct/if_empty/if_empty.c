@@ -0,0 +1,16 @@
+int main(void) {
+    int a;
+    a = 1;
+
+    if (a) ;
+    else
+        writeinteger(0); /* should not run */
+
+    if (a < 0) ;
+    else
+        writeinteger(a);
+
+    while (a++ < 3) ;
+    writeinteger(a);
+    return 0;
+}
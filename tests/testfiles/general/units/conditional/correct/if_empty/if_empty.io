@@ -0,0 +1 @@
+o1,o4,
\ No newline at end of file
@@ -0,0 +1,15 @@
+static int total;
+static int history[4];
+
+static void add(int value) {
+    history[total % 4] = value;
+    total += value;
+}
+
+int main(void) {
+    add(readinteger());
+    add(readinteger());
+    writeinteger(total);
+    writeinteger(history[3]);
+    return 0;
+}
@@ -0,0 +1 @@
+i3,i4,o7,o4,
\ No newline at end of file
@@ -0,0 +1,27 @@
+int main(void) {
+    int a;
+    uint8_t b;
+    unsigned c;
+
+    a = 10;
+    a += 5; /* 15 */
+    writeinteger(a);
+    a -= 20; /* -5 */
+    writeinteger(a);
+    a *= -3; /* 15 */
+    writeinteger(a);
+    a /= 4; /* 3 */
+    writeinteger(a);
+    a %= 2; /* 1 */
+    writeinteger(a);
+    writeinteger(a += 1); /* 2, an assignment is an expression */
+
+    b = 250;
+    b += 10; /* 260 wraps around to 4 */
+    writeinteger(b);
+
+    c = 4294967295;
+    c /= 2; /* unsigned division: 2147483647 */
+    writeunsigned(c);
+    return 0;
+}
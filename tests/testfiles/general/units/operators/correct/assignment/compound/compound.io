@@ -0,0 +1 @@
+o15,o-5,o15,o3,o1,o2,o4,o2147483647,
\ No newline at end of file
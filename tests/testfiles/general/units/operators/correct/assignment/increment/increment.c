@@ -0,0 +1,33 @@
+int main(void) {
+    int i;
+    int x;
+    uint8_t b;
+
+    i = 5;
+    writeinteger(i++); /* 5 */
+    writeinteger(i); /* 6 */
+    writeinteger(++i); /* 7 */
+    writeinteger(i--); /* 7 */
+    writeinteger(--i); /* 5 */
+
+    i = 0;
+    x = i++ * 2 + 1; /* 0 * 2 + 1 */
+    writeinteger(x);
+    writeinteger(i); /* 1 */
+    writeinteger(-++i); /* -2 */
+    writeinteger(-i--); /* -2 */
+    writeinteger(i); /* 1 */
+
+    b = 255;
+    b++; /* wraps around to 0 */
+    writeinteger(b);
+    b--; /* and back to 255 */
+    writeinteger(b);
+
+    x = 0;
+    for (i = 0; i < 5; i++) {
+        x += i;
+    }
+    writeinteger(x); /* 10 */
+    return 0;
+}
@@ -0,0 +1 @@
+o5,o6,o7,o7,o5,o1,o1,o-2,o-2,o1,o0,o255,o10,
\ No newline at end of file
@@ -0,0 +1,19 @@
+int main(void) {
+    int a;
+    int b;
+    uint8_t c;
+
+    a = 12;
+    b = 10;
+    writeinteger(a & b); /* 8 */
+    writeinteger(a | b); /* 14 */
+    writeinteger(a ^ b); /* 6 */
+    writeinteger(-1 & a); /* 12 */
+
+    c = 240;
+    c = c | 15; /* 255 */
+    writeinteger(c);
+    c = c ^ 170; /* 85 */
+    writeinteger(c);
+    return 0;
+}
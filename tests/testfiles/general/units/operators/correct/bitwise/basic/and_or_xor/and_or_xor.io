@@ -0,0 +1 @@
+o8,o14,o6,o12,o255,o85,
\ No newline at end of file
@@ -0,0 +1,19 @@
+int main(void) {
+    int a;
+    uint8_t b;
+    unsigned c;
+
+    a = 5;
+    writeinteger(~a); /* -6 */
+    writeinteger(~~a); /* 5 */
+    writeinteger(~0); /* -1 */
+
+    b = 15;
+    b = ~b; /* 240 */
+    writeinteger(b);
+
+    c = 0;
+    c = ~c; /* 4294967295 */
+    writeunsigned(c);
+    return 0;
+}
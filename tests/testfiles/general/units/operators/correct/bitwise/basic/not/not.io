@@ -0,0 +1 @@
+o-6,o5,o-1,o240,o4294967295,
\ No newline at end of file
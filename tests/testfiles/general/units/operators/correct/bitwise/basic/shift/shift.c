@@ -0,0 +1,18 @@
+int main(void) {
+    int a;
+    int n;
+
+    a = 3;
+    n = 4;
+    writeinteger(a << 2); /* 12 */
+    writeinteger(a << n); /* 48 */
+    a = 1;
+    writeinteger(a << 31); /* -2147483648 */
+
+    a = -16;
+    writeinteger(a >> 2); /* -4, arithmetic shift keeps the sign */
+    n = 31;
+    writeinteger(a >> n); /* -1 */
+    writeinteger(-16 >> 2); /* -4 */
+    return 0;
+}
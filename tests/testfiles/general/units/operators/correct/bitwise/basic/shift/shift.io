@@ -0,0 +1 @@
+o12,o48,o-2147483648,o-4,o-1,o-4,
\ No newline at end of file
@@ -0,0 +1,18 @@
+int main(void) {
+    unsigned a;
+    uint8_t b;
+    int n;
+
+    a = 0;
+    a = ~a;
+    n = 28;
+    writeunsigned(a >> n); /* 15, logical shift fills with zeroes */
+    writeunsigned(a >> 31); /* 1 */
+
+    b = 200;
+    n = 3;
+    writeinteger(b >> n); /* 25 */
+    b = b << 1; /* 400 wraps around to 144 */
+    writeinteger(b);
+    return 0;
+}
@@ -0,0 +1 @@
+o15,o1,o25,o144,
\ No newline at end of file
@@ -0,0 +1,11 @@
+int main(void) {
+    int a;
+    a = 6;
+    writeinteger(1 | a & 3); /* 1 | (6 & 3) = 3 */
+    writeinteger(a ^ 3 | 8); /* (6 ^ 3) | 8 = 13 */
+    writeinteger(a & 3 ^ 1); /* (6 & 3) ^ 1 = 3 */
+    writeinteger(1 << 2 + 1); /* 1 << 3 = 8 */
+    writeinteger(a & 7 == 6); /* 6 & (7 == 6) = 0 */
+    writeinteger(a >> 1 < 4); /* (6 >> 1) < 4 = 1 */
+    return 0;
+}
@@ -0,0 +1 @@
+o3,o13,o3,o8,o0,o1,
\ No newline at end of file
@@ -0,0 +1,7 @@
+int main(void) {
+    int a; int b;
+    a = 1; b = 0;
+    if (a && b) { writeinteger(1); } else { writeinteger(2); }
+    while (a || b) { a = 0; writeinteger(3); }
+    return 0;
+}
@@ -0,0 +1,18 @@
+int main(void) {
+    int a;
+    int b;
+    int ans;
+
+    a = -17;
+    b = 5;
+
+    ans = a / b; /* truncates towards zero: -3 */
+    writeinteger(ans);
+    ans = a % b; /* sign follows the dividend: -2 */
+    writeinteger(ans);
+    ans = -a / -b; /* 17 / -5 = -3 */
+    writeinteger(ans);
+    ans = -a % -b; /* 17 % -5 = 2 */
+    writeinteger(ans);
+    return 0;
+}
@@ -0,0 +1 @@
+o-3,o-2,o-3,o2,
\ No newline at end of file
@@ -10,13 +10,13 @@ int main(void) {
     ans = +ans; /* -100 */
     writeinteger(ans);
 
-    ans = ---------ans; /* even number of minus signs, so 100 */
+    ans = - - - - - - - - - ans; /* even number of minus signs, so 100 */
     writeinteger(ans);
 
-    ans = ++-------+++ans; /* odd number of minus signs, so -100 */
+    ans = + + - - - - - - - + + + ans; /* odd number of minus signs, so -100 */
     writeinteger(ans);
 
-    ans = ++++++--+ans; /* even number of minus signs, so stays -100 */
+    ans = + + + + + + - - + ans; /* even number of minus signs, so stays -100 */
     writeinteger(ans);
     return 0;
 }
\ No newline at end of file
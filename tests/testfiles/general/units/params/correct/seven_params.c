@@ -0,0 +1,10 @@
+/* An odd number of stack parameters needs padding to keep the stack aligned */
+int last(int a, int b, int c, int d, int e, int f, int g) {
+    writeinteger(a); /* 1 */
+    return g;
+}
+
+int main(void) {
+    writeinteger(last(1, 2, 3, 4, 5, 6, 7)); /* 7 */
+    return 0;
+}
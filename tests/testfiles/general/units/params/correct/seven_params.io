@@ -0,0 +1 @@
+o1,o7,
\ No newline at end of file
@@ -0,0 +1,12 @@
+int main(void) {
+    uint8_t c;
+    c = 'a';
+    writeinteger(c);
+    writeinteger('\n');
+    writeinteger('z' - c);
+    writeinteger('\0');
+    c = c + 1;
+    writechar(c);
+    writechar('\n');
+    return 0;
+}
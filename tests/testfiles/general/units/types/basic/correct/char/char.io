@@ -0,0 +1 @@
+o97,o10,o25,o0,ob,
\ No newline at end of file
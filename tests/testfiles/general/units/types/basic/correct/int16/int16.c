@@ -0,0 +1,17 @@
+int main(void) {
+    int16_t a = 30000;
+    int16_t b = -2;
+    writeinteger(a);
+    a = a + a; /* Wraps around to 60000 - 2^16 */
+    writeinteger(a);
+    writeinteger(a / b);
+    writeinteger(a % 7);
+    writeinteger(a >> 4);
+    if (a < b) {
+        writeinteger(1);
+    }
+    b = readinteger16();
+    writeinteger16(b);
+    writeinteger16(b - a);
+    return 0;
+}
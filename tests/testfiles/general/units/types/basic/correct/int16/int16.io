@@ -0,0 +1 @@
+o30000,o-5536,o2768,o-6,o-346,o1,i-12345,o-12345,o-6809,
\ No newline at end of file
@@ -0,0 +1,23 @@
+int main(void) {
+    int64_t sum = readinteger64();
+    int64_t big = 5000000000;
+    int64_t hash = 0;
+    int i = 0;
+    writeinteger64(sum);
+    writeinteger64(big * 3);
+    writeinteger64(-big / 7);
+    writeinteger64(big % 7);
+    writeinteger64(big << 4);
+    writeinteger64(-big >> 33);
+    while (i < 20) {
+        hash = hash * 31 + i;
+        i++;
+    }
+    writeinteger64(hash);
+    sum = sum + big;
+    writeinteger64(sum);
+    if (sum > big) {
+        writeinteger64(1);
+    }
+    return 0;
+}
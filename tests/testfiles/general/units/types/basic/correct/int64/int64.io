@@ -0,0 +1 @@
+i123456789012,o123456789012,o15000000000,o-714285714,o2,o80000000000,o-1,o-1228927331426847734,o128456789012,o1,
\ No newline at end of file
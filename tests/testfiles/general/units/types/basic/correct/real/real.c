@@ -0,0 +1,28 @@
+double scale;
+
+int main(void) {
+    double a;
+    double b = 1.5;
+    scale = 2.5e-1;
+    a = readreal();
+    writereal(a);
+    writereal(a + b);
+    writereal(a - b);
+    writereal(a * b);
+    writereal(a / b);
+    writereal(-a * scale);
+    writereal(1.0 / 3);
+    writereal(.5e7);
+    writereal(123456789.0);
+    a += 0.25;
+    b++;
+    writereal(a);
+    writereal(b);
+    if (a > b) {
+        writeinteger(1);
+    }
+    if (a <= b || a == 3.0) {
+        writeinteger(0);
+    }
+    return 0;
+}
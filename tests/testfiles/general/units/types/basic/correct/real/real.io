@@ -0,0 +1 @@
+i3,o3,o4.5,o1.5,o4.5,o2,o-0.75,o0.333333,o5e+06,o1.23457e+08,o3.25,o2.5,o1,
\ No newline at end of file
@@ -0,0 +1,17 @@
+int main(void) {
+    uint16_t a = 65535;
+    uint16_t b = 40000;
+    writeunsigned(a);
+    a++; /* Should be equal to 0 */
+    writeunsigned(a);
+    writeunsigned(b / 3);
+    writeunsigned(b * 2);
+    writeunsigned(b >> 3);
+    if (b > 32767) {
+        writeunsigned(1);
+    }
+    b = readunsigned16();
+    writeunsigned16(b);
+    writeunsigned16(b + a);
+    return 0;
+}
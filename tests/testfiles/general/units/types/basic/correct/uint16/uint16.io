@@ -0,0 +1 @@
+o65535,o0,o13333,o14464,o5000,o1,i54321,o54321,o54321,
\ No newline at end of file
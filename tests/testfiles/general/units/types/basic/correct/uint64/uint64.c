@@ -0,0 +1,23 @@
+int main(void) {
+    uint64_t max = 18446744073709551615;
+    uint64_t hash = 14695981039346656037;
+    uint64_t input = readunsigned64();
+    int i = 0;
+    writeunsigned64(max);
+    writeunsigned64(max / 3);
+    writeunsigned64(max % 10);
+    writeunsigned64(max >> 60);
+    writeunsigned64(input);
+    while (i < 10) {
+        hash = hash ^ i;
+        hash = hash * 1099511628211;
+        i++;
+    }
+    writeunsigned64(hash);
+    if (max > input) {
+        writeunsigned64(1);
+    }
+    writereal(max);
+    writereal(input);
+    return 0;
+}
@@ -0,0 +1 @@
+i12345678901234567890,o18446744073709551615,o6148914691236517205,o5,o15,o12345678901234567890,o10952987118811563378,o1,o1.84467e+19,o1.23457e+19,
\ No newline at end of file
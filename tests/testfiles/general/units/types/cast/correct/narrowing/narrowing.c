@@ -0,0 +1,22 @@
+int main(void) {
+    int i = 300;
+    int n = -1;
+    int64_t h = 1234567890123;
+    uint64_t max = 18446744073709551615;
+    uint8_t b = (uint8_t) i;
+    writeinteger(b);
+    writeinteger((int8_t) i);
+    writeunsigned((unsigned) n);
+    writeinteger((int) (unsigned) n);
+    writeinteger((int16_t) 70000);
+    writeinteger((uint16_t) n);
+    writeinteger((int) h);
+    writeinteger64((int64_t) max);
+    writeinteger((uint8_t) (i * 2) + 1);
+    if ((uint8_t) (i - 44)) {
+        writeinteger(1);
+    } else {
+        writeinteger(0);
+    }
+    return 0;
+}
@@ -0,0 +1 @@
+o44,o44,o4294967295,o-1,o4464,o65535,o1912276171,o-1,o89,o0,
\ No newline at end of file
@@ -0,0 +1,12 @@
+int main(void) {
+    double d = readreal();
+    uint64_t max = 18446744073709551615;
+    writeinteger((int) d);
+    writeinteger((uint8_t) d);
+    writeunsigned64((uint64_t) 1.5e19);
+    d = 1.5e19;
+    writeunsigned64((uint64_t) d);
+    writereal((double) max);
+    writereal((double) 7 / 2);
+    return 0;
+}
@@ -0,0 +1 @@
+i-2.75,o-2,o254,o15000000000000000000,o15000000000000000000,o1.84467e+19,o3.5,
\ No newline at end of file
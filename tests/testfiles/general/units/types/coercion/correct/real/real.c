@@ -0,0 +1,34 @@
+double average(double values[], int n) {
+    double total = 0;
+    int i;
+    for (i = 0; i < n; i++) {
+        total += values[i];
+    }
+    return total / n;
+}
+
+double weigh(int a, double x, unsigned b, double y) {
+    return a * x + b * y;
+}
+
+int main(void) {
+    double values[4] = {1, 2, 4.5, -0.5};
+    int8_t small = -3;
+    unsigned big = 4000000000;
+    double r;
+    writereal(average(values, 4));
+    r = small;
+    writereal(r);
+    r = big;
+    writereal(r);
+    writereal(weigh(2, 0.5, 3, 0.25));
+    r = 0.0;
+    if (!r) {
+        writeinteger(1);
+    }
+    r = -0.001;
+    if (r) {
+        writeinteger(2);
+    }
+    return 0;
+}
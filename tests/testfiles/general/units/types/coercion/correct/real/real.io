@@ -0,0 +1 @@
+o1.75,o-3,o4e+09,o1.75,o1,o2,
\ No newline at end of file
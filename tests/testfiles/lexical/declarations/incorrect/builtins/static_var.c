@@ -0,0 +1,4 @@
+int main(void) {
+    int static;
+    return 0;
+}
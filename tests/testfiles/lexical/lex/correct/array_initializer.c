@@ -0,0 +1,7 @@
+int a[2 * 3] = {1, 2, 3};
+int b[2] = {};
+
+int main(void) {
+    int c[1] = {a[0] + b[1]};
+    return c[0];
+}
@@ -0,0 +1,11 @@
+int main(void) {
+    int done;
+    int format;
+    int iffy;
+    int breakpoint;
+    done = 1;
+    format = done;
+    iffy = format;
+    breakpoint = iffy;
+    return breakpoint;
+}
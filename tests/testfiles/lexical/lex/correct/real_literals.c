@@ -0,0 +1,9 @@
+int main(void) {
+    double a = 1.5;
+    double b = .25;
+    double c = 3.;
+    double d = 1e3;
+    double e = 2.5E-2;
+    writereal(a + b + c + d + e);
+    return 0;
+}
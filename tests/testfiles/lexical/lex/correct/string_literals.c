@@ -0,0 +1,9 @@
+int main(void) {
+    uint8_t c;
+    c = 'x';
+    c = '\'';
+    c = '\\';
+    writestring("A string with \"quotes\", a 'single' quote and a tab\t\n");
+    writestring("");
+    return 0;
+}
@@ -0,0 +1,16 @@
+int main(void) {
+    int a[2];
+    int i;
+    int j;
+    i = 0;
+    j = i+++i;
+    j = i - --i;
+    j = -++i;
+    a[i++] -= --a[j--];
+    i *= j /= 2;
+    j %= a[0] += 1;
+    for (i = 0; i < 2; ++i) {
+        j++;
+    }
+    return 0;
+}
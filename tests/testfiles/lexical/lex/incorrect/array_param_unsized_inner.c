@@ -0,0 +1,7 @@
+int first(int m[][]) {
+    return m[0][0];
+}
+
+int main(void) {
+    return 0;
+}
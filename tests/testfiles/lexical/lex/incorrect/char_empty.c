@@ -0,0 +1,5 @@
+int main(void) {
+    uint8_t c;
+    c = '';
+    return 0;
+}
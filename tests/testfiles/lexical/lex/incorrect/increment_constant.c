@@ -0,0 +1,5 @@
+int main(void) {
+    int a;
+    a = 5++;
+    return 0;
+}
@@ -0,0 +1,4 @@
+int main(void) {
+    writestring("This string never ends);
+    return 0;
+}
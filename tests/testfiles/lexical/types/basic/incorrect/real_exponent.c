@@ -0,0 +1,5 @@
+int main(void) {
+    double a = 1.5e+;
+    writereal(a);
+    return 0;
+}
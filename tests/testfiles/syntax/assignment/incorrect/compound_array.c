@@ -0,0 +1,5 @@
+int main(void) {
+    int a[4];
+    a += 1;
+    return 0;
+}
@@ -0,0 +1,8 @@
+int main(void) {
+    int a;
+    uint8_t b;
+    a = 3;
+    b = 1;
+    b += a;
+    return 0;
+}
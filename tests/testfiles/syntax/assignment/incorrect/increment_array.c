@@ -0,0 +1,5 @@
+int main(void) {
+    int a[4];
+    a++;
+    return 0;
+}
@@ -0,0 +1,9 @@
+int a[4 * 4 + 1];
+uint8_t b[(1 << 3) - 1] = {1, 2};
+
+int main(void) {
+    int c[16 * 16];
+    int d[-(-2)] = {a[0], b[1]};
+    c[255] = d[1];
+    return c[255];
+}
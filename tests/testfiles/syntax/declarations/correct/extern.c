@@ -0,0 +1,23 @@
+extern int counter;
+extern int buffer[8];
+extern int next(int step);
+extern void reset(void);
+extern int twice(int x);
+
+static int calls;
+
+static void count(void) {
+    calls++;
+}
+
+int twice(int x) {
+    count();
+    return x * 2;
+}
+
+int main(void) {
+    reset();
+    buffer[0] = next(1);
+    counter = twice(counter);
+    return calls;
+}
@@ -0,0 +1,13 @@
+extern int count;
+int count;
+
+extern int table[2][3];
+int table[2][3] = {1, 2, 3};
+
+int limit = 4;
+extern int limit;
+
+int main(void) {
+    count = table[0][2] + limit;
+    return count;
+}
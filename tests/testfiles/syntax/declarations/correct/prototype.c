@@ -0,0 +1,22 @@
+int sum(int a, int b);
+int sum(int x, int y);
+void show(uint8_t values[], unsigned n);
+
+int main(void) {
+    uint8_t values[2];
+    values[0] = 1;
+    values[1] = 2;
+    show(values, 2);
+    return sum(1, 2);
+}
+
+int sum(int a, int b) {
+    return a + b;
+}
+
+void show(uint8_t values[], unsigned n) {
+    unsigned i;
+    for (i = 0; i < n; i++) {
+        writeinteger(values[i]);
+    }
+}
@@ -0,0 +1,15 @@
+int one(void) {
+    return 1;
+}
+
+static int two(int x) {
+    return x + 1;
+}
+
+int one(void);
+int two(int y);
+extern int one(void);
+
+int main(void) {
+    return one() + two(1);
+}
@@ -0,0 +1,5 @@
+int main(void) {
+    int m[4][8];
+    m[1] = 2;
+    return m[1][2];
+}
@@ -0,0 +1,6 @@
+int n;
+int a[2] = {1, n};
+
+int main(void) {
+    return a[0];
+}
@@ -0,0 +1,4 @@
+int main(void) {
+    uint8_t a[2] = {1, 256};
+    return 0;
+}
@@ -0,0 +1,17 @@
+int sum(int m[][8], int rows) {
+    int i;
+    int j;
+    int total;
+    total = 0;
+    for (i = 0; i < rows; i++) {
+        for (j = 0; j < 8; j++) {
+            total += m[i][j];
+        }
+    }
+    return total;
+}
+
+int main(void) {
+    int m[8][4];
+    return sum(m, 8);
+}
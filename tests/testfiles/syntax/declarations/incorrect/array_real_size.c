@@ -0,0 +1,5 @@
+int main(void) {
+    int a[2.5];
+    a[0] = 1;
+    return 0;
+}
@@ -0,0 +1,6 @@
+int main(void) {
+    int n;
+    n = 4;
+    int a[n];
+    return 0;
+}
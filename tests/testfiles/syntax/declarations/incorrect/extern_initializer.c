@@ -0,0 +1,5 @@
+extern int limit = 10;
+
+int main(void) {
+    return limit;
+}
@@ -0,0 +1,6 @@
+extern int count;
+uint8_t count;
+
+int main(void) {
+    return 0;
+}
@@ -0,0 +1,6 @@
+int n;
+int gi = n + 1;
+
+int main(void) {
+    return gi;
+}
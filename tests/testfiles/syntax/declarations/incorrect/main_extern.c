@@ -0,0 +1,5 @@
+extern int main(void);
+
+int square(int x) {
+    return x * x;
+}
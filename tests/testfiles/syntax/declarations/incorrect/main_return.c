@@ -0,0 +1,3 @@
+double main(void) {
+    return 1.5;
+}
@@ -0,0 +1,3 @@
+static int main(void) {
+    return 0;
+}
@@ -0,0 +1,9 @@
+int one(void) {
+    return 1;
+}
+
+int one(int x);
+
+int main(void) {
+    return one();
+}
@@ -0,0 +1,9 @@
+int f(int a, uint8_t b[]);
+
+int main(void) {
+    return 0;
+}
+
+int f(int a, uint8_t b) {
+    return a + b;
+}
@@ -0,0 +1,9 @@
+void f(int a);
+
+int main(void) {
+    return 0;
+}
+
+int f(int a) {
+    return a;
+}
@@ -0,0 +1,5 @@
+int f(int a);
+
+int main(void) {
+    return f(1);
+}
@@ -0,0 +1,5 @@
+static int helper(int x);
+
+int main(void) {
+    return helper(1);
+}
@@ -0,0 +1,6 @@
+int main(void) {
+    if (1) {
+        break;
+    }
+    return 0;
+}
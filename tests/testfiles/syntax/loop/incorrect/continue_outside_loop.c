@@ -0,0 +1,10 @@
+void skip(void) {
+    continue;
+}
+
+int main(void) {
+    while (1) {
+        skip();
+    }
+    return 0;
+}
@@ -0,0 +1,8 @@
+int main(void) {
+    int i;
+    uint8_t b;
+    i = 300;
+    b = (uint8_t) i;
+    i = (int) b;
+    return 0;
+}
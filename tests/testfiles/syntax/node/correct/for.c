@@ -0,0 +1,7 @@
+int main(void) {
+    for (int i = 0; i < 3; i = i + 1) {
+        if (i == 1) continue;
+        break;
+    }
+    return 0;
+}
@@ -0,0 +1,7 @@
+int main(void) {
+    double a = 7.5;
+    double b;
+    b = a % 2;
+    writereal(b);
+    return 0;
+}
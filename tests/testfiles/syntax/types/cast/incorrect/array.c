@@ -0,0 +1,9 @@
+int main(void) {
+    int a[4];
+    int b;
+    b = (int) a;
+
+    writeinteger(b);
+
+    return 0;
+}
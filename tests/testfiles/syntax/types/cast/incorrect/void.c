@@ -0,0 +1,12 @@
+void f(void) {
+    return;
+}
+
+int main(void) {
+    int b;
+    b = (int) f();
+
+    writeinteger(b);
+
+    return 0;
+}
@@ -0,0 +1,8 @@
+int main(void) {
+    int a = 100000;
+    int16_t b = a;
+
+    writeinteger(b);
+
+    return 0;
+}
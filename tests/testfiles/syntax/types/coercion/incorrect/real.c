@@ -0,0 +1,8 @@
+int main(void) {
+    double a = 1.5;
+    int b = a;
+
+    writeinteger(b);
+
+    return 0;
+}
@@ -0,0 +1,8 @@
+int main(void) {
+    uint64_t a = 18446744073709551615;
+    int64_t b = a;
+
+    writeinteger64(b);
+
+    return 0;
+}
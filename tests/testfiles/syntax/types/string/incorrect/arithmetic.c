@@ -0,0 +1,4 @@
+int main(void) {
+    writestring("one" + "two");
+    return 0;
+}
@@ -0,0 +1,6 @@
+int main(void) {
+    int a;
+    a = "text";
+    writeinteger(a);
+    return 0;
+}
@@ -0,0 +1,4 @@
+int main(void) {
+    writechar("c");
+    return 0;
+}